@@ -0,0 +1,11 @@
+/// The error returned by [`Cache::put_stream`](crate::Cache::put_stream), saying whether the cache or the reader
+/// failed.
+#[derive(Debug, thiserror::Error)]
+pub enum StreamError<C> {
+	/// The driver failed to store the blob.
+	#[error(transparent)]
+	Cache(C),
+	/// The blob couldn't be read, so nothing was stored.
+	#[error("failed to read the blob: {0}")]
+	Read(std::io::Error),
+}
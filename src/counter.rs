@@ -0,0 +1,69 @@
+use crate::drivers::{Driver, Overflow};
+use std::time::Duration;
+
+/// A handle to a named, atomically-updated counter.
+///
+/// Counters live under the `counter:` namespace and are updated through the driver's atomic `increment` operation.
+#[derive(Debug)]
+pub struct Counter<'a, D: Driver> {
+	key: String,
+	driver: &'a mut D,
+}
+
+impl<'a, D: Driver> Counter<'a, D> {
+	pub(crate) const fn new(driver: &'a mut D, key: String) -> Self {
+		Self { key, driver }
+	}
+
+	/// The cache key the counter with the given name is stored under.
+	pub(crate) fn key(name: &str) -> String {
+		format!("counter:{name}")
+	}
+
+	/// Increment the counter, returning its new value.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to update the counter.
+	pub async fn inc(&mut self, by: i64) -> Result<i64, D::Error> {
+		self.driver.increment(&self.key, by, None).await
+	}
+
+	/// Increment the counter, expiring it after the given duration if it didn't exist yet.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to update the counter.
+	pub async fn inc_with_expiry(&mut self, by: i64, expiry: Duration) -> Result<i64, D::Error> {
+		self.driver.increment(&self.key, by, Some(expiry)).await
+	}
+
+	/// Decrement the counter, returning its new value.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to update the counter, or if it would overflow.
+	pub async fn dec(&mut self, by: i64) -> Result<i64, D::Error> {
+		let by = by.checked_neg().ok_or(Overflow)?;
+
+		self.driver.increment(&self.key, by, None).await
+	}
+
+	/// Retrieve the current value of the counter, or zero if it doesn't exist (without creating it).
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to read the counter.
+	pub async fn get(&self) -> Result<i64, D::Error> {
+		Ok(self.driver.get(&self.key).await?.unwrap_or_default())
+	}
+
+	/// Reset the counter back to zero.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to remove the counter.
+	pub async fn reset(&mut self) -> Result<(), D::Error> {
+		self.driver.forget(&self.key).await.map(drop)
+	}
+}
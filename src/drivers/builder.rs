@@ -0,0 +1,44 @@
+/// Generate a `ConfigBuilder` for the `Config` of the module it's called in, with a setter for each listed field.
+///
+/// The builder starts from the config's `Default`, so fields that aren't set keep their default value and new fields
+/// can be added without breaking existing builders.
+macro_rules! config_builder {
+	($(<$($param:ident: $bound:path),+>)? $($(#[$attr:meta])* $field:ident: $ty:ty),+ $(,)?) => {
+		/// A builder for [`Config`], starting from its defaults.
+		#[must_use]
+		pub struct ConfigBuilder$(<$($param: $bound),+>)? {
+			config: Config$(<$($param),+>)?,
+		}
+
+		impl$(<$($param: $bound),+>)? Config$(<$($param),+>)? {
+			/// Start building a config from the defaults.
+			pub fn builder() -> ConfigBuilder$(<$($param),+>)?
+			where
+				Self: Default,
+			{
+				ConfigBuilder {
+					config: Self::default(),
+				}
+			}
+		}
+
+		impl$(<$($param: $bound),+>)? ConfigBuilder$(<$($param),+>)? {
+			$(
+				$(#[$attr])*
+				#[doc = concat!("Set [`Config::", stringify!($field), "`].")]
+				pub fn $field(mut self, $field: impl Into<$ty>) -> Self {
+					self.config.$field = $field.into();
+
+					self
+				}
+			)+
+
+			/// Finish building the config.
+			#[must_use]
+			#[allow(clippy::missing_const_for_fn)]
+			pub fn build(self) -> Config$(<$($param),+>)? {
+				self.config
+			}
+		}
+	};
+}
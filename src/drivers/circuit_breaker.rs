@@ -0,0 +1,562 @@
+#[cfg(feature = "format")]
+use super::Format;
+use super::{Driver, EntryMeta, GetOutcome, PoolStatus, Ttl};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	collections::VecDeque,
+	fmt,
+	sync::{Arc, Mutex, MutexGuard, PoisonError},
+	time::{Duration, Instant, SystemTime},
+};
+
+pub struct Config<D: Driver> {
+	/// The config for the driver calls go to while the circuit is closed.
+	pub inner: D::Config,
+	/// How many failures within `window` open the circuit.
+	pub failure_threshold: usize,
+	/// How far back failures are counted.
+	pub window: Duration,
+	/// How long the circuit stays open before a single call is let through to probe the backend.
+	pub cooldown: Duration,
+}
+
+config_builder! {
+	<D: Driver>
+	inner: D::Config,
+	failure_threshold: usize,
+	window: Duration,
+	cooldown: Duration,
+}
+
+impl<D: Driver> fmt::Debug for Config<D>
+where
+	D::Config: fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Config")
+			.field("inner", &self.inner)
+			.field("failure_threshold", &self.failure_threshold)
+			.field("window", &self.window)
+			.field("cooldown", &self.cooldown)
+			.finish()
+	}
+}
+
+impl<D: Driver> Default for Config<D>
+where
+	D::Config: Default,
+{
+	fn default() -> Self {
+		Self {
+			inner: D::Config::default(),
+			failure_threshold: 5,
+			window: Duration::from_secs(10),
+			cooldown: Duration::from_secs(10),
+		}
+	}
+}
+
+/// The state of a [`CircuitBreakerDriver`]'s circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+	/// Calls go to the inner driver.
+	Closed,
+	/// Too many calls failed recently, so calls aren't made at all until the cooldown is over.
+	Open,
+	/// The cooldown is over, and the next call probes whether the backend has recovered.
+	HalfOpen,
+}
+
+#[derive(Debug)]
+struct Breaker {
+	failure_threshold: usize,
+	window: Duration,
+	cooldown: Duration,
+	/// When each failure within the window happened, oldest first.
+	failures: VecDeque<Instant>,
+	/// Until when the circuit is open, or `None` if it's closed. Once this has passed, the circuit is half-open.
+	open_until: Option<Instant>,
+	/// When the call probing a half-open circuit started, if one is in flight.
+	probing_since: Option<Instant>,
+}
+
+impl Breaker {
+	fn state(&self, now: Instant) -> CircuitState {
+		match self.open_until {
+			None => CircuitState::Closed,
+			Some(until) if now < until => CircuitState::Open,
+			Some(_) => CircuitState::HalfOpen,
+		}
+	}
+
+	/// Whether a call should go to the inner driver.
+	fn allow(&mut self, now: Instant) -> bool {
+		match self.state(now) {
+			CircuitState::Closed => true,
+			CircuitState::Open => false,
+			CircuitState::HalfOpen => {
+				// Only one call probes the backend at a time, unless the probe was dropped before it finished.
+				if self
+					.probing_since
+					.is_some_and(|since| now.duration_since(since) < self.cooldown)
+				{
+					return false;
+				}
+
+				self.probing_since = Some(now);
+				true
+			},
+		}
+	}
+
+	/// Record the outcome of a call that was allowed through.
+	fn record(&mut self, failed: bool, now: Instant) {
+		if !failed {
+			// A successful probe closes the circuit again.
+			if self.probing_since.is_some() {
+				self.open_until = None;
+				self.probing_since = None;
+				self.failures.clear();
+			}
+
+			return;
+		}
+
+		if self.open_until.is_some() {
+			// The probe failed, so the backend gets another cooldown.
+			self.open_until = Some(now + self.cooldown);
+			self.probing_since = None;
+
+			return;
+		}
+
+		self.failures.push_back(now);
+		while self
+			.failures
+			.front()
+			.is_some_and(|failed_at| now.duration_since(*failed_at) > self.window)
+		{
+			self.failures.pop_front();
+		}
+
+		if self.failures.len() >= self.failure_threshold {
+			self.open_until = Some(now + self.cooldown);
+			self.failures.clear();
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+/// A driver that stops calling another driver for a while once its backend keeps failing.
+///
+/// Once `failure_threshold` calls fail within `window` because the backend couldn't be reached (see
+/// [`Driver::is_unavailable`]), the circuit opens: for the next `cooldown`, calls don't reach the inner driver at
+/// all, so requests don't pile up waiting on a degraded backend. Reads are misses, and writes and removals do nothing
+/// (and report that nothing was stored or removed). After the cooldown, a single call probes the backend, closing the
+/// circuit if it succeeds or opening it for another cooldown if it fails.
+///
+/// Failed calls that do reach the backend still return their error, so wrap this in a
+/// [`FallbackDriver`](super::FallbackDriver) to treat those as misses too. Clones share the same circuit.
+pub struct CircuitBreakerDriver<D: Driver> {
+	inner: D,
+	breaker: Arc<Mutex<Breaker>>,
+}
+
+impl<D: Driver> CircuitBreakerDriver<D> {
+	/// The driver calls go to while the circuit is closed.
+	pub const fn inner(&self) -> &D {
+		&self.inner
+	}
+
+	/// The current state of the circuit.
+	pub fn state(&self) -> CircuitState {
+		self.breaker().state(Instant::now())
+	}
+
+	fn breaker(&self) -> MutexGuard<'_, Breaker> {
+		self.breaker.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Whether a call should go to the inner driver.
+	fn allow(&self) -> bool {
+		self.breaker().allow(Instant::now())
+	}
+
+	/// Record the outcome of a call that was allowed through.
+	fn record<T>(&self, result: Result<T, D::Error>) -> Result<T, D::Error> {
+		let failed = result.as_ref().is_err_and(D::is_unavailable);
+		self.breaker().record(failed, Instant::now());
+
+		result
+	}
+}
+
+impl<D: Driver> Driver for CircuitBreakerDriver<D> {
+	type Error = D::Error;
+	type Config = Config<D>;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		Ok(Self {
+			inner: D::new(config.inner).await?,
+			breaker: Arc::new(Mutex::new(Breaker {
+				failure_threshold: config.failure_threshold,
+				window: config.window,
+				cooldown: config.cooldown,
+				failures: VecDeque::new(),
+				open_until: None,
+				probing_since: None,
+			})),
+		})
+	}
+
+	fn prefix(&self) -> &str {
+		self.inner.prefix()
+	}
+
+	fn pool_status(&self) -> Option<PoolStatus> {
+		self.inner.pool_status()
+	}
+
+	fn is_unavailable(error: &Self::Error) -> bool {
+		D::is_unavailable(error)
+	}
+
+	#[cfg(feature = "format")]
+	fn format(&self) -> Option<Format> {
+		self.inner.format()
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		if !self.allow() {
+			return Ok(None);
+		}
+
+		self.record(self.inner.get(key).await)
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		if !self.allow() {
+			return Ok(GetOutcome::Miss);
+		}
+
+		self.record(self.inner.try_get(key).await)
+	}
+
+	async fn get_many<T: DeserializeOwned + Send>(
+		&self,
+		keys: &[&str],
+	) -> Result<Vec<Option<T>>, Self::Error> {
+		if !self.allow() {
+			return Ok(keys.iter().map(|_| None).collect());
+		}
+
+		self.record(self.inner.get_many(keys).await)
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		if !self.allow() {
+			return Ok(false);
+		}
+
+		self.record(self.inner.has(key).await)
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		if !self.allow() {
+			return Ok(None);
+		}
+
+		self.record(self.inner.ttl(key).await)
+	}
+
+	async fn metadata(&self, key: &str) -> Result<Option<EntryMeta>, Self::Error> {
+		if !self.allow() {
+			return Ok(None);
+		}
+
+		self.record(self.inner.metadata(key).await)
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		if !self.allow() {
+			return Ok(Vec::new());
+		}
+
+		self.record(self.inner.keys(prefix).await)
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		if !self.allow() {
+			return Ok(());
+		}
+
+		let result = self.inner.put(key, value, expiry).await;
+		self.record(result)
+	}
+
+	async fn put_until<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		deadline: SystemTime,
+	) -> Result<(), Self::Error> {
+		if !self.allow() {
+			return Ok(());
+		}
+
+		let result = self.inner.put_until(key, value, deadline).await;
+		self.record(result)
+	}
+
+	async fn put_many<T: Serialize + Sync>(
+		&mut self,
+		entries: &[(&str, &T)],
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		if !self.allow() {
+			return Ok(());
+		}
+
+		let result = self.inner.put_many(entries, expiry).await;
+		self.record(result)
+	}
+
+	async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		expiry: Option<Duration>,
+	) -> Result<T, Self::Error> {
+		if !self.allow() {
+			return Ok(value);
+		}
+
+		let result = self.inner.get_or_put(key, value, expiry).await;
+		self.record(result)
+	}
+
+	async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<Option<T>, Self::Error> {
+		if !self.allow() {
+			return Ok(None);
+		}
+
+		let result = self.inner.replace(key, value, expiry).await;
+		self.record(result)
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		if !self.allow() {
+			return Ok(false);
+		}
+
+		let result = self.inner.add(key, value, expiry).await;
+		self.record(result)
+	}
+
+	async fn compare_and_swap<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+		new: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		if !self.allow() {
+			return Ok(false);
+		}
+
+		let result = self
+			.inner
+			.compare_and_swap(key, expected, new, expiry)
+			.await;
+		self.record(result)
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		// Like a counter that didn't exist yet, since there's no way to know its value.
+		if !self.allow() {
+			return Ok(by);
+		}
+
+		let result = self.inner.increment(key, by, expiry).await;
+		self.record(result)
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		if !self.allow() {
+			return Ok(false);
+		}
+
+		let result = self.inner.touch(key, expiry).await;
+		self.record(result)
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		if !self.allow() {
+			return Ok(false);
+		}
+
+		let result = self.inner.forget(key).await;
+		self.record(result)
+	}
+
+	async fn forget_many(&mut self, keys: &[&str]) -> Result<(), Self::Error> {
+		if !self.allow() {
+			return Ok(());
+		}
+
+		let result = self.inner.forget_many(keys).await;
+		self.record(result)
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		if !self.allow() {
+			return Ok(());
+		}
+
+		let result = self.inner.forget_prefix(prefix).await;
+		self.record(result)
+	}
+
+	async fn forget_if<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+	) -> Result<bool, Self::Error> {
+		if !self.allow() {
+			return Ok(false);
+		}
+
+		let result = self.inner.forget_if(key, expected).await;
+		self.record(result)
+	}
+
+	async fn purge_expired(&mut self) -> Result<usize, Self::Error> {
+		if !self.allow() {
+			return Ok(0);
+		}
+
+		let result = self.inner.purge_expired().await;
+		self.record(result)
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		if !self.allow() {
+			return Ok(());
+		}
+
+		let result = self.inner.flush().await;
+		self.record(result)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn breaker() -> Breaker {
+		Breaker {
+			failure_threshold: 3,
+			window: Duration::from_secs(10),
+			cooldown: Duration::from_secs(5),
+			failures: VecDeque::new(),
+			open_until: None,
+			probing_since: None,
+		}
+	}
+
+	#[test]
+	fn test_circuit_opens_after_threshold() {
+		let mut breaker = breaker();
+		let start = Instant::now();
+
+		// Failures that fall out of the window don't count.
+		breaker.record(true, start);
+		breaker.record(true, start + Duration::from_secs(11));
+		breaker.record(true, start + Duration::from_secs(12));
+		assert_eq!(
+			breaker.state(start + Duration::from_secs(12)),
+			CircuitState::Closed
+		);
+
+		let now = start + Duration::from_secs(13);
+		breaker.record(true, now);
+		assert_eq!(breaker.state(now), CircuitState::Open);
+		assert!(!breaker.allow(now));
+	}
+
+	#[test]
+	fn test_circuit_half_open_probe() {
+		let mut breaker = breaker();
+		let start = Instant::now();
+		for _ in 0..3 {
+			breaker.record(true, start);
+		}
+
+		// Once the cooldown is over, only one call probes the backend.
+		let now = start + Duration::from_secs(5);
+		assert_eq!(breaker.state(now), CircuitState::HalfOpen);
+		assert!(breaker.allow(now));
+		assert!(!breaker.allow(now));
+
+		// A failed probe opens the circuit for another cooldown.
+		breaker.record(true, now);
+		assert_eq!(breaker.state(now), CircuitState::Open);
+
+		// A successful one closes it.
+		let now = now + Duration::from_secs(5);
+		assert!(breaker.allow(now));
+		breaker.record(false, now);
+		assert_eq!(breaker.state(now), CircuitState::Closed);
+		assert!(breaker.allow(now));
+	}
+
+	#[cfg(feature = "redis")]
+	#[tokio::test]
+	async fn test_circuit_breaker_driver() {
+		use crate::{
+			drivers::{redis, RedisDriver},
+			Cache,
+		};
+
+		// Nothing listens on port 1, so connecting is refused right away.
+		let mut cache = Cache::<CircuitBreakerDriver<RedisDriver>>::new(Config {
+			inner: redis::Config {
+				redis_url: "redis://127.0.0.1:1".to_string(),
+				..Default::default()
+			},
+			failure_threshold: 2,
+			..Default::default()
+		})
+		.await
+		.unwrap();
+
+		assert!(cache.get::<String>("foo").await.is_err());
+		assert!(cache.put("foo", &"bar", None).await.is_err());
+		assert_eq!(cache.driver.state(), CircuitState::Open);
+
+		// Calls don't reach Redis anymore.
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+		cache.put("foo", &"bar", None).await.unwrap();
+	}
+}
@@ -0,0 +1,402 @@
+use super::{format::Raw, Driver, EntryMeta, GetOutcome, Overflow, PoolStatus, Ttl};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	fmt,
+	io::{self, Read, Write},
+	time::{Duration, SystemTime},
+};
+
+/// The header byte for values stored as they were serialized.
+const UNCOMPRESSED: u8 = 0;
+/// The header byte for values compressed with gzip.
+const GZIP: u8 = 1;
+
+pub struct Config<D: Driver> {
+	/// The config for the driver that stores the (possibly compressed) values.
+	pub inner: D::Config,
+	/// The size (in bytes) a serialized value needs to reach before it's compressed.
+	/// Smaller values are stored as-is, since compressing them rarely saves enough to be worth it.
+	pub threshold: usize,
+}
+
+config_builder! {
+	<D: Driver>
+	inner: D::Config,
+	threshold: usize,
+}
+
+impl<D: Driver> fmt::Debug for Config<D>
+where
+	D::Config: fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Config")
+			.field("inner", &self.inner)
+			.field("threshold", &self.threshold)
+			.finish()
+	}
+}
+
+impl<D: Driver> Default for Config<D>
+where
+	D::Config: Default,
+{
+	fn default() -> Self {
+		Self {
+			inner: D::Config::default(),
+			threshold: 1024,
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+/// A driver that gzip-compresses large values before handing them to another driver.
+///
+/// Values are serialized into bytes before reaching the inner driver, prefixed with a header byte saying whether
+/// they're compressed, so entries written through this driver can only be read back through it. Counters are stored
+/// the same way, so [`increment`](Driver::increment) reads and rewrites the value instead of being atomic.
+pub struct CompressedDriver<D: Driver> {
+	inner: D,
+	threshold: usize,
+}
+
+impl<D: Driver> CompressedDriver<D> {
+	/// Serialize a value, compressing it if it's large enough.
+	///
+	/// The bytes are wrapped in [`Raw`], so drivers that store values through a [`Format`](super::Format) keep them
+	/// as-is instead of serializing them a second time.
+	fn encode<T: Serialize>(&self, value: &T) -> Result<Raw, Error<D::Error>> {
+		let data = bitcode::serialize(value)?;
+
+		if data.len() < self.threshold {
+			let mut encoded = Vec::with_capacity(data.len() + 1);
+			encoded.push(UNCOMPRESSED);
+			encoded.extend_from_slice(&data);
+
+			return Ok(Raw(encoded));
+		}
+
+		let mut encoder = GzEncoder::new(vec![GZIP], Compression::default());
+		encoder.write_all(&data)?;
+
+		Ok(Raw(encoder.finish()?))
+	}
+
+	/// Decompress (if needed) and deserialize a stored value.
+	fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T, Error<D::Error>> {
+		match data.split_first() {
+			Some((&UNCOMPRESSED, data)) => Ok(bitcode::deserialize(data)?),
+			Some((&GZIP, data)) => {
+				let mut decompressed = Vec::new();
+				GzDecoder::new(data).read_to_end(&mut decompressed)?;
+
+				Ok(bitcode::deserialize(&decompressed)?)
+			},
+			Some((&header, _)) => Err(Error::UnknownHeader(header)),
+			None => Err(Error::Compression(io::ErrorKind::UnexpectedEof.into())),
+		}
+	}
+}
+
+impl<D: Driver> Driver for CompressedDriver<D> {
+	type Error = Error<D::Error>;
+	type Config = Config<D>;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		Ok(Self {
+			threshold: config.threshold,
+			inner: D::new(config.inner).await.map_err(Error::Driver)?,
+		})
+	}
+
+	fn prefix(&self) -> &str {
+		self.inner.prefix()
+	}
+
+	fn pool_status(&self) -> Option<PoolStatus> {
+		self.inner.pool_status()
+	}
+
+	fn is_unavailable(error: &Self::Error) -> bool {
+		matches!(error, Error::Driver(error) if D::is_unavailable(error))
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		let Some(Raw(data)) = self.inner.get::<Raw>(key).await.map_err(Error::Driver)? else {
+			return Ok(None);
+		};
+
+		Ok(Some(Self::decode(&data)?))
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		Ok(
+			match self
+				.inner
+				.try_get::<Raw>(key)
+				.await
+				.map_err(Error::Driver)?
+			{
+				GetOutcome::Hit(Raw(data)) => {
+					Self::decode(&data).map_or(GetOutcome::Corrupt(data), GetOutcome::Hit)
+				},
+				GetOutcome::Miss => GetOutcome::Miss,
+				GetOutcome::Corrupt(data) => GetOutcome::Corrupt(data),
+			},
+		)
+	}
+
+	async fn get_many<T: DeserializeOwned + Send>(
+		&self,
+		keys: &[&str],
+	) -> Result<Vec<Option<T>>, Self::Error> {
+		let mut values = Vec::with_capacity(keys.len());
+		for data in self
+			.inner
+			.get_many::<Raw>(keys)
+			.await
+			.map_err(Error::Driver)?
+		{
+			values.push(data.map(|Raw(data)| Self::decode(&data)).transpose()?);
+		}
+
+		Ok(values)
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		self.inner.has(key).await.map_err(Error::Driver)
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		self.inner.ttl(key).await.map_err(Error::Driver)
+	}
+
+	async fn metadata(&self, key: &str) -> Result<Option<EntryMeta>, Self::Error> {
+		self.inner.metadata(key).await.map_err(Error::Driver)
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		self.inner.keys(prefix).await.map_err(Error::Driver)
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		let data = self.encode(value)?;
+
+		self.inner
+			.put(key, &data, expiry)
+			.await
+			.map_err(Error::Driver)
+	}
+
+	async fn put_until<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		deadline: SystemTime,
+	) -> Result<(), Self::Error> {
+		let data = self.encode(value)?;
+
+		self.inner
+			.put_until(key, &data, deadline)
+			.await
+			.map_err(Error::Driver)
+	}
+
+	async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		expiry: Option<Duration>,
+	) -> Result<T, Self::Error> {
+		let data = self.encode(&value)?;
+		let cached = self
+			.inner
+			.get_or_put(key, data.clone(), expiry)
+			.await
+			.map_err(Error::Driver)?;
+
+		if cached == data {
+			return Ok(value);
+		}
+
+		Self::decode(&cached.0)
+	}
+
+	async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<Option<T>, Self::Error> {
+		let data = self.encode(value)?;
+		let previous = self
+			.inner
+			.replace::<Raw>(key, &data, expiry)
+			.await
+			.map_err(Error::Driver)?;
+
+		previous.map(|Raw(data)| Self::decode(&data)).transpose()
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		let data = self.encode(value)?;
+
+		self.inner
+			.add(key, &data, expiry)
+			.await
+			.map_err(Error::Driver)
+	}
+
+	async fn compare_and_swap<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+		new: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		// Like `forget_if`, this relies on equal values being compressed to equal bytes.
+		let expected = self.encode(expected)?;
+		let new = self.encode(new)?;
+
+		self.inner
+			.compare_and_swap(key, &expected, &new, expiry)
+			.await
+			.map_err(Error::Driver)
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		// Keep the remaining lifetime of an existing counter, since the expiry only applies when it's created.
+		let (value, expiry) = match self.inner.ttl(key).await.map_err(Error::Driver)? {
+			None => (by, expiry),
+			Some(ttl) => (
+				self.get::<i64>(key)
+					.await?
+					.unwrap_or_default()
+					.checked_add(by)
+					.ok_or(Overflow)?,
+				match ttl {
+					Ttl::Forever => None,
+					Ttl::In(remaining) => Some(remaining),
+				},
+			),
+		};
+
+		self.put(key, &value, expiry).await?;
+
+		Ok(value)
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		self.inner.touch(key, expiry).await.map_err(Error::Driver)
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		self.inner.forget(key).await.map_err(Error::Driver)
+	}
+
+	async fn forget_many(&mut self, keys: &[&str]) -> Result<(), Self::Error> {
+		self.inner.forget_many(keys).await.map_err(Error::Driver)
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		self.inner
+			.forget_prefix(prefix)
+			.await
+			.map_err(Error::Driver)
+	}
+
+	async fn forget_if<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+	) -> Result<bool, Self::Error> {
+		// Compression is deterministic, so equal values are stored as equal bytes.
+		let expected = self.encode(expected)?;
+
+		self.inner
+			.forget_if(key, &expected)
+			.await
+			.map_err(Error::Driver)
+	}
+
+	async fn purge_expired(&mut self) -> Result<usize, Self::Error> {
+		self.inner.purge_expired().await.map_err(Error::Driver)
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		self.inner.flush().await.map_err(Error::Driver)
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error<E> {
+	#[error(transparent)]
+	Driver(E),
+	#[error(transparent)]
+	Serialization(#[from] bitcode::Error),
+	#[error("failed to compress or decompress value: {0}")]
+	Compression(#[from] io::Error),
+	#[error("unknown compression header: {0}")]
+	UnknownHeader(u8),
+	#[error(transparent)]
+	Overflow(#[from] Overflow),
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+	use super::*;
+	use crate::drivers::MemoryDriver;
+
+	#[tokio::test]
+	async fn test_compressed_driver() {
+		let mut driver = CompressedDriver::<MemoryDriver>::new(Config::default())
+			.await
+			.unwrap();
+
+		let value = "amnesia ".repeat(128 * 1024);
+		driver.put("large", &value, None).await.unwrap();
+		assert!(driver.inner.current_bytes() < value.len() / 10);
+		assert_eq!(driver.get::<String>("large").await.unwrap(), Some(value));
+
+		driver.put("small", &"bar", None).await.unwrap();
+		assert_eq!(
+			driver.inner.get::<Raw>("small").await.unwrap().unwrap().0[0],
+			UNCOMPRESSED
+		);
+		assert_eq!(
+			driver.get::<String>("small").await.unwrap(),
+			Some("bar".to_string())
+		);
+
+		assert!(driver.forget_if("small", &"bar".to_string()).await.unwrap());
+		assert!(!driver.has("small").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_compressed_increment() {
+		let mut driver = CompressedDriver::<MemoryDriver>::new(Config::default())
+			.await
+			.unwrap();
+
+		assert_eq!(driver.increment("hits", 2, None).await.unwrap(), 2);
+		assert_eq!(driver.increment("hits", 3, None).await.unwrap(), 5);
+		assert_eq!(driver.get::<i64>("hits").await.unwrap(), Some(5));
+	}
+}
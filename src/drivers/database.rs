@@ -1,8 +1,30 @@
-use super::Driver;
-use ensemble::{types::DateTime, Model};
+use super::{format, Driver, Format, GetOutcome, Overflow, Ttl};
+use ensemble::{query::Builder, types::DateTime, value, Model};
 use serde::{de::DeserializeOwned, Serialize};
 use std::time::Duration;
 
+#[derive(Debug, Clone)]
+pub struct Config {
+	/// The table cache entries are stored in. Only letters, digits and underscores are allowed.
+	pub table: String,
+	/// A prefix prepended to every key, so several caches can share a table.
+	pub prefix: String,
+}
+
+config_builder! {
+	table: String,
+	prefix: String,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			table: "cache".to_string(),
+			prefix: String::new(),
+		}
+	}
+}
+
 #[derive(Debug, Model)]
 #[ensemble(table = "cache")]
 struct CacheEntry {
@@ -12,21 +34,101 @@ struct CacheEntry {
 	pub expiration: Option<DateTime>,
 }
 
+#[derive(Debug, Clone)]
 #[allow(clippy::module_name_repetitions)]
 /// A driver that stores cache entries in a database.
-pub struct DatabaseDriver;
+///
+/// Values are always stored as JSON, since the `value` column holds text.
+///
+/// Expirations are stored with sub-second precision, so the `expiration` column should keep at least milliseconds
+/// (like `DATETIME(3)` on `MySQL`) for sub-second expiries to be honored. Values are written with
+/// `INSERT ... ON DUPLICATE KEY UPDATE`, so the driver requires `MySQL` (or a compatible database).
+pub struct DatabaseDriver {
+	table: String,
+	prefix: String,
+}
+
+impl DatabaseDriver {
+	/// Start a query against the configured table.
+	fn query(&self) -> Builder {
+		CacheEntry::query().from(&self.table)
+	}
+
+	/// Start a query for a single (prefixed) key.
+	fn query_key(&self, key: &str) -> Builder {
+		self.query()
+			.r#where("key", '=', format!("{}{key}", self.prefix))
+	}
+
+	/// Store an entry in the configured table, replacing any existing one in the same query.
+	async fn upsert(
+		&self,
+		key: &str,
+		data: String,
+		expiration: Option<DateTime>,
+	) -> Result<(), Error> {
+		let bindings = [
+			value::for_db(format!("{}{key}", self.prefix)),
+			value::for_db(data),
+			value::for_db(expiration),
+		]
+		.into_iter()
+		.collect::<Result<Vec<_>, _>>()
+		.map_err(ensemble::Error::from)?;
+
+		// SAFETY: The table name is checked when the driver is created, and every value is passed as a binding.
+		unsafe {
+			Builder::raw_sql(
+				&format!(
+					"INSERT INTO `{}` (`key`, `value`, `expiration`) VALUES (?, ?, ?) ON DUPLICATE KEY UPDATE `value` = VALUES(`value`), `expiration` = VALUES(`expiration`)",
+					self.table
+				),
+				bindings,
+			)
+			.await?;
+		}
+
+		Ok(())
+	}
+}
 
 impl Driver for DatabaseDriver {
-	type Config = ();
+	type Config = Config;
 	type Error = Error;
 
-	async fn new((): Self::Config) -> Result<Self, Self::Error> {
-		Ok(Self)
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		// The table name ends up in the SQL itself (queries can't bind identifiers), so it's restricted to characters
+		// that can't escape it.
+		if config.table.is_empty()
+			|| !config
+				.table
+				.chars()
+				.all(|c| c.is_ascii_alphanumeric() || c == '_')
+		{
+			return Err(Error::InvalidTable(config.table));
+		}
+
+		Ok(Self {
+			table: config.table,
+			prefix: config.prefix,
+		})
+	}
+
+	fn prefix(&self) -> &str {
+		&self.prefix
+	}
+
+	fn is_unavailable(error: &Self::Error) -> bool {
+		matches!(error, Error::Database(ensemble::Error::Connection(_)))
+	}
+
+	fn format(&self) -> Option<Format> {
+		Some(Format::Json)
 	}
 
 	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
-		let Some(entry) = CacheEntry::query()
-			.r#where("key", '=', key)
+		let Some(entry) = self
+			.query_key(key)
 			.where_group(|query| {
 				query
 					.where_null("expiration")
@@ -38,20 +140,91 @@ impl Driver for DatabaseDriver {
 			return Ok(None);
 		};
 
-		Ok(Some(serde_json::from_str::<T>(&entry.value)?))
+		Ok(Some(Format::Json.deserialize::<T>(entry.value.as_bytes())?))
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		let Some(entry) = self
+			.query_key(key)
+			.where_group(|query| {
+				query
+					.where_null("expiration")
+					.or_where("expiration", '>', DateTime::now())
+			})
+			.first::<CacheEntry>()
+			.await?
+		else {
+			return Ok(GetOutcome::Miss);
+		};
+
+		Ok(Format::Json
+			.deserialize(entry.value.as_bytes())
+			.map_or_else(
+				|_| GetOutcome::Corrupt(entry.value.into_bytes()),
+				GetOutcome::Hit,
+			))
 	}
 
 	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
-		let count = CacheEntry::query()
-			.r#where("key", '=', key)
-			.where_null("expiration")
-			.or_where("expiration", '>', DateTime::now())
+		let count = self
+			.query_key(key)
+			.where_group(|query| {
+				query
+					.where_null("expiration")
+					.or_where("expiration", '>', DateTime::now())
+			})
 			.count()
 			.await?;
 
 		Ok(count != 0)
 	}
 
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		let Some(entry) = self
+			.query_key(key)
+			.where_group(|query| {
+				query
+					.where_null("expiration")
+					.or_where("expiration", '>', DateTime::now())
+			})
+			.first::<CacheEntry>()
+			.await?
+		else {
+			return Ok(None);
+		};
+
+		Ok(Some(entry.expiration.map_or(Ttl::Forever, |expiration| {
+			let remaining =
+				expiration.unix_timestamp_nano() - DateTime::now().unix_timestamp_nano();
+
+			Ttl::In(Duration::from_nanos(
+				u64::try_from(remaining).unwrap_or_default(),
+			))
+		})))
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		let entries = self
+			.query()
+			.r#where(
+				"key",
+				"like",
+				like_prefix(&format!("{}{}", self.prefix, prefix.unwrap_or_default())),
+			)
+			.where_group(|query| {
+				query
+					.where_null("expiration")
+					.or_where("expiration", '>', DateTime::now())
+			})
+			.get::<CacheEntry>()
+			.await?;
+
+		Ok(entries
+			.into_iter()
+			.filter_map(|entry| entry.key.strip_prefix(&self.prefix).map(str::to_string))
+			.collect())
+	}
+
 	async fn put<T: Serialize + Sync>(
 		&mut self,
 		key: &str,
@@ -60,44 +233,186 @@ impl Driver for DatabaseDriver {
 	) -> Result<(), Self::Error> {
 		let expiration = duration.map(|duration| DateTime::now() + duration);
 
-		// TODO: This should be a single query.
-		CacheEntry::query()
-			.r#where("key", '=', key)
+		self.upsert(key, to_json(value)?, expiration).await
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		duration: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		// Expired entries would make the insert fail, so they're cleared first.
+		self.query_key(key)
+			.r#where("expiration", "<=", DateTime::now())
 			.delete()
 			.await?;
 
-		CacheEntry::create(CacheEntry {
-			expiration,
-			key: key.to_string(),
-			value: serde_json::to_string(value)?,
-		})
-		.await?;
+		let entry = CacheEntry {
+			key: format!("{}{key}", self.prefix),
+			value: to_json(value)?,
+			expiration: duration.map(|duration| DateTime::now() + duration),
+		};
 
-		Ok(())
+		// The primary key makes the insert fail if the entry already exists, so only one caller can create it.
+		match self
+			.query()
+			.insert::<String, _>(value::for_db(&entry).map_err(ensemble::Error::from)?)
+			.await
+		{
+			Ok(_) => Ok(true),
+			Err(error) if is_duplicate_key(&error) => Ok(false),
+			Err(error) => Err(error.into()),
+		}
 	}
 
-	async fn forget(&mut self, key: &str) -> Result<(), Self::Error> {
-		CacheEntry::query()
-			.r#where("key", '=', key)
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		let now = DateTime::now();
+		let bindings = [
+			value::for_db(format!("{}{key}", self.prefix)),
+			value::for_db(by.to_string()),
+			value::for_db(expiry.map(|expiry| now.clone() + expiry)),
+			value::for_db(&now),
+			value::for_db(&now),
+		]
+		.into_iter()
+		.collect::<Result<Vec<_>, _>>()
+		.map_err(ensemble::Error::from)?;
+
+		// Adding to the stored value in the same statement that creates it keeps concurrent increments from being lost.
+		// An expired entry starts over from `by`, with the new expiry.
+		// SAFETY: The table name is checked when the driver is created, and every value is passed as a binding.
+		unsafe {
+			Builder::raw_sql(
+				&format!(
+					"INSERT INTO `{}` (`key`, `value`, `expiration`) VALUES (?, ?, ?) ON DUPLICATE KEY UPDATE `value` = IF(`expiration` IS NULL OR `expiration` > ?, CAST(`value` AS SIGNED) + CAST(VALUES(`value`) AS SIGNED), VALUES(`value`)), `expiration` = IF(`expiration` IS NULL OR `expiration` > ?, `expiration`, VALUES(`expiration`))",
+					self.table
+				),
+				bindings,
+			)
+			.await
+			.map_err(|error| {
+				if is_out_of_range(&error) {
+					Error::Overflow(Overflow)
+				} else {
+					error.into()
+				}
+			})?;
+		}
+
+		// MySQL can't return the updated row, so it's read back. Increments made in between are included in the result.
+		Ok(self.get(key).await?.unwrap_or(by))
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		let expiration = expiry.map(|expiry| DateTime::now() + expiry);
+
+		let updated = self
+			.query_key(key)
+			.where_group(|query| {
+				query
+					.where_null("expiration")
+					.or_where("expiration", '>', DateTime::now())
+			})
+			.update(vec![("expiration", expiration)])
+			.await?;
+
+		Ok(updated != 0)
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		let deleted = self.query_key(key).delete().await?;
+
+		Ok(deleted != 0)
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		self.query()
+			.r#where(
+				"key",
+				"like",
+				like_prefix(&format!("{}{prefix}", self.prefix)),
+			)
 			.delete()
 			.await?;
 
 		Ok(())
 	}
 
+	async fn purge_expired(&mut self) -> Result<usize, Self::Error> {
+		let removed = self
+			.query()
+			.r#where("expiration", "<=", DateTime::now())
+			.delete()
+			.await?;
+
+		Ok(usize::try_from(removed).unwrap_or(usize::MAX))
+	}
+
 	async fn flush(&mut self) -> Result<(), Self::Error> {
-		CacheEntry::query().delete().await?;
+		// Only remove this cache's entries when it shares the table with others.
+		self.query()
+			.when(!self.prefix.is_empty(), |query| {
+				query.r#where("key", "like", like_prefix(&self.prefix))
+			})
+			.delete()
+			.await?;
 
 		Ok(())
 	}
 }
 
+/// Serialize a value into the JSON text it's stored as.
+fn to_json<T: Serialize>(value: &T) -> Result<String, Error> {
+	Ok(String::from_utf8(Format::Json.serialize(value)?)?)
+}
+
+/// Whether `error` is the `MySQL` error with the given number, which its message starts with.
+fn is_mysql_error(error: &ensemble::Error, number: &str) -> bool {
+	matches!(error, ensemble::Error::Database(message) if message.split(|c: char| !c.is_ascii_digit()).next() == Some(number))
+}
+
+/// Whether `error` is `MySQL`'s duplicate key error (number 1062).
+fn is_duplicate_key(error: &ensemble::Error) -> bool {
+	is_mysql_error(error, "1062")
+}
+
+/// Whether `error` is `MySQL`'s out of range error (number 1690), returned when a counter would overflow.
+fn is_out_of_range(error: &ensemble::Error) -> bool {
+	is_mysql_error(error, "1690")
+}
+
+/// A `LIKE` pattern matching every key that starts with `prefix`, with its wildcards escaped.
+fn like_prefix(prefix: &str) -> String {
+	let mut pattern = String::with_capacity(prefix.len() + 1);
+	for c in prefix.chars() {
+		if matches!(c, '%' | '_' | '\\') {
+			pattern.push('\\');
+		}
+		pattern.push(c);
+	}
+	pattern.push('%');
+
+	pattern
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
 	#[error(transparent)]
 	Database(#[from] ensemble::Error),
 	#[error(transparent)]
-	Serialize(#[from] serde_json::Error),
+	Serialize(#[from] format::Error),
+	#[error("invalid table name {0:?}, only letters, digits and underscores are allowed.")]
+	InvalidTable(String),
+	#[error("the serialized value isn't valid UTF-8.")]
+	Utf8(#[from] std::string::FromUtf8Error),
+	#[error(transparent)]
+	Overflow(#[from] Overflow),
 }
 
 #[cfg(test)]
@@ -107,11 +422,58 @@ mod tests {
 	use super::*;
 	use crate::Cache;
 
+	#[test]
+	fn test_is_duplicate_key() {
+		assert!(is_duplicate_key(&ensemble::Error::Database(
+			"1062 (23000): Duplicate entry 'foo' for key 'PRIMARY'".to_string()
+		)));
+		assert!(is_duplicate_key(&ensemble::Error::Database(
+			"1062: Duplicate entry 'foo' for key 'PRIMARY'".to_string()
+		)));
+		assert!(!is_duplicate_key(&ensemble::Error::Database(
+			"1146 (42S02): Table 'amnesia.cache' doesn't exist".to_string()
+		)));
+		assert!(!is_duplicate_key(&ensemble::Error::UniqueViolation));
+	}
+
+	#[test]
+	fn test_is_out_of_range() {
+		assert!(is_out_of_range(&ensemble::Error::Database(
+			"1690 (22003): BIGINT value is out of range in '(cast(`value` as signed) + cast(`value` as signed))'"
+				.to_string()
+		)));
+		assert!(!is_out_of_range(&ensemble::Error::Database(
+			"1062 (23000): Duplicate entry 'foo' for key 'PRIMARY'".to_string()
+		)));
+	}
+
+	#[tokio::test]
+	async fn test_database_table_name() {
+		let driver = DatabaseDriver::new(Config::builder().table("amnesia_cache_2").build())
+			.await
+			.unwrap();
+		assert_eq!(driver.table, "amnesia_cache_2");
+
+		for table in [
+			"",
+			"cache`; DROP TABLE users; --",
+			"cache.entries",
+			"cache entries",
+		] {
+			assert!(matches!(
+				DatabaseDriver::new(Config::builder().table(table).build()).await,
+				Err(Error::InvalidTable(name)) if name == table
+			));
+		}
+	}
+
 	#[tokio::test]
 	async fn test_database_driver() {
 		ensemble::setup(&env::var("DATABASE_URL").expect("DATABASE_URL not set")).unwrap();
 
-		let mut cache = Cache::<DatabaseDriver>::new(()).await.unwrap();
+		let mut cache = Cache::<DatabaseDriver>::new(Config::default())
+			.await
+			.unwrap();
 
 		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
 		assert!(!cache.has("foo").await.unwrap());
@@ -133,5 +495,33 @@ mod tests {
 			.put("foo", &"bar".to_string(), Duration::from_secs(1))
 			.await
 			.unwrap();
+
+		cache.forever("foo", "baz".to_string()).await.unwrap();
+		assert_eq!(cache.get("foo").await.unwrap(), Some("baz".to_string()));
+		assert_eq!(cache.ttl("foo").await.unwrap(), Some(Ttl::Forever));
+	}
+
+	#[tokio::test]
+	async fn test_database_has_ignores_other_keys() {
+		ensemble::setup(&env::var("DATABASE_URL").expect("DATABASE_URL not set")).unwrap();
+
+		let mut cache = Cache::<DatabaseDriver>::new(Config::default())
+			.await
+			.unwrap();
+
+		cache
+			.put("fresh", &"bar".to_string(), Duration::from_secs(30))
+			.await
+			.unwrap();
+		cache
+			.put("stale", &"bar".to_string(), Duration::from_millis(10))
+			.await
+			.unwrap();
+		tokio::time::sleep(Duration::from_millis(50)).await;
+
+		// Another key that hasn't expired shouldn't make an expired one look present.
+		assert!(cache.has("fresh").await.unwrap());
+		assert!(!cache.has("stale").await.unwrap());
+		assert!(!cache.has("missing").await.unwrap());
 	}
 }
@@ -0,0 +1,674 @@
+use super::{
+	format::{self, Raw},
+	Driver, EntryMeta, Format, GetOutcome, Overflow, PoolStatus, Ttl,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	future::Future,
+	pin::Pin,
+	time::{Duration, SystemTime},
+};
+
+/// A boxed, `Send` future, as returned by [`DynDriver`] methods.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// An object-safe version of [`Driver`], so the backend can be picked at runtime.
+///
+/// Every [`Driver`] implements this trait, and `Box<dyn DynDriver>` implements [`Driver`] in turn, so a
+/// `Cache<Box<dyn DynDriver>>` can be backed by any driver. Values are serialized with the underlying driver's
+/// [`format`](Driver::format) before reaching it, and stored as-is, so entries written through a boxed driver can be
+/// read back without the box (and the other way around). Drivers without a format are handed the `bitcode` bytes.
+pub trait DynDriver: Send + Sync {
+	/// The prefix prepended to every key by the driver.
+	fn key_prefix(&self) -> &str;
+
+	/// The status of the driver's connection pool, if it uses one.
+	fn connection_pool_status(&self) -> Option<PoolStatus>;
+
+	/// The format the driver serializes values with, if it has one.
+	fn value_format(&self) -> Option<Format>;
+
+	/// Get the raw bytes for a value from the cache.
+	fn get_bytes<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<Vec<u8>>, Error>>;
+
+	/// Get the raw bytes for many values from the cache, in the same order as `keys`.
+	fn get_many_bytes<'a>(
+		&'a self,
+		keys: &'a [&'a str],
+	) -> BoxFuture<'a, Result<Vec<Option<Vec<u8>>>, Error>>;
+
+	/// Check if a value exists in the cache.
+	fn has_key<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<bool, Error>>;
+
+	/// Get how long a value has left before it expires.
+	fn key_ttl<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<Ttl>, Error>>;
+
+	/// Get the metadata for a value in the cache, if the driver tracks it.
+	fn entry_metadata<'a>(
+		&'a self,
+		key: &'a str,
+	) -> BoxFuture<'a, Result<Option<EntryMeta>, Error>>;
+
+	/// List the keys of the values in the cache, optionally only the ones starting with `prefix`.
+	fn list_keys<'a>(
+		&'a self,
+		prefix: Option<&'a str>,
+	) -> BoxFuture<'a, Result<Vec<String>, Error>>;
+
+	/// Put the raw bytes for a value into the cache.
+	fn put_bytes<'a>(
+		&'a mut self,
+		key: &'a str,
+		data: Vec<u8>,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<(), Error>>;
+
+	/// Put the raw bytes for a value into the cache until the given deadline.
+	fn put_bytes_until<'a>(
+		&'a mut self,
+		key: &'a str,
+		data: Vec<u8>,
+		deadline: SystemTime,
+	) -> BoxFuture<'a, Result<(), Error>>;
+
+	/// Put the raw bytes for many values into the cache.
+	fn put_many_bytes<'a>(
+		&'a mut self,
+		entries: Vec<(&'a str, Vec<u8>)>,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<(), Error>>;
+
+	/// Get the raw bytes for a value from the cache, or store the given ones if it doesn't exist yet.
+	fn get_or_put_bytes<'a>(
+		&'a mut self,
+		key: &'a str,
+		data: Vec<u8>,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<Vec<u8>, Error>>;
+
+	/// Put the raw bytes for a value into the cache, returning the ones they replaced.
+	fn replace_bytes<'a>(
+		&'a mut self,
+		key: &'a str,
+		data: Vec<u8>,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<Option<Vec<u8>>, Error>>;
+
+	/// Put the raw bytes for a value into the cache only if it doesn't exist yet.
+	fn add_bytes<'a>(
+		&'a mut self,
+		key: &'a str,
+		data: Vec<u8>,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<bool, Error>>;
+
+	/// Store a value in the cache only if the current one's raw bytes are equal to `expected`.
+	fn compare_and_swap_bytes<'a>(
+		&'a mut self,
+		key: &'a str,
+		expected: Vec<u8>,
+		new: Vec<u8>,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<bool, Error>>;
+
+	/// Increment a numeric value in the cache.
+	fn increment_key<'a>(
+		&'a mut self,
+		key: &'a str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<i64, Error>>;
+
+	/// Change when a value expires without rewriting it.
+	fn touch_key<'a>(
+		&'a mut self,
+		key: &'a str,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<bool, Error>>;
+
+	/// Remove a value from the cache.
+	fn forget_key<'a>(&'a mut self, key: &'a str) -> BoxFuture<'a, Result<bool, Error>>;
+
+	/// Remove many values from the cache.
+	fn forget_many_keys<'a>(&'a mut self, keys: &'a [&'a str]) -> BoxFuture<'a, Result<(), Error>>;
+
+	/// Remove every value whose key starts with `prefix`.
+	fn forget_keys_with_prefix<'a>(
+		&'a mut self,
+		prefix: &'a str,
+	) -> BoxFuture<'a, Result<(), Error>>;
+
+	/// Remove a value from the cache if its raw bytes are equal to `expected`.
+	fn forget_key_if<'a>(
+		&'a mut self,
+		key: &'a str,
+		expected: Vec<u8>,
+	) -> BoxFuture<'a, Result<bool, Error>>;
+
+	/// Remove every expired value from the cache, returning how many were removed.
+	fn purge_expired_values(&mut self) -> BoxFuture<'_, Result<usize, Error>>;
+
+	/// Remove all values from the cache.
+	fn flush_all(&mut self) -> BoxFuture<'_, Result<(), Error>>;
+}
+
+impl<D: Driver> DynDriver for D {
+	fn key_prefix(&self) -> &str {
+		self.prefix()
+	}
+
+	fn connection_pool_status(&self) -> Option<PoolStatus> {
+		self.pool_status()
+	}
+
+	fn value_format(&self) -> Option<Format> {
+		self.format()
+	}
+
+	fn get_bytes<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<Vec<u8>>, Error>> {
+		Box::pin(async move {
+			let data = self.get::<Raw>(key).await.map_err(Error::driver::<D>)?;
+
+			Ok(data.map(|Raw(data)| data))
+		})
+	}
+
+	fn get_many_bytes<'a>(
+		&'a self,
+		keys: &'a [&'a str],
+	) -> BoxFuture<'a, Result<Vec<Option<Vec<u8>>>, Error>> {
+		Box::pin(async move {
+			let values = self
+				.get_many::<Raw>(keys)
+				.await
+				.map_err(Error::driver::<D>)?;
+
+			Ok(values
+				.into_iter()
+				.map(|data| data.map(|Raw(data)| data))
+				.collect())
+		})
+	}
+
+	fn has_key<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<bool, Error>> {
+		Box::pin(async move { self.has(key).await.map_err(Error::driver::<D>) })
+	}
+
+	fn key_ttl<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<Ttl>, Error>> {
+		Box::pin(async move { self.ttl(key).await.map_err(Error::driver::<D>) })
+	}
+
+	fn entry_metadata<'a>(
+		&'a self,
+		key: &'a str,
+	) -> BoxFuture<'a, Result<Option<EntryMeta>, Error>> {
+		Box::pin(async move { self.metadata(key).await.map_err(Error::driver::<D>) })
+	}
+
+	fn list_keys<'a>(
+		&'a self,
+		prefix: Option<&'a str>,
+	) -> BoxFuture<'a, Result<Vec<String>, Error>> {
+		Box::pin(async move { self.keys(prefix).await.map_err(Error::driver::<D>) })
+	}
+
+	fn put_bytes<'a>(
+		&'a mut self,
+		key: &'a str,
+		data: Vec<u8>,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<(), Error>> {
+		Box::pin(async move {
+			self.put(key, &Raw(data), expiry)
+				.await
+				.map_err(Error::driver::<D>)
+		})
+	}
+
+	fn put_bytes_until<'a>(
+		&'a mut self,
+		key: &'a str,
+		data: Vec<u8>,
+		deadline: SystemTime,
+	) -> BoxFuture<'a, Result<(), Error>> {
+		Box::pin(async move {
+			self.put_until(key, &Raw(data), deadline)
+				.await
+				.map_err(Error::driver::<D>)
+		})
+	}
+
+	fn put_many_bytes<'a>(
+		&'a mut self,
+		entries: Vec<(&'a str, Vec<u8>)>,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<(), Error>> {
+		Box::pin(async move {
+			let entries = entries
+				.into_iter()
+				.map(|(key, data)| (key, Raw(data)))
+				.collect::<Vec<_>>();
+			let entries = entries
+				.iter()
+				.map(|(key, data)| (*key, data))
+				.collect::<Vec<_>>();
+
+			self.put_many(&entries, expiry)
+				.await
+				.map_err(Error::driver::<D>)
+		})
+	}
+
+	fn get_or_put_bytes<'a>(
+		&'a mut self,
+		key: &'a str,
+		data: Vec<u8>,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<Vec<u8>, Error>> {
+		Box::pin(async move {
+			let Raw(data) = self
+				.get_or_put(key, Raw(data), expiry)
+				.await
+				.map_err(Error::driver::<D>)?;
+
+			Ok(data)
+		})
+	}
+
+	fn replace_bytes<'a>(
+		&'a mut self,
+		key: &'a str,
+		data: Vec<u8>,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<Option<Vec<u8>>, Error>> {
+		Box::pin(async move {
+			let previous = self
+				.replace(key, &Raw(data), expiry)
+				.await
+				.map_err(Error::driver::<D>)?;
+
+			Ok(previous.map(|Raw(previous)| previous))
+		})
+	}
+
+	fn add_bytes<'a>(
+		&'a mut self,
+		key: &'a str,
+		data: Vec<u8>,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<bool, Error>> {
+		Box::pin(async move {
+			self.add(key, &Raw(data), expiry)
+				.await
+				.map_err(Error::driver::<D>)
+		})
+	}
+
+	fn compare_and_swap_bytes<'a>(
+		&'a mut self,
+		key: &'a str,
+		expected: Vec<u8>,
+		new: Vec<u8>,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<bool, Error>> {
+		Box::pin(async move {
+			self.compare_and_swap(key, &Raw(expected), &Raw(new), expiry)
+				.await
+				.map_err(Error::driver::<D>)
+		})
+	}
+
+	fn increment_key<'a>(
+		&'a mut self,
+		key: &'a str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<i64, Error>> {
+		Box::pin(async move {
+			self.increment(key, by, expiry)
+				.await
+				.map_err(Error::driver::<D>)
+		})
+	}
+
+	fn touch_key<'a>(
+		&'a mut self,
+		key: &'a str,
+		expiry: Option<Duration>,
+	) -> BoxFuture<'a, Result<bool, Error>> {
+		Box::pin(async move { self.touch(key, expiry).await.map_err(Error::driver::<D>) })
+	}
+
+	fn forget_key<'a>(&'a mut self, key: &'a str) -> BoxFuture<'a, Result<bool, Error>> {
+		Box::pin(async move { self.forget(key).await.map_err(Error::driver::<D>) })
+	}
+
+	fn forget_many_keys<'a>(&'a mut self, keys: &'a [&'a str]) -> BoxFuture<'a, Result<(), Error>> {
+		Box::pin(async move { self.forget_many(keys).await.map_err(Error::driver::<D>) })
+	}
+
+	fn forget_keys_with_prefix<'a>(
+		&'a mut self,
+		prefix: &'a str,
+	) -> BoxFuture<'a, Result<(), Error>> {
+		Box::pin(async move { self.forget_prefix(prefix).await.map_err(Error::driver::<D>) })
+	}
+
+	fn forget_key_if<'a>(
+		&'a mut self,
+		key: &'a str,
+		expected: Vec<u8>,
+	) -> BoxFuture<'a, Result<bool, Error>> {
+		Box::pin(async move {
+			self.forget_if(key, &Raw(expected))
+				.await
+				.map_err(Error::driver::<D>)
+		})
+	}
+
+	fn purge_expired_values(&mut self) -> BoxFuture<'_, Result<usize, Error>> {
+		Box::pin(async move { self.purge_expired().await.map_err(Error::driver::<D>) })
+	}
+
+	fn flush_all(&mut self) -> BoxFuture<'_, Result<(), Error>> {
+		Box::pin(async move { self.flush().await.map_err(Error::driver::<D>) })
+	}
+}
+
+impl dyn DynDriver {
+	/// The format values are serialized with before being handed to the driver.
+	fn codec(&self) -> Format {
+		self.value_format().unwrap_or_default()
+	}
+}
+
+impl Driver for Box<dyn DynDriver> {
+	type Error = Error;
+	type Config = Self;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		Ok(config)
+	}
+
+	fn prefix(&self) -> &str {
+		self.as_ref().key_prefix()
+	}
+
+	fn pool_status(&self) -> Option<PoolStatus> {
+		self.as_ref().connection_pool_status()
+	}
+
+	fn is_unavailable(error: &Self::Error) -> bool {
+		matches!(error, Error::Unavailable(_))
+	}
+
+	fn format(&self) -> Option<Format> {
+		self.as_ref().value_format()
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		let Some(data) = self.as_ref().get_bytes(key).await? else {
+			return Ok(None);
+		};
+
+		Ok(Some(self.codec().deserialize(&data)?))
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		let Some(data) = self.as_ref().get_bytes(key).await? else {
+			return Ok(GetOutcome::Miss);
+		};
+
+		Ok(self
+			.codec()
+			.deserialize(&data)
+			.map_or(GetOutcome::Corrupt(data), GetOutcome::Hit))
+	}
+
+	async fn get_many<T: DeserializeOwned + Send>(
+		&self,
+		keys: &[&str],
+	) -> Result<Vec<Option<T>>, Self::Error> {
+		let mut values = Vec::with_capacity(keys.len());
+		for data in self.as_ref().get_many_bytes(keys).await? {
+			values.push(
+				data.map(|data| self.codec().deserialize(&data))
+					.transpose()?,
+			);
+		}
+
+		Ok(values)
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		self.as_ref().has_key(key).await
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		self.as_ref().key_ttl(key).await
+	}
+
+	async fn metadata(&self, key: &str) -> Result<Option<EntryMeta>, Self::Error> {
+		self.as_ref().entry_metadata(key).await
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		self.as_ref().list_keys(prefix).await
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		let data = self.codec().serialize(value)?;
+
+		self.as_mut().put_bytes(key, data, expiry).await
+	}
+
+	async fn put_until<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		deadline: SystemTime,
+	) -> Result<(), Self::Error> {
+		let data = self.codec().serialize(value)?;
+
+		self.as_mut().put_bytes_until(key, data, deadline).await
+	}
+
+	async fn put_many<T: Serialize + Sync>(
+		&mut self,
+		entries: &[(&str, &T)],
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		let entries = entries
+			.iter()
+			.map(|(key, value)| Ok((*key, self.codec().serialize(value)?)))
+			.collect::<Result<Vec<_>, format::Error>>()?;
+
+		self.as_mut().put_many_bytes(entries, expiry).await
+	}
+
+	async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		expiry: Option<Duration>,
+	) -> Result<T, Self::Error> {
+		let data = self.codec().serialize(&value)?;
+		let cached = self.as_mut().get_or_put_bytes(key, data, expiry).await?;
+
+		Ok(self.codec().deserialize(&cached)?)
+	}
+
+	async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<Option<T>, Self::Error> {
+		let data = self.codec().serialize(value)?;
+		let previous = self.as_mut().replace_bytes(key, data, expiry).await?;
+
+		Ok(previous
+			.map(|previous| self.codec().deserialize(&previous))
+			.transpose()?)
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		let data = self.codec().serialize(value)?;
+
+		self.as_mut().add_bytes(key, data, expiry).await
+	}
+
+	async fn compare_and_swap<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+		new: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		let expected = self.codec().serialize(expected)?;
+		let new = self.codec().serialize(new)?;
+
+		self.as_mut()
+			.compare_and_swap_bytes(key, expected, new, expiry)
+			.await
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		self.as_mut().increment_key(key, by, expiry).await
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		self.as_mut().touch_key(key, expiry).await
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		self.as_mut().forget_key(key).await
+	}
+
+	async fn forget_many(&mut self, keys: &[&str]) -> Result<(), Self::Error> {
+		self.as_mut().forget_many_keys(keys).await
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		self.as_mut().forget_keys_with_prefix(prefix).await
+	}
+
+	async fn forget_if<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+	) -> Result<bool, Self::Error> {
+		let expected = self.codec().serialize(expected)?;
+
+		self.as_mut().forget_key_if(key, expected).await
+	}
+
+	async fn purge_expired(&mut self) -> Result<usize, Self::Error> {
+		self.as_mut().purge_expired_values().await
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		self.as_mut().flush_all().await
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error(transparent)]
+	Driver(Box<dyn std::error::Error + Send + Sync>),
+	/// The underlying driver couldn't reach its backend (see [`Driver::is_unavailable`]).
+	#[error(transparent)]
+	Unavailable(Box<dyn std::error::Error + Send + Sync>),
+	#[error(transparent)]
+	Serialization(#[from] format::Error),
+	#[error(transparent)]
+	Overflow(#[from] Overflow),
+}
+
+impl Error {
+	fn driver<D: Driver>(error: D::Error) -> Self {
+		if D::is_unavailable(&error) {
+			Self::Unavailable(Box::new(error))
+		} else {
+			Self::Driver(Box::new(error))
+		}
+	}
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+	use super::*;
+	use crate::{
+		drivers::{memory, MemoryDriver},
+		Cache,
+	};
+
+	#[tokio::test]
+	async fn test_dynamic_driver() {
+		let driver: Box<dyn DynDriver> =
+			Box::new(MemoryDriver::new(memory::Config::default()).await.unwrap());
+		let mut cache = Cache::<Box<dyn DynDriver>>::new(driver).await.unwrap();
+
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+		assert!(!cache.has("foo").await.unwrap());
+
+		cache
+			.put("foo", &"bar".to_string(), Duration::from_secs(10))
+			.await
+			.unwrap();
+
+		assert_eq!(cache.get("foo").await.unwrap(), Some("bar".to_string()));
+		assert!(cache.has("foo").await.unwrap());
+
+		cache.forget("foo").await.unwrap();
+
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+		assert!(!cache.has("foo").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_dynamic_driver_shares_entries() {
+		let mut memory = MemoryDriver::new(memory::Config {
+			format: Format::Json,
+			..Default::default()
+		})
+		.await
+		.unwrap();
+		let mut cache = Cache::<Box<dyn DynDriver>>::new(Box::new(memory.clone()))
+			.await
+			.unwrap();
+
+		cache
+			.put("foo", &vec![1_u32, 2, 3], Duration::from_secs(10))
+			.await
+			.unwrap();
+		memory.put("bar", &"baz", None).await.unwrap();
+
+		assert_eq!(
+			memory.get::<Vec<u32>>("foo").await.unwrap(),
+			Some(vec![1, 2, 3])
+		);
+		assert_eq!(cache.get("bar").await.unwrap(), Some("baz".to_string()));
+
+		assert!(cache
+			.compare_and_swap("bar", &"baz".to_string(), &"qux".to_string(), None)
+			.await
+			.unwrap());
+		assert_eq!(memory.get("bar").await.unwrap(), Some("qux".to_string()));
+	}
+}
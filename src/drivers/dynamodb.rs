@@ -1,9 +1,21 @@
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{
+	collections::{BTreeSet, HashMap},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use aws_sdk_dynamodb::{primitives::Blob, types::AttributeValue};
+use aws_sdk_dynamodb::{
+	error::BuildError,
+	primitives::Blob,
+	types::{
+		AttributeValue, DeleteRequest, KeysAndAttributes, PutRequest, ReturnValue, WriteRequest,
+	},
+};
+use aws_smithy_runtime_api::client::result::SdkError;
 use serde::{de::DeserializeOwned, Serialize};
 
-use super::Driver;
+use super::{
+	format, is_aws_unavailable, schema, Driver, Format, GetOutcome, OnCorrupt, Overflow, Ttl,
+};
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -11,10 +23,62 @@ pub struct Config {
 	pub prefix: String,
 	pub key_attribute: String,
 	pub value_attribute: String,
+	/// The attribute holding each item's expiration, as a Unix timestamp in seconds.
+	/// Enable `DynamoDB`'s TTL on it so expired items eventually get deleted. Since that can happen up to 48 hours
+	/// after they expire, reads treat items as missing as soon as their expiration passes.
 	pub expiration_attribute: String,
+	/// The attribute holding each item's expiration as a Unix timestamp in milliseconds, so sub-second TTLs are
+	/// honored on read. Falls back to `expiration_attribute` for items that don't have it.
+	pub expiration_millis_attribute: String,
+	/// Read string values as JSON, for tables previously written by a JSON-based cache.
+	pub legacy_json_values: bool,
+	/// A version stored alongside every value. Values stored with a different version are treated as misses.
+	pub schema_version: Option<u32>,
+	/// What `get` does with items that can't be deserialized.
+	pub on_corrupt: OnCorrupt,
+	/// The format values are serialized with, before being stored as binary attributes.
+	pub format: Format,
+	/// A grace period after an item's expiration during which it's still considered fresh, to absorb clock skew
+	/// between the machines writing and reading the table. Items may be served for up to this long past their TTL.
+	pub clock_skew_tolerance: Duration,
+	/// What calling `flush` does, since `DynamoDB` has no way of emptying a table in a single operation.
+	pub flush_behavior: FlushBehavior,
+	/// Use strongly consistent reads, so values are visible as soon as they're written instead of after a short
+	/// replication delay. Strongly consistent reads consume twice the read capacity of eventually consistent ones.
+	pub consistent_read: bool,
 	pub aws_config: aws_types::SdkConfig,
 }
 
+config_builder! {
+	table: String,
+	prefix: String,
+	key_attribute: String,
+	value_attribute: String,
+	expiration_attribute: String,
+	expiration_millis_attribute: String,
+	legacy_json_values: bool,
+	schema_version: Option<u32>,
+	on_corrupt: OnCorrupt,
+	format: Format,
+	clock_skew_tolerance: Duration,
+	flush_behavior: FlushBehavior,
+	consistent_read: bool,
+	aws_config: aws_types::SdkConfig,
+}
+
+/// How the `DynamoDB` driver handles `flush`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlushBehavior {
+	/// Scan the table and delete every item under the driver's prefix.
+	/// This consumes read and write capacity proportional to the size of the table.
+	#[default]
+	ScanDelete,
+	/// Return [`Error::FlushNotSupported`], for tables too large (or too shared) to scan.
+	Error,
+	/// Do nothing, leaving every item in place.
+	Noop,
+}
+
 impl Default for Config {
 	fn default() -> Self {
 		Self {
@@ -23,60 +87,326 @@ impl Default for Config {
 			key_attribute: String::from("key"),
 			value_attribute: String::from("value"),
 			expiration_attribute: String::from("expires_at"),
+			expiration_millis_attribute: String::from("expires_at_ms"),
+			legacy_json_values: false,
+			schema_version: None,
+			on_corrupt: OnCorrupt::default(),
+			format: Format::default(),
+			clock_skew_tolerance: Duration::ZERO,
+			flush_behavior: FlushBehavior::default(),
+			consistent_read: false,
 			aws_config: aws_types::SdkConfig::builder().build(),
 		}
 	}
 }
 
+#[derive(Debug, Clone)]
 #[allow(clippy::module_name_repetitions)]
-/// A driver that uses DynamoDB as a backend.
+/// A driver that uses `DynamoDB` as a backend.
 pub struct DynamoDBDriver {
 	table: String,
 	prefix: String,
 	key_attribute: String,
 	value_attribute: String,
 	expiration_attribute: String,
+	expiration_millis_attribute: String,
+	legacy_json_values: bool,
+	schema_version: Option<u32>,
+	on_corrupt: OnCorrupt,
+	format: Format,
+	clock_skew_tolerance: Duration,
+	flush_behavior: FlushBehavior,
+	consistent_read: bool,
 	client: aws_sdk_dynamodb::Client,
 }
 
+/// The contents of a stored item's value attribute.
+enum StoredValue {
+	Binary(Vec<u8>),
+	LegacyJson(String),
+	/// A counter, which `increment` stores as a native number so it can be updated atomically.
+	Number(String),
+}
+
+impl StoredValue {
+	#[allow(clippy::result_large_err)]
+	fn deserialize<T: DeserializeOwned>(&self, format: Format) -> Result<T, Error> {
+		match self {
+			Self::Binary(data) => Ok(format.deserialize(data)?),
+			Self::LegacyJson(data) | Self::Number(data) => Ok(serde_json::from_str(data)?),
+		}
+	}
+}
+
 impl DynamoDBDriver {
-	async fn get_item(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+	/// Deserialize the value read from `key`, deleting the item instead if it's corrupt and `on_corrupt` says to.
+	async fn decode<T: DeserializeOwned>(
+		&self,
+		key: &str,
+		value: &StoredValue,
+	) -> Result<Option<T>, Error> {
+		let error = match value.deserialize(self.format) {
+			Ok(value) => return Ok(Some(value)),
+			Err(error) => error,
+		};
+
+		if self.on_corrupt != OnCorrupt::Evict {
+			return Err(error);
+		}
+
+		self.client
+			.delete_item()
+			.table_name(&self.table)
+			.key(
+				&self.key_attribute,
+				AttributeValue::S(format!("{}{key}", self.prefix)),
+			)
+			.send()
+			.await?;
+
+		Ok(None)
+	}
+
+	/// Delete every item whose key starts with `prefix`, scanning the table a page at a time.
+	async fn scan_delete(&self, prefix: &str) -> Result<(), Error> {
+		let mut start_key = None;
+
+		loop {
+			let mut request = self
+				.client
+				.scan()
+				.table_name(&self.table)
+				.projection_expression("#key")
+				.expression_attribute_names("#key", &self.key_attribute)
+				.set_exclusive_start_key(start_key);
+
+			if !prefix.is_empty() {
+				request = request
+					.filter_expression("begins_with(#key, :prefix)")
+					.expression_attribute_values(":prefix", AttributeValue::S(prefix.to_string()));
+			}
+
+			let response = request.send().await?;
+
+			let requests = response
+				.items
+				.unwrap_or_default()
+				.into_iter()
+				.map(|key| {
+					let delete = DeleteRequest::builder().set_key(Some(key)).build()?;
+
+					Ok(WriteRequest::builder().delete_request(delete).build())
+				})
+				.collect::<Result<Vec<_>, BuildError>>()?;
+
+			self.batch_write(requests).await?;
+
+			start_key = response.last_evaluated_key;
+			if start_key.is_none() {
+				return Ok(());
+			}
+		}
+	}
+
+	/// Send the write requests in batches, retrying any the table didn't get around to processing.
+	async fn batch_write(&self, requests: Vec<WriteRequest>) -> Result<(), Error> {
+		// Batch writes are limited to 25 items each.
+		for chunk in requests.chunks(25) {
+			let mut requests = chunk.to_vec();
+
+			while !requests.is_empty() {
+				let response = self
+					.client
+					.batch_write_item()
+					.request_items(&self.table, requests)
+					.send()
+					.await?;
+
+				requests = response
+					.unprocessed_items
+					.and_then(|mut items| items.remove(&self.table))
+					.unwrap_or_default();
+			}
+		}
+
+		Ok(())
+	}
+
+	/// The attributes of the item storing the given value.
+	fn item<T: Serialize>(
+		&self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<HashMap<String, AttributeValue>, format::Error> {
+		self.item_expiring_at(key, value, expiry.map(|expiry| SystemTime::now() + expiry))
+	}
+
+	/// The attributes of the item storing the given value until `expires_at`.
+	fn item_expiring_at<T: Serialize>(
+		&self,
+		key: &str,
+		value: &T,
+		expires_at: Option<SystemTime>,
+	) -> Result<HashMap<String, AttributeValue>, format::Error> {
+		let mut item = HashMap::from([
+			(
+				self.key_attribute.clone(),
+				AttributeValue::S(format!("{}{key}", self.prefix)),
+			),
+			(
+				self.value_attribute.clone(),
+				AttributeValue::B(Blob::new(schema::wrap(
+					self.schema_version,
+					self.format.serialize(value)?,
+				))),
+			),
+			(
+				self.expiration_attribute.clone(),
+				expires_at.map_or(AttributeValue::Null(true), |expires_at| {
+					AttributeValue::N(expiration_timestamp(expires_at).to_string())
+				}),
+			),
+		]);
+
+		if let Some(expires_at) = expires_at {
+			let millis = expires_at.duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+			item.insert(
+				self.expiration_millis_attribute.clone(),
+				AttributeValue::N(millis.to_string()),
+			);
+		}
+
+		Ok(item)
+	}
+
+	/// The point in time items must expire after to still be considered fresh.
+	fn expiry_cutoff(&self) -> SystemTime {
+		SystemTime::now()
+			.checked_sub(self.clock_skew_tolerance)
+			.unwrap_or(UNIX_EPOCH)
+	}
+
+	async fn get_item(&self, key: &str) -> Result<Option<StoredValue>, Error> {
+		let key = format!("{}{key}", self.prefix);
+
+		let Some(item) = self.fetch_item(&key).await? else {
+			return Ok(None);
+		};
+
+		self.parse_item(key, item)
+	}
+
+	/// Fetch the value and expiration attributes of the item with the given (prefixed) key.
+	async fn fetch_item(
+		&self,
+		key: &str,
+	) -> Result<Option<HashMap<String, AttributeValue>>, Error> {
 		let response = self
 			.client
 			.get_item()
 			.table_name(&self.table)
 			.key(
 				self.key_attribute.clone(),
-				AttributeValue::S(format!("{}{key}", self.prefix)),
+				AttributeValue::S(key.to_string()),
 			)
+			.projection_expression("#value, #expires_at, #expires_at_ms")
+			.expression_attribute_names("#value", &self.value_attribute)
+			.expression_attribute_names("#expires_at", &self.expiration_attribute)
+			.expression_attribute_names("#expires_at_ms", &self.expiration_millis_attribute)
+			.consistent_read(self.consistent_read)
 			.send()
 			.await?;
 
-		let Some(item) = response.item else {
+		Ok(response.item)
+	}
+
+	/// When an item expires (since the Unix epoch), or `None` if it doesn't.
+	#[allow(clippy::result_large_err)]
+	fn expires_at(
+		&self,
+		key: &str,
+		item: &HashMap<String, AttributeValue>,
+	) -> Result<Option<Duration>, Error> {
+		match (
+			item.get(&self.expiration_millis_attribute),
+			item.get(&self.expiration_attribute),
+		) {
+			(Some(AttributeValue::N(millis)), _) => Ok(Some(Duration::from_millis(
+				millis.parse().map_err(|_| Error::InvalidDataFormat)?,
+			))),
+			(_, None | Some(AttributeValue::Null(_))) => Ok(None),
+			(_, Some(AttributeValue::N(secs))) => Ok(Some(Duration::from_secs(
+				secs.parse().map_err(|_| Error::InvalidDataFormat)?,
+			))),
+			(_, Some(value)) => Err(Error::UnexpectedAttributeType {
+				key: key.to_string(),
+				attribute: self.expiration_attribute.clone(),
+				found: attribute_type(value),
+			}),
+		}
+	}
+
+	/// Read the stored value out of an item, treating items that have logically expired as missing.
+	#[allow(clippy::result_large_err)]
+	fn parse_item(
+		&self,
+		key: String,
+		mut item: HashMap<String, AttributeValue>,
+	) -> Result<Option<StoredValue>, Error> {
+		let expires_at = self.expires_at(&key, &item)?;
+
+		// The item may still be around because TTL deletion lags behind, but it has logically expired.
+		if expires_at.is_some_and(|expires_at| is_expired(expires_at, self.expiry_cutoff())) {
 			return Ok(None);
-		};
+		}
 
-		if let Some(expires_at) = item
-			.get(&self.expiration_attribute)
-			.map(|value| value.as_n())
-		{
-			let expires_at: u64 = expires_at
-				.map_err(|_| Error::InvalidDataFormat)?
-				.parse()
-				.map_err(|_| Error::InvalidDataFormat)?;
-
-			if UNIX_EPOCH + Duration::from_secs(expires_at) < SystemTime::now() {
-				return Ok(None);
-			}
+		match item.remove(&self.value_attribute) {
+			Some(AttributeValue::B(data)) => Ok(schema::unwrap(self.schema_version, data.as_ref())
+				.map(|data| StoredValue::Binary(data.to_vec()))),
+			Some(AttributeValue::S(data)) if self.legacy_json_values => {
+				Ok(Some(StoredValue::LegacyJson(data)))
+			},
+			// Counters aren't serialized, so they don't carry a schema version either.
+			Some(AttributeValue::N(data)) => Ok(Some(StoredValue::Number(data))),
+			value => Err(Error::UnexpectedAttributeType {
+				key,
+				attribute: self.value_attribute.clone(),
+				found: value.as_ref().map_or("missing", attribute_type),
+			}),
 		}
+	}
+}
 
-		let data = if let Some(data) = item.get(&self.value_attribute).map(|value| value.as_b()) {
-			data.map_err(|_| Error::InvalidDataFormat)?
-		} else {
-			return Err(Error::InvalidDataFormat);
-		};
+/// The value stored in the expiration attribute for an item expiring at the given time.
+/// `DynamoDB`'s TTL works with whole seconds, so this rounds up to avoid deleting items before they expire.
+fn expiration_timestamp(expires_at: SystemTime) -> u64 {
+	let since_epoch = expires_at.duration_since(UNIX_EPOCH).unwrap();
 
-		Ok(Some(data.as_ref().to_vec()))
+	since_epoch.as_secs() + u64::from(since_epoch.subsec_nanos() > 0)
+}
+
+/// Whether an item expiring at the given time (since the Unix epoch) has logically expired, regardless of whether it
+/// was deleted.
+fn is_expired(expires_at: Duration, now: SystemTime) -> bool {
+	now.duration_since(UNIX_EPOCH).unwrap() >= expires_at
+}
+
+/// The `DynamoDB` type descriptor for an attribute value, used in error messages.
+const fn attribute_type(value: &AttributeValue) -> &'static str {
+	match value {
+		AttributeValue::B(_) => "B",
+		AttributeValue::Bool(_) => "BOOL",
+		AttributeValue::Bs(_) => "BS",
+		AttributeValue::L(_) => "L",
+		AttributeValue::M(_) => "M",
+		AttributeValue::N(_) => "N",
+		AttributeValue::Ns(_) => "NS",
+		AttributeValue::Null(_) => "NULL",
+		AttributeValue::S(_) => "S",
+		AttributeValue::Ss(_) => "SS",
+		_ => "unknown",
 	}
 }
 
@@ -91,16 +421,129 @@ impl Driver for DynamoDBDriver {
 			key_attribute: config.key_attribute,
 			value_attribute: config.value_attribute,
 			expiration_attribute: config.expiration_attribute,
+			expiration_millis_attribute: config.expiration_millis_attribute,
+			legacy_json_values: config.legacy_json_values,
+			schema_version: config.schema_version,
+			on_corrupt: config.on_corrupt,
+			format: config.format,
+			clock_skew_tolerance: config.clock_skew_tolerance,
+			flush_behavior: config.flush_behavior,
+			consistent_read: config.consistent_read,
 			client: aws_sdk_dynamodb::Client::new(&config.aws_config),
 		})
 	}
 
+	fn prefix(&self) -> &str {
+		&self.prefix
+	}
+
+	fn is_unavailable(error: &Self::Error) -> bool {
+		error.is_unavailable()
+	}
+
+	fn format(&self) -> Option<Format> {
+		Some(self.format)
+	}
+
 	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
-		let item = self.get_item(key).await?;
+		match self.get_item(key).await? {
+			None => Ok(None),
+			Some(value) => self.decode(key, &value).await,
+		}
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		let outcome = match self.get_item(key).await? {
+			None => GetOutcome::Miss,
+			Some(StoredValue::Binary(data)) => self
+				.format
+				.deserialize(&data)
+				.map_or(GetOutcome::Corrupt(data), GetOutcome::Hit),
+			Some(StoredValue::LegacyJson(data) | StoredValue::Number(data)) => {
+				serde_json::from_str(&data)
+					.map_or_else(|_| GetOutcome::Corrupt(data.into_bytes()), GetOutcome::Hit)
+			},
+		};
 
-		Ok(item
-			.map(|item| bitcode::deserialize(item.as_ref()))
-			.transpose()?)
+		Ok(outcome)
+	}
+
+	async fn get_many<T: DeserializeOwned + Send>(
+		&self,
+		keys: &[&str],
+	) -> Result<Vec<Option<T>>, Self::Error> {
+		// Batch reads reject duplicate keys, and are limited to 100 keys each.
+		let unique = keys
+			.iter()
+			.map(|key| format!("{}{key}", self.prefix))
+			.collect::<BTreeSet<_>>()
+			.into_iter()
+			.collect::<Vec<_>>();
+
+		let mut values = HashMap::new();
+		for chunk in unique.chunks(100) {
+			let mut request = Some(
+				KeysAndAttributes::builder()
+					.set_keys(Some(
+						chunk
+							.iter()
+							.map(|key| {
+								HashMap::from([(
+									self.key_attribute.clone(),
+									AttributeValue::S(key.clone()),
+								)])
+							})
+							.collect(),
+					))
+					.projection_expression("#key, #value, #expires_at, #expires_at_ms")
+					.expression_attribute_names("#key", &self.key_attribute)
+					.expression_attribute_names("#value", &self.value_attribute)
+					.expression_attribute_names("#expires_at", &self.expiration_attribute)
+					.expression_attribute_names("#expires_at_ms", &self.expiration_millis_attribute)
+					.consistent_read(self.consistent_read)
+					.build()?,
+			);
+
+			while let Some(keys) = request {
+				let response = self
+					.client
+					.batch_get_item()
+					.request_items(&self.table, keys)
+					.send()
+					.await?;
+
+				let items = response
+					.responses
+					.and_then(|mut responses| responses.remove(&self.table))
+					.unwrap_or_default();
+
+				for item in items {
+					let Some(AttributeValue::S(key)) = item.get(&self.key_attribute).cloned()
+					else {
+						return Err(Error::InvalidDataFormat);
+					};
+
+					if let Some(value) = self.parse_item(key.clone(), item)? {
+						values.insert(key, value);
+					}
+				}
+
+				request = response
+					.unprocessed_keys
+					.and_then(|mut keys| keys.remove(&self.table))
+					.filter(|keys| !keys.keys.is_empty());
+			}
+		}
+
+		let mut decoded = Vec::with_capacity(keys.len());
+		for key in keys {
+			decoded.push(match values.get(&format!("{}{key}", self.prefix)) {
+				None => None,
+				Some(value) => self.decode(key, value).await?,
+			});
+		}
+
+		Ok(decoded)
 	}
 
 	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
@@ -109,65 +552,511 @@ impl Driver for DynamoDBDriver {
 		Ok(item.is_some())
 	}
 
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		let key = format!("{}{key}", self.prefix);
+
+		let Some(item) = self.fetch_item(&key).await? else {
+			return Ok(None);
+		};
+
+		let expires_at = self.expires_at(&key, &item)?;
+		if self.parse_item(key, item)?.is_none() {
+			return Ok(None);
+		}
+
+		Ok(Some(expires_at.map_or(Ttl::Forever, |expires_at| {
+			let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+			Ttl::In(expires_at.saturating_sub(now))
+		})))
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		let prefix = format!("{}{}", self.prefix, prefix.unwrap_or_default());
+		let cutoff = self.expiry_cutoff();
+
+		let mut keys = Vec::new();
+		let mut start_key = None;
+		loop {
+			let mut request = self
+				.client
+				.scan()
+				.table_name(&self.table)
+				.projection_expression("#key, #expires_at, #expires_at_ms")
+				.expression_attribute_names("#key", &self.key_attribute)
+				.expression_attribute_names("#expires_at", &self.expiration_attribute)
+				.expression_attribute_names("#expires_at_ms", &self.expiration_millis_attribute)
+				.consistent_read(self.consistent_read)
+				.set_exclusive_start_key(start_key);
+
+			if !prefix.is_empty() {
+				request = request
+					.filter_expression("begins_with(#key, :prefix)")
+					.expression_attribute_values(":prefix", AttributeValue::S(prefix.clone()));
+			}
+
+			let response = request.send().await?;
+
+			for item in response.items.unwrap_or_default() {
+				let Some(AttributeValue::S(key)) = item.get(&self.key_attribute) else {
+					return Err(Error::InvalidDataFormat);
+				};
+
+				if self
+					.expires_at(key, &item)?
+					.is_some_and(|expires_at| is_expired(expires_at, cutoff))
+				{
+					continue;
+				}
+
+				if let Some(key) = key.strip_prefix(&self.prefix) {
+					keys.push(key.to_string());
+				}
+			}
+
+			start_key = response.last_evaluated_key;
+			if start_key.is_none() {
+				return Ok(keys);
+			}
+		}
+	}
+
 	async fn put<T: Serialize + Sync>(
 		&mut self,
 		key: &str,
 		value: &T,
 		expiry: Option<Duration>,
 	) -> Result<(), Self::Error> {
-		let expires_at = expiry.map(|expiry| SystemTime::now() + expiry);
+		self.client
+			.put_item()
+			.table_name(&self.table)
+			.set_item(Some(self.item(key, value, expiry)?))
+			.send()
+			.await?;
+
+		Ok(())
+	}
+
+	async fn put_until<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		deadline: SystemTime,
+	) -> Result<(), Self::Error> {
+		// Like the other deadline paths, a deadline that has already passed removes the entry right away (which also
+		// covers deadlines before the Unix epoch, which can't be stored).
+		if deadline <= SystemTime::now() {
+			return self.forget(key).await.map(drop);
+		}
 
 		self.client
 			.put_item()
 			.table_name(&self.table)
-			.item(
-				self.key_attribute.clone(),
+			.set_item(Some(self.item_expiring_at(key, value, Some(deadline))?))
+			.send()
+			.await?;
+
+		Ok(())
+	}
+
+	async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<Option<T>, Self::Error> {
+		let response = self
+			.client
+			.put_item()
+			.table_name(&self.table)
+			.set_item(Some(self.item(key, value, expiry)?))
+			.return_values(ReturnValue::AllOld)
+			.send()
+			.await?;
+
+		let Some(previous) = response.attributes else {
+			return Ok(None);
+		};
+
+		let Some(previous) = self.parse_item(format!("{}{key}", self.prefix), previous)? else {
+			return Ok(None);
+		};
+
+		Ok(Some(previous.deserialize(self.format)?))
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		let cutoff = self
+			.expiry_cutoff()
+			.duration_since(UNIX_EPOCH)
+			.unwrap()
+			.as_secs();
+
+		// Items that have expired but haven't been deleted by the table's TTL yet are replaced.
+		let response = self
+			.client
+			.put_item()
+			.table_name(&self.table)
+			.set_item(Some(self.item(key, value, expiry)?))
+			.condition_expression("attribute_not_exists(#key) OR #expires_at <= :now")
+			.expression_attribute_names("#key", &self.key_attribute)
+			.expression_attribute_names("#expires_at", &self.expiration_attribute)
+			.expression_attribute_values(":now", AttributeValue::N(cutoff.to_string()))
+			.send()
+			.await;
+
+		match response {
+			Ok(_) => Ok(true),
+			Err(SdkError::ServiceError(error))
+				if error.err().is_conditional_check_failed_exception() =>
+			{
+				Ok(false)
+			},
+			Err(error) => Err(error.into()),
+		}
+	}
+
+	async fn compare_and_swap<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+		new: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		let expected = schema::wrap(self.schema_version, self.format.serialize(expected)?);
+		let cutoff = self
+			.expiry_cutoff()
+			.duration_since(UNIX_EPOCH)
+			.unwrap()
+			.as_secs();
+
+		let response = self
+			.client
+			.put_item()
+			.table_name(&self.table)
+			.set_item(Some(self.item(key, new, expiry)?))
+			.condition_expression(
+				"#value = :expected AND (attribute_not_exists(#expires_at) OR attribute_type(#expires_at, :null) OR #expires_at > :now)",
+			)
+			.expression_attribute_names("#value", &self.value_attribute)
+			.expression_attribute_names("#expires_at", &self.expiration_attribute)
+			.expression_attribute_values(":expected", AttributeValue::B(Blob::new(expected)))
+			.expression_attribute_values(":null", AttributeValue::S("NULL".to_string()))
+			.expression_attribute_values(":now", AttributeValue::N(cutoff.to_string()))
+			.send()
+			.await;
+
+		match response {
+			Ok(_) => Ok(true),
+			Err(SdkError::ServiceError(error))
+				if error.err().is_conditional_check_failed_exception() =>
+			{
+				Ok(false)
+			},
+			Err(error) => Err(error.into()),
+		}
+	}
+
+	async fn put_many<T: Serialize + Sync>(
+		&mut self,
+		entries: &[(&str, &T)],
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		// Batch writes reject duplicate keys, so only the last value for each key is written.
+		let mut items = HashMap::new();
+		for (key, value) in entries {
+			items.insert(*key, self.item(key, value, expiry)?);
+		}
+
+		let requests = items
+			.into_values()
+			.map(|item| {
+				let put = PutRequest::builder().set_item(Some(item)).build()?;
+
+				Ok(WriteRequest::builder().put_request(put).build())
+			})
+			.collect::<Result<Vec<_>, BuildError>>()?;
+
+		self.batch_write(requests).await
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		let key = format!("{}{key}", self.prefix);
+		let now = SystemTime::now();
+		let cutoff = AttributeValue::N(
+			self.expiry_cutoff()
+				.duration_since(UNIX_EPOCH)
+				.unwrap()
+				.as_secs()
+				.to_string(),
+		);
+		let expires_at = expiry.map(|expiry| expiration_timestamp(now + expiry));
+
+		loop {
+			let request = self
+				.client
+				.update_item()
+				.table_name(&self.table)
+				.key(self.key_attribute.clone(), AttributeValue::S(key.clone()))
+				.condition_expression(
+					"attribute_not_exists(#expires_at) OR attribute_type(#expires_at, :null) OR #expires_at > :now",
+				)
+				.expression_attribute_names("#value", &self.value_attribute)
+				.expression_attribute_names("#expires_at", &self.expiration_attribute)
+				.expression_attribute_values(":by", AttributeValue::N(by.to_string()))
+				.expression_attribute_values(":now", cutoff.clone())
+				.expression_attribute_values(":null", AttributeValue::S("NULL".to_string()))
+				.return_values(ReturnValue::UpdatedNew);
+
+			let request = if let Some(expires_at) = expires_at {
+				request
+					.update_expression(
+						"ADD #value :by SET #expires_at = if_not_exists(#expires_at, :expires_at)",
+					)
+					.expression_attribute_values(
+						":expires_at",
+						AttributeValue::N(expires_at.to_string()),
+					)
+			} else {
+				request.update_expression("ADD #value :by")
+			};
+
+			match request.send().await {
+				Ok(response) => {
+					return response
+						.attributes
+						.and_then(|mut attributes| attributes.remove(&self.value_attribute))
+						.ok_or(Error::InvalidDataFormat)?
+						.as_n()
+						.map_err(|_| Error::InvalidDataFormat)?
+						.parse()
+						.map_err(|_| Error::InvalidDataFormat);
+				},
+				Err(SdkError::ServiceError(error))
+					if error.err().is_conditional_check_failed_exception() => {},
+				Err(error) => return Err(error.into()),
+			}
+
+			// The counter has expired but hasn't been deleted yet, so start it over (unless someone else beat us to it).
+			let reset = self
+				.client
+				.put_item()
+				.table_name(&self.table)
+				.item(self.key_attribute.clone(), AttributeValue::S(key.clone()))
+				.item(
+					self.value_attribute.clone(),
+					AttributeValue::N(by.to_string()),
+				)
+				.item(
+					self.expiration_attribute.clone(),
+					expires_at.map_or(AttributeValue::Null(true), |expires_at| {
+						AttributeValue::N(expires_at.to_string())
+					}),
+				)
+				.condition_expression("#expires_at <= :now")
+				.expression_attribute_names("#expires_at", &self.expiration_attribute)
+				.expression_attribute_values(":now", cutoff.clone())
+				.send()
+				.await;
+
+			match reset {
+				Ok(_) => return Ok(by),
+				Err(SdkError::ServiceError(error))
+					if error.err().is_conditional_check_failed_exception() => {},
+				Err(error) => return Err(error.into()),
+			}
+		}
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		let cutoff = self
+			.expiry_cutoff()
+			.duration_since(UNIX_EPOCH)
+			.unwrap()
+			.as_secs();
+
+		let request = self
+			.client
+			.update_item()
+			.table_name(&self.table)
+			.key(
+				&self.key_attribute,
 				AttributeValue::S(format!("{}{key}", self.prefix)),
 			)
-			.item(
-				self.value_attribute.clone(),
-				AttributeValue::B(Blob::new(bitcode::serialize(value)?)),
+			.condition_expression(
+				"attribute_exists(#key) AND (attribute_not_exists(#expires_at) OR attribute_type(#expires_at, :null) OR #expires_at > :now)",
 			)
-			.item(
-				self.expiration_attribute.clone(),
-				expires_at.map_or(AttributeValue::Null(true), |expires_at| {
-					AttributeValue::N(
-						expires_at
-							.duration_since(SystemTime::UNIX_EPOCH)
-							.unwrap()
-							.as_secs()
-							.to_string(),
+			.expression_attribute_names("#key", &self.key_attribute)
+			.expression_attribute_names("#expires_at", &self.expiration_attribute)
+			.expression_attribute_names("#expires_at_ms", &self.expiration_millis_attribute)
+			.expression_attribute_values(":null", AttributeValue::S("NULL".to_string()))
+			.expression_attribute_values(":now", AttributeValue::N(cutoff.to_string()));
+
+		let request = match expiry {
+			Some(expiry) => {
+				let expires_at = SystemTime::now() + expiry;
+				let millis = expires_at.duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+				request
+					.update_expression(
+						"SET #expires_at = :expires_at, #expires_at_ms = :expires_at_ms",
 					)
-				}),
+					.expression_attribute_values(
+						":expires_at",
+						AttributeValue::N(expiration_timestamp(expires_at).to_string()),
+					)
+					.expression_attribute_values(
+						":expires_at_ms",
+						AttributeValue::N(millis.to_string()),
+					)
+			},
+			None => request
+				.update_expression("SET #expires_at = :never REMOVE #expires_at_ms")
+				.expression_attribute_values(":never", AttributeValue::Null(true)),
+		};
+
+		match request.send().await {
+			Ok(_) => Ok(true),
+			Err(SdkError::ServiceError(error))
+				if error.err().is_conditional_check_failed_exception() =>
+			{
+				Ok(false)
+			},
+			Err(error) => Err(error.into()),
+		}
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		let response = self
+			.client
+			.delete_item()
+			.table_name(&self.table)
+			.key(
+				&self.key_attribute,
+				AttributeValue::S(format!("{}{key}", self.prefix)),
 			)
+			.return_values(ReturnValue::AllOld)
 			.send()
 			.await?;
 
-		Ok(())
+		Ok(response.attributes.is_some())
 	}
 
-	async fn forget(&mut self, key: &str) -> Result<(), Self::Error> {
-		self.client
+	async fn forget_many(&mut self, keys: &[&str]) -> Result<(), Self::Error> {
+		// Batch writes reject duplicate keys, so each key is only removed once.
+		let requests = keys
+			.iter()
+			.collect::<BTreeSet<_>>()
+			.into_iter()
+			.map(|key| {
+				let delete = DeleteRequest::builder()
+					.key(
+						&self.key_attribute,
+						AttributeValue::S(format!("{}{key}", self.prefix)),
+					)
+					.build()?;
+
+				Ok(WriteRequest::builder().delete_request(delete).build())
+			})
+			.collect::<Result<Vec<_>, BuildError>>()?;
+
+		self.batch_write(requests).await
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		// Keys can only be matched by prefix with a filter, so this scans the whole table regardless of `flush_behavior`.
+		self.scan_delete(&format!("{}{prefix}", self.prefix)).await
+	}
+
+	async fn forget_if<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+	) -> Result<bool, Self::Error> {
+		let expected = schema::wrap(self.schema_version, self.format.serialize(expected)?);
+		let cutoff = self
+			.expiry_cutoff()
+			.duration_since(UNIX_EPOCH)
+			.unwrap()
+			.as_secs();
+
+		let response = self
+			.client
 			.delete_item()
 			.table_name(&self.table)
-			.key(&self.key_attribute, AttributeValue::S(key.to_string()))
+			.key(
+				&self.key_attribute,
+				AttributeValue::S(format!("{}{key}", self.prefix)),
+			)
+			.condition_expression(
+				"#value = :expected AND (attribute_not_exists(#expires_at) OR attribute_type(#expires_at, :null) OR #expires_at > :now)",
+			)
+			.expression_attribute_names("#value", &self.value_attribute)
+			.expression_attribute_names("#expires_at", &self.expiration_attribute)
+			.expression_attribute_values(":expected", AttributeValue::B(Blob::new(expected)))
+			.expression_attribute_values(":null", AttributeValue::S("NULL".to_string()))
+			.expression_attribute_values(":now", AttributeValue::N(cutoff.to_string()))
 			.send()
-			.await?;
+			.await;
 
-		Ok(())
+		match response {
+			Ok(_) => Ok(true),
+			Err(SdkError::ServiceError(error))
+				if error.err().is_conditional_check_failed_exception() =>
+			{
+				Ok(false)
+			},
+			Err(error) => Err(error.into()),
+		}
 	}
 
 	async fn flush(&mut self) -> Result<(), Self::Error> {
-		Err(Error::FlushNotSupported)
+		match self.flush_behavior {
+			FlushBehavior::ScanDelete => self.scan_delete(&self.prefix).await,
+			FlushBehavior::Error => Err(Error::FlushNotSupported),
+			FlushBehavior::Noop => Ok(()),
+		}
+	}
+}
+
+impl Error {
+	/// Whether `DynamoDB` couldn't be reached, or took too long to respond.
+	const fn is_unavailable(&self) -> bool {
+		match self {
+			Self::GetItem(error) => is_aws_unavailable(error),
+			Self::PutItem(error) => is_aws_unavailable(error),
+			Self::UpdateItem(error) => is_aws_unavailable(error),
+			Self::DeleteItem(error) => is_aws_unavailable(error),
+			Self::Scan(error) => is_aws_unavailable(error),
+			Self::BatchGetItem(error) => is_aws_unavailable(error),
+			Self::BatchWriteItem(error) => is_aws_unavailable(error),
+			_ => false,
+		}
 	}
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-	#[error("DynamoDB does not support flushing the cache.")]
+	#[error("flushing is disabled for this DynamoDB cache.")]
 	FlushNotSupported,
 	#[error("the stored data was on an unexpected format.")]
 	InvalidDataFormat,
+	#[error("the `{attribute}` attribute of `{key}` has an unexpected type `{found}`.")]
+	UnexpectedAttributeType {
+		key: String,
+		attribute: String,
+		found: &'static str,
+	},
 	#[error(transparent)]
 	GetItem(
 		#[from]
@@ -185,6 +1074,14 @@ pub enum Error {
 		>,
 	),
 	#[error(transparent)]
+	UpdateItem(
+		#[from]
+		aws_smithy_runtime_api::client::result::SdkError<
+			aws_sdk_dynamodb::operation::update_item::UpdateItemError,
+			aws_smithy_runtime_api::client::orchestrator::HttpResponse,
+		>,
+	),
+	#[error(transparent)]
 	DeleteItem(
 		#[from]
 		aws_smithy_runtime_api::client::result::SdkError<
@@ -193,7 +1090,37 @@ pub enum Error {
 		>,
 	),
 	#[error(transparent)]
-	Serialization(#[from] bitcode::Error),
+	Scan(
+		#[from]
+		aws_smithy_runtime_api::client::result::SdkError<
+			aws_sdk_dynamodb::operation::scan::ScanError,
+			aws_smithy_runtime_api::client::orchestrator::HttpResponse,
+		>,
+	),
+	#[error(transparent)]
+	BatchGetItem(
+		#[from]
+		aws_smithy_runtime_api::client::result::SdkError<
+			aws_sdk_dynamodb::operation::batch_get_item::BatchGetItemError,
+			aws_smithy_runtime_api::client::orchestrator::HttpResponse,
+		>,
+	),
+	#[error(transparent)]
+	BatchWriteItem(
+		#[from]
+		aws_smithy_runtime_api::client::result::SdkError<
+			aws_sdk_dynamodb::operation::batch_write_item::BatchWriteItemError,
+			aws_smithy_runtime_api::client::orchestrator::HttpResponse,
+		>,
+	),
+	#[error(transparent)]
+	Build(#[from] BuildError),
+	#[error(transparent)]
+	Serialization(#[from] format::Error),
+	#[error(transparent)]
+	Json(#[from] serde_json::Error),
+	#[error(transparent)]
+	Overflow(#[from] Overflow),
 }
 
 #[cfg(test)]
@@ -226,4 +1153,53 @@ mod tests {
 		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
 		assert!(!cache.has("foo").await.unwrap());
 	}
+
+	#[tokio::test]
+	async fn test_dynamodb_prefixed_forget() {
+		let mut cache = Cache::<DynamoDBDriver>::new(Config {
+			prefix: "amnesia_test:".to_string(),
+			..Config::default()
+		})
+		.await
+		.unwrap();
+
+		cache
+			.put("foo", &"bar", Duration::from_secs(10))
+			.await
+			.unwrap();
+		assert!(cache.forget("foo").await.unwrap());
+
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+	}
+
+	#[test]
+	fn test_dynamodb_expiry_window() {
+		let now = UNIX_EPOCH + Duration::from_millis(1_000_500);
+
+		// Partial seconds round up, so native TTL never deletes an item early.
+		assert_eq!(expiration_timestamp(now), 1001);
+		assert_eq!(
+			expiration_timestamp(UNIX_EPOCH + Duration::from_secs(1000)),
+			1000
+		);
+
+		let expires_at = Duration::from_secs(expiration_timestamp(now + Duration::from_secs(2)));
+		assert!(!is_expired(expires_at, now));
+		assert!(!is_expired(expires_at, now + Duration::from_secs(2)));
+
+		// Past its expiration but before DynamoDB gets around to deleting it, the item is hidden from reads.
+		assert!(is_expired(expires_at, now + Duration::from_secs(3)));
+		assert!(is_expired(expires_at, now + Duration::from_secs(172_799)));
+	}
+
+	#[test]
+	fn test_dynamodb_millisecond_expiry() {
+		let now = UNIX_EPOCH + Duration::from_secs(1000);
+		let expires_at = (now + Duration::from_millis(1500))
+			.duration_since(UNIX_EPOCH)
+			.unwrap();
+
+		assert!(!is_expired(expires_at, now + Duration::from_millis(1499)));
+		assert!(is_expired(expires_at, now + Duration::from_millis(1500)));
+	}
 }
@@ -0,0 +1,441 @@
+use super::{format::Raw, Driver, EntryMeta, GetOutcome, Overflow, PoolStatus, Ttl};
+use chacha20poly1305::{
+	aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+	ChaCha20Poly1305, Nonce,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	fmt,
+	time::{Duration, SystemTime},
+};
+
+/// The size (in bytes) of the random nonce stored in front of every value.
+const NONCE_SIZE: usize = 12;
+
+pub struct Config<D: Driver> {
+	/// The config for the driver that stores the encrypted values.
+	pub inner: D::Config,
+	/// The 256-bit key values are encrypted with. Changing it makes every existing entry unreadable.
+	pub key: [u8; 32],
+}
+
+/// A builder for [`Config`]. There's no default encryption key, so it has to be given up front.
+#[must_use]
+pub struct ConfigBuilder<D: Driver> {
+	config: Config<D>,
+}
+
+impl<D: Driver> Config<D>
+where
+	D::Config: Default,
+{
+	/// Start building a config that encrypts values with `key`, wrapping the inner driver's default config.
+	pub fn builder(key: [u8; 32]) -> ConfigBuilder<D> {
+		ConfigBuilder {
+			config: Self {
+				key,
+				inner: D::Config::default(),
+			},
+		}
+	}
+}
+
+impl<D: Driver> ConfigBuilder<D> {
+	/// Set [`Config::inner`].
+	pub fn inner(mut self, inner: impl Into<D::Config>) -> Self {
+		self.config.inner = inner.into();
+
+		self
+	}
+
+	/// Finish building the config.
+	#[must_use]
+	#[allow(clippy::missing_const_for_fn)]
+	pub fn build(self) -> Config<D> {
+		self.config
+	}
+}
+
+impl<D: Driver> fmt::Debug for Config<D>
+where
+	D::Config: fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Config")
+			.field("inner", &self.inner)
+			.finish_non_exhaustive()
+	}
+}
+
+#[derive(Clone)]
+#[allow(clippy::module_name_repetitions)]
+/// A driver that encrypts values with ChaCha20-Poly1305 before handing them to another driver.
+///
+/// Each value is encrypted with a random nonce (stored in front of it) and bound to its key, so a value copied to a
+/// different key, tampered with or read with the wrong encryption key fails with [`Error::Decryption`]. Entries
+/// written through this driver can only be read back through it. Since equal values encrypt differently,
+/// [`increment`](Driver::increment), [`compare_and_swap`](Driver::compare_and_swap) and [`forget_if`](Driver::forget_if)
+/// read the value before writing instead of being atomic.
+pub struct EncryptedDriver<D: Driver> {
+	inner: D,
+	cipher: ChaCha20Poly1305,
+}
+
+impl<D: Driver + fmt::Debug> fmt::Debug for EncryptedDriver<D> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("EncryptedDriver")
+			.field("inner", &self.inner)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<D: Driver> EncryptedDriver<D> {
+	/// Serialize and encrypt a value, bound to the key it's stored under.
+	///
+	/// The bytes are wrapped in [`Raw`], so drivers that store values through a [`Format`](super::Format) keep them
+	/// as-is instead of serializing them a second time.
+	fn encrypt<T: Serialize>(&self, key: &str, value: &T) -> Result<Raw, Error<D::Error>> {
+		let data = bitcode::serialize(value)?;
+		let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+		let ciphertext = self
+			.cipher
+			.encrypt(
+				&nonce,
+				Payload {
+					msg: &data,
+					aad: key.as_bytes(),
+				},
+			)
+			.map_err(|_| Error::Encryption)?;
+
+		let mut encrypted = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+		encrypted.extend_from_slice(&nonce);
+		encrypted.extend_from_slice(&ciphertext);
+
+		Ok(Raw(encrypted))
+	}
+
+	/// Decrypt a stored value, returning the serialized bytes.
+	fn decrypt(&self, key: &str, data: &[u8]) -> Result<Vec<u8>, Error<D::Error>> {
+		if data.len() < NONCE_SIZE {
+			return Err(Error::Decryption);
+		}
+		let (nonce, ciphertext) = data.split_at(NONCE_SIZE);
+
+		self.cipher
+			.decrypt(
+				Nonce::from_slice(nonce),
+				Payload {
+					msg: ciphertext,
+					aad: key.as_bytes(),
+				},
+			)
+			.map_err(|_| Error::Decryption)
+	}
+
+	/// Decrypt and deserialize a stored value.
+	fn decode<T: DeserializeOwned>(&self, key: &str, data: &[u8]) -> Result<T, Error<D::Error>> {
+		Ok(bitcode::deserialize(&self.decrypt(key, data)?)?)
+	}
+}
+
+impl<D: Driver> Driver for EncryptedDriver<D> {
+	type Error = Error<D::Error>;
+	type Config = Config<D>;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		Ok(Self {
+			cipher: ChaCha20Poly1305::new(&config.key.into()),
+			inner: D::new(config.inner).await.map_err(Error::Driver)?,
+		})
+	}
+
+	fn prefix(&self) -> &str {
+		self.inner.prefix()
+	}
+
+	fn pool_status(&self) -> Option<PoolStatus> {
+		self.inner.pool_status()
+	}
+
+	fn is_unavailable(error: &Self::Error) -> bool {
+		matches!(error, Error::Driver(error) if D::is_unavailable(error))
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		let Some(Raw(data)) = self.inner.get::<Raw>(key).await.map_err(Error::Driver)? else {
+			return Ok(None);
+		};
+
+		Ok(Some(self.decode(key, &data)?))
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		Ok(
+			match self
+				.inner
+				.try_get::<Raw>(key)
+				.await
+				.map_err(Error::Driver)?
+			{
+				GetOutcome::Hit(Raw(data)) => {
+					// Values that can't be decrypted are an error, since their bytes can't be trusted.
+					let data = self.decrypt(key, &data)?;
+
+					bitcode::deserialize(&data).map_or(GetOutcome::Corrupt(data), GetOutcome::Hit)
+				},
+				GetOutcome::Miss => GetOutcome::Miss,
+				GetOutcome::Corrupt(data) => GetOutcome::Corrupt(data),
+			},
+		)
+	}
+
+	async fn get_many<T: DeserializeOwned + Send>(
+		&self,
+		keys: &[&str],
+	) -> Result<Vec<Option<T>>, Self::Error> {
+		let mut values = Vec::with_capacity(keys.len());
+		for (key, data) in keys.iter().zip(
+			self.inner
+				.get_many::<Raw>(keys)
+				.await
+				.map_err(Error::Driver)?,
+		) {
+			values.push(data.map(|Raw(data)| self.decode(key, &data)).transpose()?);
+		}
+
+		Ok(values)
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		self.inner.has(key).await.map_err(Error::Driver)
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		self.inner.ttl(key).await.map_err(Error::Driver)
+	}
+
+	async fn metadata(&self, key: &str) -> Result<Option<EntryMeta>, Self::Error> {
+		self.inner.metadata(key).await.map_err(Error::Driver)
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		self.inner.keys(prefix).await.map_err(Error::Driver)
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		let data = self.encrypt(key, value)?;
+
+		self.inner
+			.put(key, &data, expiry)
+			.await
+			.map_err(Error::Driver)
+	}
+
+	async fn put_until<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		deadline: SystemTime,
+	) -> Result<(), Self::Error> {
+		let data = self.encrypt(key, value)?;
+
+		self.inner
+			.put_until(key, &data, deadline)
+			.await
+			.map_err(Error::Driver)
+	}
+
+	async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		expiry: Option<Duration>,
+	) -> Result<T, Self::Error> {
+		let data = self.encrypt(key, &value)?;
+		let cached = self
+			.inner
+			.get_or_put(key, data.clone(), expiry)
+			.await
+			.map_err(Error::Driver)?;
+
+		// The nonce is random, so the stored bytes only match ours if our value was the one stored.
+		if cached == data {
+			return Ok(value);
+		}
+
+		self.decode(key, &cached.0)
+	}
+
+	async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<Option<T>, Self::Error> {
+		let data = self.encrypt(key, value)?;
+		let previous = self
+			.inner
+			.replace::<Raw>(key, &data, expiry)
+			.await
+			.map_err(Error::Driver)?;
+
+		previous
+			.map(|Raw(data)| self.decode(key, &data))
+			.transpose()
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		let data = self.encrypt(key, value)?;
+
+		self.inner
+			.add(key, &data, expiry)
+			.await
+			.map_err(Error::Driver)
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		// Keep the remaining lifetime of an existing counter, since the expiry only applies when it's created.
+		let (value, expiry) = match self.inner.ttl(key).await.map_err(Error::Driver)? {
+			None => (by, expiry),
+			Some(ttl) => (
+				self.get::<i64>(key)
+					.await?
+					.unwrap_or_default()
+					.checked_add(by)
+					.ok_or(Overflow)?,
+				match ttl {
+					Ttl::Forever => None,
+					Ttl::In(remaining) => Some(remaining),
+				},
+			),
+		};
+
+		self.put(key, &value, expiry).await?;
+
+		Ok(value)
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		self.inner.touch(key, expiry).await.map_err(Error::Driver)
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		self.inner.forget(key).await.map_err(Error::Driver)
+	}
+
+	async fn forget_many(&mut self, keys: &[&str]) -> Result<(), Self::Error> {
+		self.inner.forget_many(keys).await.map_err(Error::Driver)
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		self.inner
+			.forget_prefix(prefix)
+			.await
+			.map_err(Error::Driver)
+	}
+
+	async fn purge_expired(&mut self) -> Result<usize, Self::Error> {
+		self.inner.purge_expired().await.map_err(Error::Driver)
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		self.inner.flush().await.map_err(Error::Driver)
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error<E> {
+	#[error(transparent)]
+	Driver(E),
+	#[error(transparent)]
+	Serialization(#[from] bitcode::Error),
+	#[error("failed to encrypt value")]
+	Encryption,
+	#[error("failed to decrypt value (it was tampered with, or encrypted with a different key)")]
+	Decryption,
+	#[error(transparent)]
+	Overflow(#[from] Overflow),
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+	use super::*;
+	use crate::drivers::MemoryDriver;
+
+	fn config(key: [u8; 32]) -> Config<MemoryDriver> {
+		Config {
+			key,
+			inner: crate::drivers::memory::Config::default(),
+		}
+	}
+
+	#[tokio::test]
+	async fn test_encrypted_driver() {
+		let mut driver = EncryptedDriver::new(config([7; 32])).await.unwrap();
+
+		driver.put("foo", &"bar", None).await.unwrap();
+		assert_eq!(
+			driver.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+
+		let Raw(stored) = driver.inner.get::<Raw>("foo").await.unwrap().unwrap();
+		assert!(!stored.windows(3).any(|window| window == b"bar"));
+
+		assert!(driver.forget_if("foo", &"bar".to_string()).await.unwrap());
+		assert!(!driver.has("foo").await.unwrap());
+
+		assert_eq!(driver.increment("hits", 2, None).await.unwrap(), 2);
+		assert_eq!(driver.increment("hits", 3, None).await.unwrap(), 5);
+	}
+
+	#[tokio::test]
+	async fn test_encrypted_rejects_tampering() {
+		let mut driver = EncryptedDriver::new(config([7; 32])).await.unwrap();
+		driver.put("foo", &"bar", None).await.unwrap();
+
+		let Raw(mut stored) = driver.inner.get::<Raw>("foo").await.unwrap().unwrap();
+		*stored.last_mut().unwrap() ^= 1;
+		driver.inner.put("foo", &Raw(stored), None).await.unwrap();
+		assert!(matches!(
+			driver.get::<String>("foo").await,
+			Err(Error::Decryption)
+		));
+
+		// Values can't be read with a different key, or moved to a different cache key.
+		driver.put("foo", &"bar", None).await.unwrap();
+		let Raw(stored) = driver.inner.get::<Raw>("foo").await.unwrap().unwrap();
+
+		let other = EncryptedDriver {
+			inner: driver.inner.clone(),
+			cipher: ChaCha20Poly1305::new(&[8; 32].into()),
+		};
+		assert!(matches!(
+			other.get::<String>("foo").await,
+			Err(Error::Decryption)
+		));
+
+		driver.inner.put("baz", &Raw(stored), None).await.unwrap();
+		assert!(matches!(
+			driver.get::<String>("baz").await,
+			Err(Error::Decryption)
+		));
+	}
+}
@@ -0,0 +1,444 @@
+#[cfg(feature = "format")]
+use super::Format;
+use super::{Driver, EntryMeta, GetOutcome, PoolStatus, Ttl};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	fmt,
+	sync::{Arc, Mutex, PoisonError},
+	time::{Duration, Instant, SystemTime},
+};
+
+pub struct Config<D: Driver> {
+	/// The config for the driver that's used while its backend is reachable.
+	pub inner: D::Config,
+	/// How long to stop calling the inner driver after its backend turns out to be unavailable, so every operation
+	/// doesn't have to wait for it to time out. With zero, every operation tries the backend again.
+	pub retry_after: Duration,
+}
+
+config_builder! {
+	<D: Driver>
+	inner: D::Config,
+	retry_after: Duration,
+}
+
+impl<D: Driver> fmt::Debug for Config<D>
+where
+	D::Config: fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Config")
+			.field("inner", &self.inner)
+			.field("retry_after", &self.retry_after)
+			.finish()
+	}
+}
+
+impl<D: Driver> Default for Config<D>
+where
+	D::Config: Default,
+{
+	fn default() -> Self {
+		Self {
+			inner: D::Config::default(),
+			retry_after: Duration::from_secs(5),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+/// A driver that keeps working (as if it were empty) while another driver's backend is unreachable.
+///
+/// Operations that fail because the backend couldn't be reached (see [`Driver::is_unavailable`]) don't return an
+/// error: reads are misses, and writes and removals do nothing (and report that nothing was stored or removed). Any
+/// other error, like a value that can't be deserialized, is returned as usual. After such a failure, the inner driver
+/// isn't called at all for `retry_after`, so a backend that's down doesn't slow every request down with a timeout.
+/// Clones share this state.
+///
+/// Only drivers that tell these errors apart benefit from it: Redis, `DynamoDB`, S3, Memcached and the database
+/// driver, boxed drivers backed by one of them, and the drivers wrapping them. The memory, file, Moka, `SQLite` and
+/// recording drivers never report being unavailable, since they don't depend on a backend that can go away.
+pub struct FallbackDriver<D: Driver> {
+	inner: D,
+	retry_after: Duration,
+	/// Until when the inner driver is skipped, after its backend was last found to be unavailable.
+	skip_until: Arc<Mutex<Option<Instant>>>,
+}
+
+impl<D: Driver> FallbackDriver<D> {
+	/// The driver used while its backend is reachable.
+	pub const fn inner(&self) -> &D {
+		&self.inner
+	}
+
+	/// Whether the backend was found to be unavailable recently enough that the inner driver should be skipped.
+	fn is_skipping(&self) -> bool {
+		self.skip_until
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.is_some_and(|until| Instant::now() < until)
+	}
+
+	/// Replace the result of an operation with `fallback` if it failed because the backend is unavailable.
+	fn fall_back<T>(&self, result: Result<T, D::Error>, fallback: T) -> Result<T, D::Error> {
+		match result {
+			Err(error) if D::is_unavailable(&error) => {
+				*self
+					.skip_until
+					.lock()
+					.unwrap_or_else(PoisonError::into_inner) = Some(Instant::now() + self.retry_after);
+
+				Ok(fallback)
+			},
+			result => result,
+		}
+	}
+}
+
+impl<D: Driver> Driver for FallbackDriver<D> {
+	type Error = D::Error;
+	type Config = Config<D>;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		Ok(Self {
+			inner: D::new(config.inner).await?,
+			retry_after: config.retry_after,
+			skip_until: Arc::default(),
+		})
+	}
+
+	fn prefix(&self) -> &str {
+		self.inner.prefix()
+	}
+
+	fn pool_status(&self) -> Option<PoolStatus> {
+		self.inner.pool_status()
+	}
+
+	fn is_unavailable(error: &Self::Error) -> bool {
+		D::is_unavailable(error)
+	}
+
+	#[cfg(feature = "format")]
+	fn format(&self) -> Option<Format> {
+		self.inner.format()
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		if self.is_skipping() {
+			return Ok(None);
+		}
+
+		self.fall_back(self.inner.get(key).await, None)
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		if self.is_skipping() {
+			return Ok(GetOutcome::Miss);
+		}
+
+		self.fall_back(self.inner.try_get(key).await, GetOutcome::Miss)
+	}
+
+	async fn get_many<T: DeserializeOwned + Send>(
+		&self,
+		keys: &[&str],
+	) -> Result<Vec<Option<T>>, Self::Error> {
+		let misses = || keys.iter().map(|_| None).collect();
+
+		if self.is_skipping() {
+			return Ok(misses());
+		}
+
+		self.fall_back(self.inner.get_many(keys).await, misses())
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		if self.is_skipping() {
+			return Ok(false);
+		}
+
+		self.fall_back(self.inner.has(key).await, false)
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		if self.is_skipping() {
+			return Ok(None);
+		}
+
+		self.fall_back(self.inner.ttl(key).await, None)
+	}
+
+	async fn metadata(&self, key: &str) -> Result<Option<EntryMeta>, Self::Error> {
+		if self.is_skipping() {
+			return Ok(None);
+		}
+
+		self.fall_back(self.inner.metadata(key).await, None)
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		if self.is_skipping() {
+			return Ok(Vec::new());
+		}
+
+		self.fall_back(self.inner.keys(prefix).await, Vec::new())
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		if self.is_skipping() {
+			return Ok(());
+		}
+
+		let result = self.inner.put(key, value, expiry).await;
+		self.fall_back(result, ())
+	}
+
+	async fn put_until<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		deadline: SystemTime,
+	) -> Result<(), Self::Error> {
+		if self.is_skipping() {
+			return Ok(());
+		}
+
+		let result = self.inner.put_until(key, value, deadline).await;
+		self.fall_back(result, ())
+	}
+
+	async fn put_many<T: Serialize + Sync>(
+		&mut self,
+		entries: &[(&str, &T)],
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		if self.is_skipping() {
+			return Ok(());
+		}
+
+		let result = self.inner.put_many(entries, expiry).await;
+		self.fall_back(result, ())
+	}
+
+	async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		expiry: Option<Duration>,
+	) -> Result<T, Self::Error> {
+		if self.is_skipping() {
+			return Ok(value);
+		}
+
+		// The inner driver's `get_or_put` takes the value, so it couldn't be returned if the backend is unavailable.
+		// Adding it instead keeps it around, and only one of several concurrent callers still stores theirs.
+		let result = self.inner.add(key, &value, expiry).await;
+		if self.fall_back(result, true)? {
+			return Ok(value);
+		}
+
+		let result = self.inner.get(key).await;
+		Ok(self.fall_back(result, None)?.unwrap_or(value))
+	}
+
+	async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<Option<T>, Self::Error> {
+		if self.is_skipping() {
+			return Ok(None);
+		}
+
+		let result = self.inner.replace(key, value, expiry).await;
+		self.fall_back(result, None)
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		if self.is_skipping() {
+			return Ok(false);
+		}
+
+		let result = self.inner.add(key, value, expiry).await;
+		self.fall_back(result, false)
+	}
+
+	async fn compare_and_swap<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+		new: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		if self.is_skipping() {
+			return Ok(false);
+		}
+
+		let result = self
+			.inner
+			.compare_and_swap(key, expected, new, expiry)
+			.await;
+		self.fall_back(result, false)
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		// Like a counter that didn't exist yet, since there's no way to know its value.
+		if self.is_skipping() {
+			return Ok(by);
+		}
+
+		let result = self.inner.increment(key, by, expiry).await;
+		self.fall_back(result, by)
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		if self.is_skipping() {
+			return Ok(false);
+		}
+
+		let result = self.inner.touch(key, expiry).await;
+		self.fall_back(result, false)
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		if self.is_skipping() {
+			return Ok(false);
+		}
+
+		let result = self.inner.forget(key).await;
+		self.fall_back(result, false)
+	}
+
+	async fn forget_many(&mut self, keys: &[&str]) -> Result<(), Self::Error> {
+		if self.is_skipping() {
+			return Ok(());
+		}
+
+		let result = self.inner.forget_many(keys).await;
+		self.fall_back(result, ())
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		if self.is_skipping() {
+			return Ok(());
+		}
+
+		let result = self.inner.forget_prefix(prefix).await;
+		self.fall_back(result, ())
+	}
+
+	async fn forget_if<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+	) -> Result<bool, Self::Error> {
+		if self.is_skipping() {
+			return Ok(false);
+		}
+
+		let result = self.inner.forget_if(key, expected).await;
+		self.fall_back(result, false)
+	}
+
+	async fn purge_expired(&mut self) -> Result<usize, Self::Error> {
+		if self.is_skipping() {
+			return Ok(0);
+		}
+
+		let result = self.inner.purge_expired().await;
+		self.fall_back(result, 0)
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		if self.is_skipping() {
+			return Ok(());
+		}
+
+		let result = self.inner.flush().await;
+		self.fall_back(result, ())
+	}
+}
+
+#[cfg(all(test, feature = "redis"))]
+mod tests {
+	use super::*;
+	use crate::{
+		drivers::{redis, RedisDriver},
+		Cache,
+	};
+
+	#[tokio::test]
+	async fn test_fallback_when_unavailable() {
+		// Nothing listens on port 1, so connecting is refused right away.
+		let mut cache = Cache::<FallbackDriver<RedisDriver>>::new(Config {
+			inner: redis::Config {
+				redis_url: "redis://127.0.0.1:1".to_string(),
+				..Default::default()
+			},
+			..Default::default()
+		})
+		.await
+		.unwrap();
+
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+		assert!(cache.driver.is_skipping());
+
+		cache
+			.put("foo", &"bar", Duration::from_secs(10))
+			.await
+			.unwrap();
+		assert!(!cache.has("foo").await.unwrap());
+		assert_eq!(
+			cache
+				.remember("foo", Duration::from_secs(10), 42)
+				.await
+				.unwrap(),
+			42
+		);
+
+		// Errors that aren't about reaching Redis are still returned.
+		assert!(!FallbackDriver::<RedisDriver>::is_unavailable(
+			&redis::Error::ValueTooLarge { size: 2, limit: 1 }
+		));
+	}
+
+	#[cfg(feature = "dynamic")]
+	#[tokio::test]
+	async fn test_fallback_when_boxed_driver_unavailable() {
+		use crate::drivers::DynDriver;
+
+		let driver = RedisDriver::new(redis::Config {
+			redis_url: "redis://127.0.0.1:1".to_string(),
+			..Default::default()
+		})
+		.await
+		.unwrap();
+		let cache = Cache::<FallbackDriver<Box<dyn DynDriver>>>::new(Config {
+			inner: Box::new(driver) as Box<dyn DynDriver>,
+			retry_after: Duration::from_secs(5),
+		})
+		.await
+		.unwrap();
+
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+		assert!(cache.driver.is_skipping());
+	}
+}
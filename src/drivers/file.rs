@@ -0,0 +1,716 @@
+#[cfg(feature = "stream")]
+use super::BlobReader;
+use super::{format, hash::stable_hash, Driver, Format, GetOutcome, Overflow, Ttl};
+#[cfg(feature = "stream")]
+use crate::StreamError;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	io,
+	path::{Path, PathBuf},
+	process,
+	sync::atomic::{AtomicU64, Ordering},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::fs;
+#[cfg(feature = "stream")]
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+
+/// Distinguishes the temporary files written by concurrent calls in the same process.
+static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone)]
+pub struct Config {
+	/// The directory entries are stored in. It's created if it doesn't exist yet.
+	pub directory: PathBuf,
+	pub prefix: String,
+	/// The format values are serialized with.
+	pub format: Format,
+}
+
+config_builder! {
+	directory: PathBuf,
+	prefix: String,
+	format: Format,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			prefix: String::new(),
+			format: Format::default(),
+			directory: std::env::temp_dir().join("amnesia"),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+/// A driver that stores each entry in its own file.
+///
+/// Files are named after a hash of the key, and hold the entry's expiry and key next to the serialized value. Expired
+/// entries are treated as misses until they're overwritten or flushed. Writes go through a temporary file that's
+/// renamed into place, so readers never see a partially written entry.
+///
+/// Note that `increment` and `touch` read the file before rewriting it, so they aren't atomic across processes.
+pub struct FileDriver {
+	directory: PathBuf,
+	prefix: String,
+	format: Format,
+}
+
+/// A stored entry.
+///
+/// On disk, it's laid out as its expiry (in milliseconds since the Unix epoch, or zero if it never expires), the length
+/// of its key, the key itself and then the serialized value. Numbers are stored as little-endian `u64` and `u32`.
+struct Entry {
+	expires_at: Option<SystemTime>,
+	data: Vec<u8>,
+}
+
+impl Entry {
+	fn is_expired(&self) -> bool {
+		self.expires_at
+			.is_some_and(|expires_at| expires_at <= SystemTime::now())
+	}
+
+	fn ttl(&self) -> Ttl {
+		self.expires_at.map_or(Ttl::Forever, |expires_at| {
+			Ttl::In(
+				expires_at
+					.duration_since(SystemTime::now())
+					.unwrap_or(Duration::ZERO),
+			)
+		})
+	}
+
+	fn encode(&self, key: &str) -> Vec<u8> {
+		let expires_at = self.expires_at.map_or(0, |expires_at| {
+			let millis = expires_at
+				.duration_since(UNIX_EPOCH)
+				.unwrap_or_default()
+				.as_millis();
+
+			u64::try_from(millis).unwrap_or(u64::MAX).max(1)
+		});
+		let key_len = u32::try_from(key.len()).expect("keys should be shorter than 4GiB");
+
+		let mut bytes = Vec::with_capacity(12 + key.len() + self.data.len());
+		bytes.extend_from_slice(&expires_at.to_le_bytes());
+		bytes.extend_from_slice(&key_len.to_le_bytes());
+		bytes.extend_from_slice(key.as_bytes());
+		bytes.extend_from_slice(&self.data);
+
+		bytes
+	}
+
+	/// Decode a stored entry, returning `None` if it's malformed or was stored under another key with the same hash.
+	fn decode(key: &str, bytes: &[u8]) -> Option<Self> {
+		let (expires_at, bytes) = bytes.split_first_chunk::<8>()?;
+		let (key_len, bytes) = bytes.split_first_chunk::<4>()?;
+		let (stored_key, data) =
+			bytes.split_at_checked(usize::try_from(u32::from_le_bytes(*key_len)).ok()?)?;
+
+		if stored_key != key.as_bytes() {
+			return None;
+		}
+
+		let expires_at = match u64::from_le_bytes(*expires_at) {
+			0 => None,
+			millis => Some(UNIX_EPOCH + Duration::from_millis(millis)),
+		};
+
+		Some(Self {
+			expires_at,
+			data: data.to_vec(),
+		})
+	}
+
+	/// The key a stored entry belongs to, or `None` if it's malformed.
+	fn stored_key(bytes: &[u8]) -> Option<&[u8]> {
+		let (key_len, bytes) = bytes.get(8..)?.split_first_chunk::<4>()?;
+
+		bytes.get(..usize::try_from(u32::from_le_bytes(*key_len)).ok()?)
+	}
+}
+
+impl FileDriver {
+	fn key(&self, key: &str) -> String {
+		format!("{}{key}", self.prefix)
+	}
+
+	fn path(&self, key: &str) -> PathBuf {
+		self.directory
+			.join(format!("{:016x}", stable_hash(key.as_bytes())))
+	}
+
+	/// Read the entry stored under a (prefixed) key, including it if it has expired.
+	async fn read(&self, key: &str) -> Result<Option<Entry>, Error> {
+		let Some(bytes) = read_if_exists(&self.path(key)).await? else {
+			return Ok(None);
+		};
+
+		Ok(Entry::decode(key, &bytes))
+	}
+
+	/// Read the entry stored under a (prefixed) key, treating expired entries as missing.
+	async fn read_fresh(&self, key: &str) -> Result<Option<Entry>, Error> {
+		Ok(self.read(key).await?.filter(|entry| !entry.is_expired()))
+	}
+
+	/// The paths of every stored entry, skipping the temporary files of writes that are still in progress.
+	async fn entry_paths(&self) -> Result<Vec<PathBuf>, Error> {
+		let mut entries = match fs::read_dir(&self.directory).await {
+			Ok(entries) => entries,
+			Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+			Err(error) => return Err(error.into()),
+		};
+
+		let mut paths = Vec::new();
+		while let Some(entry) = entries.next_entry().await? {
+			if entry.file_type().await?.is_file() && entry.path().extension().is_none() {
+				paths.push(entry.path());
+			}
+		}
+
+		Ok(paths)
+	}
+
+	/// A new temporary file to write an entry to before it's renamed to `path`.
+	fn temp_path(path: &Path) -> PathBuf {
+		path.with_extension(format!(
+			"{}-{}.tmp",
+			process::id(),
+			TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+		))
+	}
+
+	async fn write(&self, key: &str, entry: &Entry) -> Result<(), Error> {
+		let path = self.path(key);
+		let temp_path = Self::temp_path(&path);
+
+		fs::write(&temp_path, entry.encode(key)).await?;
+		if let Err(error) = fs::rename(&temp_path, &path).await {
+			fs::remove_file(&temp_path).await.ok();
+
+			return Err(error.into());
+		}
+
+		Ok(())
+	}
+}
+
+/// Write `header` followed by everything read from `reader` to a new file at `path`.
+#[cfg(feature = "stream")]
+async fn write_stream(
+	path: &Path,
+	header: &[u8],
+	mut reader: impl AsyncRead + Unpin,
+) -> Result<(), StreamError<Error>> {
+	let failed = |error: io::Error| StreamError::Cache(Error::Io(error));
+
+	let mut file = fs::File::create(path).await.map_err(failed)?;
+	file.write_all(header).await.map_err(failed)?;
+
+	let mut buffer = vec![0; 64 * 1024];
+	loop {
+		let read = reader.read(&mut buffer).await.map_err(StreamError::Read)?;
+		if read == 0 {
+			break;
+		}
+
+		file.write_all(&buffer[..read]).await.map_err(failed)?;
+	}
+
+	// Writes finish in the background, so make sure they're done before the file is renamed into place.
+	file.flush().await.map_err(failed)
+}
+
+/// Read a file, returning `None` if it doesn't exist (like when it's been removed since it was listed).
+async fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>, Error> {
+	match fs::read(path).await {
+		Ok(bytes) => Ok(Some(bytes)),
+		Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+		Err(error) => Err(error.into()),
+	}
+}
+
+impl Driver for FileDriver {
+	type Error = Error;
+	type Config = Config;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		fs::create_dir_all(&config.directory).await?;
+
+		Ok(Self {
+			directory: config.directory,
+			prefix: config.prefix,
+			format: config.format,
+		})
+	}
+
+	fn prefix(&self) -> &str {
+		&self.prefix
+	}
+
+	fn format(&self) -> Option<Format> {
+		Some(self.format)
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		let Some(entry) = self.read_fresh(&self.key(key)).await? else {
+			return Ok(None);
+		};
+
+		Ok(Some(self.format.deserialize(&entry.data)?))
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		let Some(entry) = self.read_fresh(&self.key(key)).await? else {
+			return Ok(GetOutcome::Miss);
+		};
+
+		Ok(self
+			.format
+			.deserialize(&entry.data)
+			.map_or(GetOutcome::Corrupt(entry.data), GetOutcome::Hit))
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		Ok(self.read_fresh(&self.key(key)).await?.is_some())
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		Ok(self
+			.read_fresh(&self.key(key))
+			.await?
+			.map(|entry| entry.ttl()))
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		let prefix = self.key(prefix.unwrap_or_default());
+
+		let mut keys = Vec::new();
+		for path in self.entry_paths().await? {
+			let Some(bytes) = read_if_exists(&path).await? else {
+				continue;
+			};
+			let Some(key) = Entry::stored_key(&bytes).and_then(|key| std::str::from_utf8(key).ok())
+			else {
+				continue;
+			};
+
+			if key.starts_with(&prefix)
+				&& Entry::decode(key, &bytes).is_some_and(|entry| !entry.is_expired())
+			{
+				keys.push(key[self.prefix.len()..].to_string());
+			}
+		}
+
+		Ok(keys)
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		let entry = Entry {
+			data: self.format.serialize(value)?,
+			expires_at: expiry.map(|expiry| SystemTime::now() + expiry),
+		};
+
+		self.write(&self.key(key), &entry).await
+	}
+
+	#[cfg(feature = "stream")]
+	async fn get_stream(&self, key: &str) -> Result<Option<BlobReader>, Self::Error> {
+		let key = self.key(key);
+
+		let mut file = match fs::File::open(self.path(&key)).await {
+			Ok(file) => file,
+			Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+			Err(error) => return Err(error.into()),
+		};
+
+		// Only the expiry and key are read up front, leaving the file at the start of the value for the caller.
+		let mut header = vec![0; 12 + key.len()];
+		match file.read_exact(&mut header).await {
+			Ok(_) => {},
+			Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+			Err(error) => return Err(error.into()),
+		}
+
+		Ok(Entry::decode(&key, &header)
+			.filter(|entry| !entry.is_expired())
+			.map(|_| Box::new(file) as BlobReader))
+	}
+
+	#[cfg(feature = "stream")]
+	async fn put_stream<R: AsyncRead + Send + Unpin>(
+		&mut self,
+		key: &str,
+		reader: R,
+		expiry: Option<Duration>,
+	) -> Result<(), StreamError<Self::Error>> {
+		let key = self.key(key);
+		let header = Entry {
+			data: Vec::new(),
+			expires_at: expiry.map(|expiry| SystemTime::now() + expiry),
+		}
+		.encode(&key);
+
+		let path = self.path(&key);
+		let temp_path = Self::temp_path(&path);
+
+		let written = match write_stream(&temp_path, &header, reader).await {
+			Ok(()) => fs::rename(&temp_path, &path)
+				.await
+				.map_err(|error| StreamError::Cache(error.into())),
+			Err(error) => Err(error),
+		};
+		if written.is_err() {
+			fs::remove_file(&temp_path).await.ok();
+		}
+
+		written
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		let key = self.key(key);
+
+		let (value, expires_at) = match self.read_fresh(&key).await? {
+			Some(entry) => (
+				self.format
+					.deserialize::<i64>(&entry.data)?
+					.checked_add(by)
+					.ok_or(Overflow)?,
+				entry.expires_at,
+			),
+			None => (by, expiry.map(|expiry| SystemTime::now() + expiry)),
+		};
+
+		let entry = Entry {
+			expires_at,
+			data: self.format.serialize(&value)?,
+		};
+		self.write(&key, &entry).await?;
+
+		Ok(value)
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		let key = self.key(key);
+
+		let Some(mut entry) = self.read_fresh(&key).await? else {
+			return Ok(false);
+		};
+
+		entry.expires_at = expiry.map(|expiry| SystemTime::now() + expiry);
+		self.write(&key, &entry).await?;
+
+		Ok(true)
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		let key = self.key(key);
+
+		// Only remove the file if it belongs to this key, and not to another one that hashes the same.
+		let Some(entry) = self.read(&key).await? else {
+			return Ok(false);
+		};
+
+		match fs::remove_file(self.path(&key)).await {
+			Ok(()) => Ok(!entry.is_expired()),
+			Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(false),
+			Err(error) => Err(error.into()),
+		}
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		let prefix = self.key(prefix);
+
+		// Files are named after a hash of their key, so every one of them has to be read to find the matching keys.
+		for path in self.entry_paths().await? {
+			let Some(bytes) = read_if_exists(&path).await? else {
+				continue;
+			};
+			if !Entry::stored_key(&bytes).is_some_and(|key| key.starts_with(prefix.as_bytes())) {
+				continue;
+			}
+
+			match fs::remove_file(path).await {
+				Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error.into()),
+				_ => {},
+			}
+		}
+
+		Ok(())
+	}
+
+	async fn purge_expired(&mut self) -> Result<usize, Self::Error> {
+		let mut removed = 0;
+		for path in self.entry_paths().await? {
+			let Some(bytes) = read_if_exists(&path).await? else {
+				continue;
+			};
+			let expired = Entry::stored_key(&bytes)
+				.and_then(|key| std::str::from_utf8(key).ok())
+				.and_then(|key| Entry::decode(key, &bytes))
+				.is_some_and(|entry| entry.is_expired());
+			if !expired {
+				continue;
+			}
+
+			match fs::remove_file(path).await {
+				Ok(()) => removed += 1,
+				Err(error) if error.kind() == io::ErrorKind::NotFound => {},
+				Err(error) => return Err(error.into()),
+			}
+		}
+
+		Ok(removed)
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		let mut entries = match fs::read_dir(&self.directory).await {
+			Ok(entries) => entries,
+			Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+			Err(error) => return Err(error.into()),
+		};
+
+		while let Some(entry) = entries.next_entry().await? {
+			if !entry.file_type().await?.is_file() {
+				continue;
+			}
+
+			match fs::remove_file(entry.path()).await {
+				Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error.into()),
+				_ => {},
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error(transparent)]
+	Io(#[from] io::Error),
+	#[error(transparent)]
+	Serialization(#[from] format::Error),
+	#[error(transparent)]
+	Overflow(#[from] Overflow),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Cache;
+
+	async fn cache(name: &str) -> Cache<FileDriver> {
+		let directory = std::env::temp_dir().join(format!("amnesia-{name}-{}", process::id()));
+		fs::remove_dir_all(&directory).await.ok();
+
+		Cache::new(Config {
+			directory,
+			..Default::default()
+		})
+		.await
+		.unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_file_driver() {
+		let mut cache = cache("driver").await;
+
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+		assert!(!cache.has("foo").await.unwrap());
+
+		cache
+			.put("foo", &"bar", Duration::from_secs(10))
+			.await
+			.unwrap();
+
+		assert_eq!(
+			cache.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+		assert!(cache.has("foo").await.unwrap());
+
+		assert!(cache.forget("foo").await.unwrap());
+		assert!(!cache.forget("foo").await.unwrap());
+
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+		assert!(!cache.has("foo").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_file_expiry() {
+		let mut cache = cache("expiry").await;
+
+		cache
+			.put("foo", &"bar", Duration::from_millis(50))
+			.await
+			.unwrap();
+		assert!(cache.has("foo").await.unwrap());
+
+		tokio::time::sleep(Duration::from_millis(100)).await;
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+		assert_eq!(cache.ttl("foo").await.unwrap(), None);
+	}
+
+	#[tokio::test]
+	async fn test_file_increment_and_touch() {
+		let mut cache = cache("increment").await;
+
+		assert_eq!(cache.increment("hits", 2).await.unwrap(), 2);
+		assert_eq!(cache.decrement("hits", 1).await.unwrap(), 1);
+		assert_eq!(cache.ttl("hits").await.unwrap(), Some(Ttl::Forever));
+
+		assert!(cache.touch("hits", Duration::from_secs(10)).await.unwrap());
+		assert!(matches!(cache.ttl("hits").await.unwrap(), Some(Ttl::In(_))));
+		assert_eq!(cache.get::<i64>("hits").await.unwrap(), Some(1));
+	}
+
+	#[tokio::test]
+	async fn test_file_flush() {
+		let mut cache = cache("flush").await;
+
+		cache.forever("foo", "bar").await.unwrap();
+		cache.forever("baz", "qux").await.unwrap();
+		cache.flush().await.unwrap();
+
+		assert!(!cache.has("foo").await.unwrap());
+		assert!(!cache.has("baz").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_file_forget_prefix() {
+		let mut cache = cache("forget-prefix").await;
+
+		cache.forever("user:1:posts", 1).await.unwrap();
+		cache.forever("user:1:profile", 2).await.unwrap();
+		cache.forever("user:12:posts", 3).await.unwrap();
+		cache.forget_prefix("user:1:").await.unwrap();
+
+		assert!(!cache.has("user:1:posts").await.unwrap());
+		assert!(!cache.has("user:1:profile").await.unwrap());
+		assert_eq!(cache.get::<i32>("user:12:posts").await.unwrap(), Some(3));
+	}
+
+	#[tokio::test]
+	async fn test_file_keys() {
+		let mut cache = cache("keys").await;
+
+		cache.forever("user:1", 1).await.unwrap();
+		cache.forever("post:1", 2).await.unwrap();
+		cache
+			.put("user:2", &3, Duration::from_millis(1))
+			.await
+			.unwrap();
+		tokio::time::sleep(Duration::from_millis(5)).await;
+
+		let mut keys = cache.keys(None).await.unwrap();
+		keys.sort();
+		assert_eq!(keys, ["post:1", "user:1"]);
+		assert_eq!(cache.keys(Some("user:")).await.unwrap(), ["user:1"]);
+	}
+
+	#[tokio::test]
+	async fn test_file_purge_expired() {
+		let mut cache = cache("purge-expired").await;
+
+		cache.forever("foo", 1).await.unwrap();
+		cache
+			.put("bar", &2, Duration::from_millis(1))
+			.await
+			.unwrap();
+		tokio::time::sleep(Duration::from_millis(5)).await;
+
+		assert_eq!(cache.purge_expired().await.unwrap(), 1);
+		assert_eq!(cache.purge_expired().await.unwrap(), 0);
+		assert_eq!(cache.get::<i32>("foo").await.unwrap(), Some(1));
+	}
+
+	#[tokio::test]
+	#[cfg(feature = "stream")]
+	async fn test_file_streaming() {
+		let mut cache = cache("streaming").await;
+		let blob = (0..200_000_u32)
+			.map(|i| u8::try_from(i % 251).unwrap())
+			.collect::<Vec<_>>();
+
+		assert!(cache.get_stream("blob").await.unwrap().is_none());
+
+		cache
+			.put_stream("blob", blob.as_slice(), Duration::from_secs(10))
+			.await
+			.unwrap();
+		assert!(matches!(cache.ttl("blob").await.unwrap(), Some(Ttl::In(_))));
+
+		let mut streamed = Vec::new();
+		cache
+			.get_stream("blob")
+			.await
+			.unwrap()
+			.unwrap()
+			.read_to_end(&mut streamed)
+			.await
+			.unwrap();
+		assert_eq!(streamed, blob);
+
+		cache
+			.put_stream("blob", blob.as_slice(), Duration::from_millis(1))
+			.await
+			.unwrap();
+		tokio::time::sleep(Duration::from_millis(5)).await;
+		assert!(cache.get_stream("blob").await.unwrap().is_none());
+	}
+
+	#[tokio::test]
+	#[cfg(feature = "memory")]
+	async fn test_file_restore_from_memory() {
+		let mut memory =
+			Cache::<crate::drivers::MemoryDriver>::new(crate::drivers::memory::Config::default())
+				.await
+				.unwrap();
+		memory.forever("foo", "bar").await.unwrap();
+		memory
+			.put("counter", &3_i64, Duration::from_secs(60))
+			.await
+			.unwrap();
+
+		let mut cache = cache("restore").await;
+		cache.restore(memory.dump().await.unwrap()).await.unwrap();
+
+		assert_eq!(
+			cache.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+		assert_eq!(cache.increment("counter", 1).await.unwrap(), 4);
+		assert!(matches!(
+			cache.ttl("counter").await.unwrap(),
+			Some(Ttl::In(_))
+		));
+	}
+
+	#[test]
+	fn test_file_entry_checks_key() {
+		let entry = Entry {
+			expires_at: None,
+			data: b"value".to_vec(),
+		};
+		let bytes = entry.encode("foo");
+
+		assert_eq!(Entry::decode("foo", &bytes).unwrap().data, b"value");
+		assert!(Entry::decode("bar", &bytes).is_none());
+		assert!(Entry::decode("foo", &bytes[..6]).is_none());
+	}
+}
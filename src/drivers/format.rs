@@ -0,0 +1,143 @@
+//! Serialization formats for byte-oriented drivers.
+//!
+//! Drivers that store raw bytes let you pick the format values are serialized with through their config. Values
+//! written in one format can't be read back in another, so changing it effectively invalidates the cache.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+mod php;
+mod raw;
+
+pub(crate) use raw::Raw;
+
+/// The format values are serialized with before they're stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+	/// A compact and fast binary format, only readable from Rust.
+	#[default]
+	Bitcode,
+	/// JSON, which can be inspected with other tools (like `redis-cli`) and read from other languages.
+	Json,
+	/// `MessagePack`, a compact binary format with implementations in most languages.
+	#[cfg(feature = "msgpack")]
+	MessagePack,
+	/// The format Laravel's cache stores values in, for sharing a Redis cache with PHP services: numbers as plain text,
+	/// and everything else with PHP's `serialize()`.
+	///
+	/// Only values JSON can represent are supported, so PHP objects can't be read, and structs are written as
+	/// associative arrays. To share keys as well, set the driver's prefix to Laravel's (the Redis connection prefix
+	/// followed by the cache prefix, like `laravel_database_laravel_cache_:`), and don't set a schema version.
+	Laravel,
+}
+
+impl Format {
+	/// Serialize a value into bytes.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the value can't be represented in this format.
+	pub fn serialize<T: Serialize>(self, value: &T) -> Result<Vec<u8>, Error> {
+		if let Some(data) = raw::as_bytes(value) {
+			return Ok(data);
+		}
+
+		match self {
+			Self::Bitcode => Ok(bitcode::serialize(value)?),
+			Self::Json => Ok(serde_json::to_vec(value)?),
+			#[cfg(feature = "msgpack")]
+			Self::MessagePack => Ok(rmp_serde::to_vec(value)?),
+			Self::Laravel => Ok(php::to_vec(value)?),
+		}
+	}
+
+	/// Deserialize a value from bytes.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the bytes aren't a valid value in this format.
+	pub fn deserialize<T: DeserializeOwned>(self, data: &[u8]) -> Result<T, Error> {
+		if let Some(value) = raw::from_bytes(data) {
+			return Ok(value);
+		}
+
+		match self {
+			Self::Bitcode => Ok(bitcode::deserialize(data)?),
+			Self::Json => Ok(serde_json::from_slice(data)?),
+			#[cfg(feature = "msgpack")]
+			Self::MessagePack => Ok(rmp_serde::from_slice(data)?),
+			Self::Laravel => Ok(php::from_slice(data)?),
+		}
+	}
+
+	/// The byte values stored with [`Cache::put_with_format`](crate::Cache::put_with_format) are prefixed with, so
+	/// they can be read back with the same format.
+	pub(crate) const fn marker(self) -> u8 {
+		match self {
+			Self::Bitcode => 0,
+			Self::Json => 1,
+			#[cfg(feature = "msgpack")]
+			Self::MessagePack => 2,
+			Self::Laravel => 3,
+		}
+	}
+
+	/// The format a value stored with [`Cache::put_with_format`](crate::Cache::put_with_format) was serialized with.
+	pub(crate) const fn from_marker(marker: u8) -> Option<Self> {
+		match marker {
+			0 => Some(Self::Bitcode),
+			1 => Some(Self::Json),
+			#[cfg(feature = "msgpack")]
+			2 => Some(Self::MessagePack),
+			3 => Some(Self::Laravel),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error(transparent)]
+	Bitcode(#[from] bitcode::Error),
+	#[error(transparent)]
+	Json(#[from] serde_json::Error),
+	#[cfg(feature = "msgpack")]
+	#[error(transparent)]
+	MessagePackEncode(#[from] rmp_serde::encode::Error),
+	#[cfg(feature = "msgpack")]
+	#[error(transparent)]
+	MessagePackDecode(#[from] rmp_serde::decode::Error),
+	#[error(transparent)]
+	Php(#[from] php::Error),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_format_roundtrip() {
+		let value = (String::from("amnesia"), 42_u32, vec![true, false]);
+
+		for format in [
+			Format::Bitcode,
+			Format::Json,
+			#[cfg(feature = "msgpack")]
+			Format::MessagePack,
+			Format::Laravel,
+		] {
+			let data = format.serialize(&value).unwrap();
+
+			assert_eq!(
+				format
+					.deserialize::<(String, u32, Vec<bool>)>(&data)
+					.unwrap(),
+				value
+			);
+		}
+
+		assert_eq!(
+			Format::Json.serialize(&value).unwrap(),
+			br#"["amnesia",42,[true,false]]"#
+		);
+	}
+}
@@ -0,0 +1,279 @@
+//! The format Laravel's Redis cache store uses: numbers as plain text, and everything else through PHP's `serialize()`.
+//!
+//! Values go through [`serde_json::Value`] on their way in and out, so only what JSON can represent is supported:
+//! `null`, booleans, numbers, strings, and arrays (lists, or maps with string or integer keys). PHP objects can't be
+//! read, and structs and maps are written as associative arrays.
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Number, Value};
+use std::{fmt::Write, str};
+
+/// Serialize a value the way Laravel's cache would store it.
+pub fn to_vec<T: Serialize>(value: &T) -> Result<Vec<u8>, Error> {
+	let value = serde_json::to_value(value)?;
+
+	// Laravel stores numbers as-is, so Redis can increment them.
+	if let Value::Number(number) = &value {
+		return Ok(number.to_string().into_bytes());
+	}
+
+	let mut out = String::new();
+	write_value(&mut out, &value);
+
+	Ok(out.into_bytes())
+}
+
+/// Deserialize a value stored by Laravel's cache.
+pub fn from_slice<T: DeserializeOwned>(data: &[u8]) -> Result<T, Error> {
+	// Laravel stores numeric values (including numeric strings) as-is, so try them as either.
+	if let Some(number) = str::from_utf8(data)
+		.ok()
+		.and_then(|text| text.parse::<Number>().ok())
+	{
+		return serde_json::from_value(Value::Number(number)).or_else(|_| {
+			Ok(serde_json::from_value(Value::String(
+				String::from_utf8_lossy(data).into_owned(),
+			))?)
+		});
+	}
+
+	let mut parser = Parser { data, pos: 0 };
+	let value = parser.value()?;
+
+	if parser.pos != data.len() {
+		return Err(Error::Invalid(parser.pos));
+	}
+
+	Ok(serde_json::from_value(value)?)
+}
+
+fn write_value(out: &mut String, value: &Value) {
+	match value {
+		Value::Null => out.push_str("N;"),
+		Value::Bool(value) => {
+			let _ = write!(out, "b:{};", u8::from(*value));
+		},
+		Value::Number(number) if number.is_f64() => {
+			let _ = write!(out, "d:{number};");
+		},
+		Value::Number(number) => {
+			let _ = write!(out, "i:{number};");
+		},
+		Value::String(value) => write_string(out, value),
+		Value::Array(values) => {
+			let _ = write!(out, "a:{}:{{", values.len());
+			for (index, value) in values.iter().enumerate() {
+				let _ = write!(out, "i:{index};");
+				write_value(out, value);
+			}
+			out.push('}');
+		},
+		Value::Object(values) => {
+			let _ = write!(out, "a:{}:{{", values.len());
+			for (key, value) in values {
+				// PHP turns keys that look like integers into integers.
+				match key.parse::<i64>() {
+					Ok(index) if index.to_string() == *key => {
+						let _ = write!(out, "i:{index};");
+					},
+					_ => write_string(out, key),
+				}
+				write_value(out, value);
+			}
+			out.push('}');
+		},
+	}
+}
+
+fn write_string(out: &mut String, value: &str) {
+	let _ = write!(out, "s:{}:\"{value}\";", value.len());
+}
+
+/// A parser for PHP's `serialize()` format.
+struct Parser<'a> {
+	data: &'a [u8],
+	pos: usize,
+}
+
+impl Parser<'_> {
+	fn value(&mut self) -> Result<Value, Error> {
+		let kind = self.next()?;
+
+		if kind == b'N' {
+			self.expect(b';')?;
+			return Ok(Value::Null);
+		}
+
+		self.expect(b':')?;
+
+		match kind {
+			b'b' => match self.until(b';')? {
+				"0" => Ok(Value::Bool(false)),
+				"1" => Ok(Value::Bool(true)),
+				_ => Err(Error::Invalid(self.pos)),
+			},
+			b'i' => {
+				let pos = self.pos;
+				let value = self.until(b';')?;
+
+				value
+					.parse::<i64>()
+					.map(Value::from)
+					.map_err(|_| Error::Invalid(pos))
+			},
+			b'd' => {
+				let pos = self.pos;
+				let value = self.until(b';')?;
+
+				value
+					.parse::<f64>()
+					.ok()
+					.and_then(Number::from_f64)
+					.map(Value::Number)
+					.ok_or(Error::Invalid(pos))
+			},
+			b's' => self.string().map(Value::String),
+			b'a' => self.array(),
+			kind => Err(Error::Unsupported(kind as char)),
+		}
+	}
+
+	/// A string's contents, after its `s:`.
+	fn string(&mut self) -> Result<String, Error> {
+		let pos = self.pos;
+		let len = self
+			.until(b':')?
+			.parse::<usize>()
+			.map_err(|_| Error::Invalid(pos))?;
+
+		self.expect(b'"')?;
+		let start = self.pos;
+		let end = start
+			.checked_add(len)
+			.filter(|end| *end <= self.data.len())
+			.ok_or(Error::Invalid(start))?;
+		let value = str::from_utf8(&self.data[start..end]).map_err(|_| Error::Invalid(start))?;
+		self.pos = end;
+		self.expect(b'"')?;
+		self.expect(b';')?;
+
+		Ok(value.to_string())
+	}
+
+	/// An array's entries, after its `a:`. Arrays keyed `0..n` in order become lists, and any other array a map.
+	fn array(&mut self) -> Result<Value, Error> {
+		let pos = self.pos;
+		let len = self
+			.until(b':')?
+			.parse::<usize>()
+			.map_err(|_| Error::Invalid(pos))?;
+		self.expect(b'{')?;
+
+		let mut entries = Vec::new();
+		for _ in 0..len {
+			let key = match self.value()? {
+				Value::Number(index) => index.to_string(),
+				Value::String(key) => key,
+				_ => return Err(Error::Invalid(self.pos)),
+			};
+
+			entries.push((key, self.value()?));
+		}
+		self.expect(b'}')?;
+
+		if entries
+			.iter()
+			.enumerate()
+			.all(|(index, (key, _))| *key == index.to_string())
+		{
+			return Ok(Value::Array(
+				entries.into_iter().map(|(_, value)| value).collect(),
+			));
+		}
+
+		Ok(Value::Object(entries.into_iter().collect::<Map<_, _>>()))
+	}
+
+	fn next(&mut self) -> Result<u8, Error> {
+		let byte = *self.data.get(self.pos).ok_or(Error::Invalid(self.pos))?;
+		self.pos += 1;
+
+		Ok(byte)
+	}
+
+	fn expect(&mut self, expected: u8) -> Result<(), Error> {
+		if self.next()? != expected {
+			return Err(Error::Invalid(self.pos - 1));
+		}
+
+		Ok(())
+	}
+
+	/// Everything up to the next `delimiter`, consuming it.
+	fn until(&mut self, delimiter: u8) -> Result<&str, Error> {
+		let start = self.pos;
+		let len = self.data[start..]
+			.iter()
+			.position(|byte| *byte == delimiter)
+			.ok_or(Error::Invalid(start))?;
+		self.pos = start + len + 1;
+
+		str::from_utf8(&self.data[start..start + len]).map_err(|_| Error::Invalid(start))
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("invalid PHP serialized value at byte {0}")]
+	Invalid(usize),
+	#[error("unsupported PHP serialized type `{0}`")]
+	Unsupported(char),
+	#[error(transparent)]
+	Json(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_reads_laravel_values() {
+		// What Laravel stores for
+		// `Cache::put('user', ['name' => 'Taylor', 'roles' => ['admin'], 'active' => true, 'score' => 9.5])`.
+		let stored = br#"a:4:{s:4:"name";s:6:"Taylor";s:5:"roles";a:1:{i:0;s:5:"admin";}s:6:"active";b:1;s:5:"score";d:9.5;}"#;
+
+		assert_eq!(
+			from_slice::<Value>(stored).unwrap(),
+			json!({ "name": "Taylor", "roles": ["admin"], "active": true, "score": 9.5 })
+		);
+		assert_eq!(from_slice::<i64>(b"42").unwrap(), 42);
+		assert_eq!(from_slice::<String>(b"42").unwrap(), "42");
+		assert!(matches!(
+			from_slice::<String>(br#"s:9:"short";"#),
+			Err(Error::Invalid(5))
+		));
+		assert_eq!(
+			from_slice::<String>("s:6:\"héllo\";".as_bytes()).unwrap(),
+			"héllo"
+		);
+		assert!(matches!(
+			from_slice::<Value>(br#"O:8:"stdClass":0:{}"#),
+			Err(Error::Unsupported('O'))
+		));
+	}
+
+	#[test]
+	fn test_writes_laravel_values() {
+		assert_eq!(
+			to_vec(&json!({ "active": true, "roles": ["admin"], "score": 9.5, "7": null }))
+				.unwrap(),
+			br#"a:4:{i:7;N;s:6:"active";b:1;s:5:"roles";a:1:{i:0;s:5:"admin";}s:5:"score";d:9.5;}"#
+		);
+		assert_eq!(to_vec(&42).unwrap(), b"42");
+		assert_eq!(
+			from_slice::<(String, bool)>(&to_vec(&("foo", false)).unwrap()).unwrap(),
+			("foo".to_string(), false)
+		);
+	}
+}
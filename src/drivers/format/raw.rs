@@ -0,0 +1,226 @@
+//! Values that were already serialized, passed through a driver's format untouched.
+//!
+//! `Box<dyn DynDriver>` serializes values before they reach the underlying driver, so it hands them over wrapped in
+//! [`Raw`]. [`Format`](super::Format) recognizes it and stores (or returns) the bytes verbatim, instead of serializing
+//! them a second time. Drivers that don't go through a [`Format`](super::Format) see it as a plain byte string.
+
+use serde::{
+	de::{self, value::Error, Visitor},
+	forward_to_deserialize_any,
+	ser::{Error as _, Impossible},
+	Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt;
+
+/// The newtype name [`Raw`] is serialized with, so the probes below can tell it apart from any other value.
+const TOKEN: &str = "$amnesia::private::Raw";
+
+/// A value that has already been serialized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Raw(pub Vec<u8>);
+
+impl Serialize for Raw {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_newtype_struct(TOKEN, &Bytes(&self.0))
+	}
+}
+
+impl<'de> Deserialize<'de> for Raw {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		deserializer.deserialize_newtype_struct(TOKEN, RawVisitor)
+	}
+}
+
+/// Get the bytes of `value` if it's a [`Raw`].
+pub(super) fn as_bytes<T: Serialize + ?Sized>(value: &T) -> Option<Vec<u8>> {
+	value.serialize(SerializerProbe { in_raw: false }).ok()
+}
+
+/// Wrap `data` in `T` if it's a [`Raw`].
+pub(super) fn from_bytes<T: de::DeserializeOwned>(data: &[u8]) -> Option<T> {
+	T::deserialize(DeserializerProbe(data)).ok()
+}
+
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_bytes(self.0)
+	}
+}
+
+struct RawVisitor;
+
+impl<'de> Visitor<'de> for RawVisitor {
+	type Value = Raw;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a serialized value")
+	}
+
+	fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<Raw, D::Error> {
+		deserializer.deserialize_byte_buf(self)
+	}
+
+	fn visit_bytes<E: de::Error>(self, data: &[u8]) -> Result<Raw, E> {
+		Ok(Raw(data.to_vec()))
+	}
+
+	fn visit_byte_buf<E: de::Error>(self, data: Vec<u8>) -> Result<Raw, E> {
+		Ok(Raw(data))
+	}
+
+	fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Raw, A::Error> {
+		let mut data = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+		while let Some(byte) = seq.next_element()? {
+			data.push(byte);
+		}
+
+		Ok(Raw(data))
+	}
+}
+
+/// A serializer that only accepts [`Raw`], returning its bytes.
+struct SerializerProbe {
+	in_raw: bool,
+}
+
+fn not_raw() -> Error {
+	Error::custom("not a raw value")
+}
+
+macro_rules! reject {
+	($($method:ident($($arg:ty),*) -> $ok:ty;)*) => {
+		$(
+			fn $method(self, $(_: $arg),*) -> Result<$ok, Error> {
+				Err(not_raw())
+			}
+		)*
+	};
+}
+
+impl Serializer for SerializerProbe {
+	type Ok = Vec<u8>;
+	type Error = Error;
+	type SerializeSeq = Impossible<Vec<u8>, Error>;
+	type SerializeTuple = Impossible<Vec<u8>, Error>;
+	type SerializeTupleStruct = Impossible<Vec<u8>, Error>;
+	type SerializeTupleVariant = Impossible<Vec<u8>, Error>;
+	type SerializeMap = Impossible<Vec<u8>, Error>;
+	type SerializeStruct = Impossible<Vec<u8>, Error>;
+	type SerializeStructVariant = Impossible<Vec<u8>, Error>;
+
+	fn serialize_bytes(self, data: &[u8]) -> Result<Vec<u8>, Error> {
+		if self.in_raw {
+			Ok(data.to_vec())
+		} else {
+			Err(not_raw())
+		}
+	}
+
+	fn serialize_newtype_struct<T: Serialize + ?Sized>(
+		self,
+		name: &'static str,
+		value: &T,
+	) -> Result<Vec<u8>, Error> {
+		if name == TOKEN && !self.in_raw {
+			value.serialize(Self { in_raw: true })
+		} else {
+			Err(not_raw())
+		}
+	}
+
+	fn serialize_some<T: Serialize + ?Sized>(self, _: &T) -> Result<Vec<u8>, Error> {
+		Err(not_raw())
+	}
+
+	fn serialize_newtype_variant<T: Serialize + ?Sized>(
+		self,
+		_: &'static str,
+		_: u32,
+		_: &'static str,
+		_: &T,
+	) -> Result<Vec<u8>, Error> {
+		Err(not_raw())
+	}
+
+	reject! {
+		serialize_bool(bool) -> Vec<u8>;
+		serialize_i8(i8) -> Vec<u8>;
+		serialize_i16(i16) -> Vec<u8>;
+		serialize_i32(i32) -> Vec<u8>;
+		serialize_i64(i64) -> Vec<u8>;
+		serialize_u8(u8) -> Vec<u8>;
+		serialize_u16(u16) -> Vec<u8>;
+		serialize_u32(u32) -> Vec<u8>;
+		serialize_u64(u64) -> Vec<u8>;
+		serialize_f32(f32) -> Vec<u8>;
+		serialize_f64(f64) -> Vec<u8>;
+		serialize_char(char) -> Vec<u8>;
+		serialize_str(&str) -> Vec<u8>;
+		serialize_none() -> Vec<u8>;
+		serialize_unit() -> Vec<u8>;
+		serialize_unit_struct(&'static str) -> Vec<u8>;
+		serialize_unit_variant(&'static str, u32, &'static str) -> Vec<u8>;
+		serialize_seq(Option<usize>) -> Self::SerializeSeq;
+		serialize_tuple(usize) -> Self::SerializeTuple;
+		serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+		serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+		serialize_map(Option<usize>) -> Self::SerializeMap;
+		serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+		serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+	}
+}
+
+/// A deserializer that only produces [`Raw`], holding the given bytes.
+struct DeserializerProbe<'a>(&'a [u8]);
+
+impl<'de> Deserializer<'de> for DeserializerProbe<'_> {
+	type Error = Error;
+
+	fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Error> {
+		Err(de::Error::custom("not a raw value"))
+	}
+
+	fn deserialize_newtype_struct<V: Visitor<'de>>(
+		self,
+		name: &'static str,
+		visitor: V,
+	) -> Result<V::Value, Error> {
+		if name == TOKEN {
+			visitor.visit_byte_buf(self.0.to_vec())
+		} else {
+			Err(de::Error::custom("not a raw value"))
+		}
+	}
+
+	forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option unit
+		unit_struct seq tuple tuple_struct map struct enum identifier ignored_any
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::drivers::Format;
+
+	#[test]
+	fn test_raw_passthrough() {
+		let data = Format::Json.serialize(&"amnesia").unwrap();
+
+		for format in [Format::Bitcode, Format::Json, Format::Laravel] {
+			assert_eq!(format.serialize(&Raw(data.clone())).unwrap(), data);
+			assert_eq!(format.deserialize::<Raw>(&data).unwrap(), Raw(data.clone()));
+		}
+
+		assert_eq!(
+			Format::Json.serialize(&data).unwrap(),
+			b"[34,97,109,110,101,115,105,97,34]"
+		);
+		assert_eq!(
+			bitcode::deserialize::<Raw>(&bitcode::serialize(&Raw(data.clone())).unwrap()).unwrap(),
+			Raw(data)
+		);
+	}
+}
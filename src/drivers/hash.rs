@@ -0,0 +1,16 @@
+//! A stable hash for keys, for drivers that place entries based on it.
+
+/// 64-bit FNV-1a with a murmur3 finalizer, used instead of the std hasher because it must be stable across
+/// processes. The finalizer spreads similar keys (like `user:1` and `user:2`) across the whole output range.
+pub fn stable_hash(data: &[u8]) -> u64 {
+	let mut hash = data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+		(hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+	});
+
+	hash ^= hash >> 33;
+	hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+	hash ^= hash >> 33;
+	hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+
+	hash ^ (hash >> 33)
+}
@@ -0,0 +1,514 @@
+use super::{format, hash::stable_hash, Driver, Format, GetOutcome, Overflow, Ttl};
+use async_memcached::{AsciiProtocol, Client, MetaProtocol, Status};
+use futures_util::lock::{Mutex, MutexGuard};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	fmt,
+	future::Future,
+	sync::Arc,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The longest TTL memcached treats as relative. Longer ones are read as an absolute unix timestamp.
+const MAX_RELATIVE_TTL: u64 = 60 * 60 * 24 * 30;
+
+/// How many times `increment` retries its check-and-set when other clients keep changing the counter in between.
+const MAX_INCREMENT_ATTEMPTS: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+	/// The memcached servers to connect to (like `127.0.0.1:11211` or `unix:///tmp/memcached.sock`).
+	/// Keys are spread across them by hash, so adding or removing one moves most keys to a different server.
+	pub servers: Vec<String>,
+	pub prefix: String,
+	/// The format values are serialized with.
+	pub format: Format,
+}
+
+config_builder! {
+	servers: Vec<String>,
+	prefix: String,
+	format: Format,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			prefix: String::new(),
+			format: Format::default(),
+			servers: vec!["127.0.0.1:11211".to_string()],
+		}
+	}
+}
+
+#[derive(Clone)]
+#[allow(clippy::module_name_repetitions)]
+/// A driver that uses Memcached.
+///
+/// Each server gets a single connection, shared by clones of the driver, which is reopened if a request is cancelled
+/// or fails without a response. Keys (including the prefix) can't be longer than 250 bytes or contain whitespace, and
+/// [`flush`](Driver::flush) removes every entry on the servers, not just the ones under the prefix. Since memcached
+/// can't list its keys, [`keys`](Driver::keys) and [`forget_prefix`](Driver::forget_prefix) always fail with
+/// [`Error::KeyListingNotSupported`].
+pub struct MemcachedDriver {
+	prefix: String,
+	format: Format,
+	servers: Vec<Server>,
+}
+
+#[derive(Clone)]
+struct Server {
+	addr: String,
+	client: Arc<Mutex<Option<Client>>>,
+}
+
+impl Server {
+	/// Lock the server's connection, reconnecting if the last one was discarded.
+	async fn connection(&self) -> Result<Connection<'_>, Error> {
+		let mut client = self.client.lock().await;
+		if client.is_none() {
+			*client = Some(Client::new(&self.addr).await?);
+		}
+
+		Ok(Connection {
+			client,
+			broken: false,
+		})
+	}
+}
+
+/// A server's connection, locked for a request.
+///
+/// A request that's cancelled (or fails without a response from the server) can leave part of itself or its response
+/// on the connection, which the next request would read instead of its own. Those connections are discarded when
+/// they're dropped, so the next request opens a new one.
+struct Connection<'a> {
+	client: MutexGuard<'a, Option<Client>>,
+	broken: bool,
+}
+
+impl Connection<'_> {
+	/// Send a request on the connection.
+	async fn send<'c, T, F, Fut>(&'c mut self, request: F) -> Result<T, async_memcached::Error>
+	where
+		F: FnOnce(&'c mut Client) -> Fut,
+		Fut: Future<Output = Result<T, async_memcached::Error>>,
+	{
+		let Self { client, broken } = self;
+
+		// Stays set if the request is cancelled, since the future (and this connection with it) is dropped mid-way.
+		*broken = true;
+		let result = request(
+			client
+				.as_mut()
+				.expect("the connection is open while it's locked"),
+		)
+		.await;
+		*broken = result
+			.as_ref()
+			.is_err_and(|error| !matches!(error, async_memcached::Error::Protocol(_)));
+
+		result
+	}
+}
+
+impl Drop for Connection<'_> {
+	fn drop(&mut self) {
+		if self.broken {
+			*self.client = None;
+		}
+	}
+}
+
+impl fmt::Debug for MemcachedDriver {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("MemcachedDriver")
+			.field("prefix", &self.prefix)
+			.field("format", &self.format)
+			.field(
+				"servers",
+				&self
+					.servers
+					.iter()
+					.map(|server| &server.addr)
+					.collect::<Vec<_>>(),
+			)
+			.finish()
+	}
+}
+
+impl MemcachedDriver {
+	/// The connection to the server holding a key, and the key as stored there.
+	async fn connection(&self, key: &str) -> Result<(Connection<'_>, String), Error> {
+		let key = format!("{}{key}", self.prefix);
+		let index = usize::try_from(stable_hash(key.as_bytes()) % self.servers.len() as u64)
+			.expect("the index is smaller than the number of servers");
+
+		Ok((self.servers[index].connection().await?, key))
+	}
+
+	async fn read(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+		let (mut conn, key) = self.connection(key).await?;
+
+		Ok(conn
+			.send(|client| client.get(&key))
+			.await?
+			.and_then(|value| value.data))
+	}
+}
+
+/// Convert an expiry into a memcached TTL, in seconds.
+///
+/// Memcached has no sub-second expiries, so they're rounded up. TTLs over 30 days are sent as a unix timestamp
+/// instead, since that's how memcached reads them.
+fn memcached_ttl(expiry: Option<Duration>) -> i64 {
+	let Some(expiry) = expiry else {
+		return 0;
+	};
+
+	// A TTL of zero means "never expire", while a negative one expires the entry immediately.
+	if expiry.is_zero() {
+		return -1;
+	}
+
+	let seconds = expiry.as_secs() + u64::from(expiry.subsec_nanos() > 0);
+	let seconds = if seconds > MAX_RELATIVE_TTL {
+		SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_secs()
+			+ seconds
+	} else {
+		seconds
+	};
+
+	i64::try_from(seconds).unwrap_or(i64::MAX)
+}
+
+impl Driver for MemcachedDriver {
+	type Error = Error;
+	type Config = Config;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		if config.servers.is_empty() {
+			return Err(Error::NoServers);
+		}
+
+		let mut servers = Vec::with_capacity(config.servers.len());
+		for addr in config.servers {
+			servers.push(Server {
+				client: Arc::new(Mutex::new(Some(Client::new(&addr).await?))),
+				addr,
+			});
+		}
+
+		Ok(Self {
+			servers,
+			prefix: config.prefix,
+			format: config.format,
+		})
+	}
+
+	fn prefix(&self) -> &str {
+		&self.prefix
+	}
+
+	fn is_unavailable(error: &Self::Error) -> bool {
+		matches!(
+			error,
+			Error::Memcached(async_memcached::Error::Connect(_) | async_memcached::Error::Io(_))
+		)
+	}
+
+	fn format(&self) -> Option<Format> {
+		Some(self.format)
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		let Some(data) = self.read(key).await? else {
+			return Ok(None);
+		};
+
+		Ok(Some(self.format.deserialize(&data)?))
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		let Some(data) = self.read(key).await? else {
+			return Ok(GetOutcome::Miss);
+		};
+
+		Ok(self
+			.format
+			.deserialize(&data)
+			.map_or(GetOutcome::Corrupt(data), GetOutcome::Hit))
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		Ok(self.read(key).await?.is_some())
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		let (mut conn, key) = self.connection(key).await?;
+
+		let Some(value) = conn
+			.send(|client| client.meta_get(&key, false, None, Some(&["t"])))
+			.await?
+		else {
+			return Ok(None);
+		};
+
+		Ok(Some(match value.ttl_remaining {
+			Some(remaining) if remaining >= 0 => {
+				Ttl::In(Duration::from_secs(remaining.unsigned_abs()))
+			},
+			_ => Ttl::Forever,
+		}))
+	}
+
+	async fn keys(&self, _: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		Err(Error::KeyListingNotSupported)
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		let data = self.format.serialize(value)?;
+		let (mut conn, key) = self.connection(key).await?;
+
+		conn.send(|client| client.set(&key, data.as_slice(), Some(memcached_ttl(expiry)), None))
+			.await?;
+
+		Ok(())
+	}
+
+	async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		expiry: Option<Duration>,
+	) -> Result<T, Self::Error> {
+		let data = self.format.serialize(&value)?;
+		let (mut conn, key) = self.connection(key).await?;
+
+		// `add` only stores the value if the key doesn't exist yet, so racing callers agree on a single value.
+		match conn
+			.send(|client| client.add(&key, data.as_slice(), Some(memcached_ttl(expiry)), None))
+			.await
+		{
+			Ok(()) => return Ok(value),
+			Err(async_memcached::Error::Protocol(Status::NotStored)) => {},
+			Err(error) => return Err(error.into()),
+		}
+
+		if let Some(existing) = conn
+			.send(|client| client.get(&key))
+			.await?
+			.and_then(|value| value.data)
+		{
+			return Ok(self.format.deserialize(&existing)?);
+		}
+
+		// The existing entry expired (or was removed) in between, so ours can be stored after all.
+		conn.send(|client| client.set(&key, data.as_slice(), Some(memcached_ttl(expiry)), None))
+			.await?;
+
+		Ok(value)
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		let data = self.format.serialize(value)?;
+		let (mut conn, key) = self.connection(key).await?;
+
+		match conn
+			.send(|client| client.add(&key, data.as_slice(), Some(memcached_ttl(expiry)), None))
+			.await
+		{
+			Ok(()) => Ok(true),
+			Err(async_memcached::Error::Protocol(Status::NotStored)) => Ok(false),
+			Err(error) => Err(error.into()),
+		}
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		let format = self.format;
+		let (mut conn, key) = self.connection(key).await?;
+
+		// Values are stored serialized rather than as memcached counters, so they're updated with check-and-set,
+		// retrying whenever another client changed the value in between.
+		for _ in 0..MAX_INCREMENT_ATTEMPTS {
+			let Some(existing) = conn
+				.send(|client| client.meta_get(&key, false, None, Some(&["v", "c", "t"])))
+				.await?
+			else {
+				let data = format.serialize(&by)?;
+
+				match conn
+					.send(|client| {
+						client.add(&key, data.as_slice(), Some(memcached_ttl(expiry)), None)
+					})
+					.await
+				{
+					Ok(()) => return Ok(by),
+					Err(async_memcached::Error::Protocol(Status::NotStored)) => continue,
+					Err(error) => return Err(error.into()),
+				}
+			};
+
+			let value = format
+				.deserialize::<i64>(&existing.data.unwrap_or_default())?
+				.checked_add(by)
+				.ok_or(Overflow)?;
+			let data = format.serialize(&value)?;
+
+			// The remaining lifetime is converted like any other expiry, since memcached reads TTLs over 30 days as a
+			// timestamp. A negative one means the counter never expires.
+			let ttl = existing
+				.ttl_remaining
+				.filter(|remaining| *remaining >= 0)
+				.map_or(0, |remaining| {
+					memcached_ttl(Some(Duration::from_secs(remaining.unsigned_abs())))
+				});
+			let cas = format!("C{}", existing.cas.unwrap_or_default());
+			let ttl = format!("T{ttl}");
+			let flags = [cas.as_str(), ttl.as_str()];
+
+			match conn
+				.send(|client| client.meta_set(&key, data.as_slice(), false, None, Some(&flags)))
+				.await
+			{
+				Ok(_) => return Ok(value),
+				Err(async_memcached::Error::Protocol(Status::Exists | Status::NotFound)) => {},
+				Err(error) => return Err(error.into()),
+			}
+		}
+
+		Err(Error::Contended)
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		let (mut conn, key) = self.connection(key).await?;
+		let ttl = format!("T{}", memcached_ttl(expiry));
+		let flags = [ttl.as_str(), "t"];
+
+		Ok(conn
+			.send(|client| client.meta_get(&key, false, None, Some(&flags)))
+			.await?
+			.is_some())
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		let (mut conn, key) = self.connection(key).await?;
+
+		match conn.send(|client| client.delete(&key)).await {
+			Ok(()) => Ok(true),
+			Err(async_memcached::Error::Protocol(Status::NotFound)) => Ok(false),
+			Err(error) => Err(error.into()),
+		}
+	}
+
+	async fn forget_prefix(&mut self, _: &str) -> Result<(), Self::Error> {
+		Err(Error::KeyListingNotSupported)
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		for server in &self.servers {
+			server.connection().await?.send(Client::flush_all).await?;
+		}
+
+		Ok(())
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("no Memcached servers were configured.")]
+	NoServers,
+	#[error("Memcached can't list its keys.")]
+	KeyListingNotSupported,
+	#[error(transparent)]
+	Memcached(#[from] async_memcached::Error),
+	#[error("the counter kept changing while it was being incremented.")]
+	Contended,
+	#[error(transparent)]
+	Serialization(#[from] format::Error),
+	#[error(transparent)]
+	Overflow(#[from] Overflow),
+}
+
+#[cfg(test)]
+mod tests {
+	use std::env;
+
+	use super::*;
+	use crate::Cache;
+
+	#[test]
+	fn test_memcached_ttl() {
+		assert_eq!(memcached_ttl(None), 0);
+		assert_eq!(memcached_ttl(Some(Duration::ZERO)), -1);
+		assert_eq!(memcached_ttl(Some(Duration::from_millis(1500))), 2);
+		assert_eq!(memcached_ttl(Some(Duration::from_secs(60 * 60))), 3600);
+
+		// Anything longer than 30 days becomes an absolute timestamp.
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap()
+			.as_secs();
+		let ttl = memcached_ttl(Some(Duration::from_secs(MAX_RELATIVE_TTL + 1)));
+		assert!(ttl.unsigned_abs() >= now + MAX_RELATIVE_TTL);
+	}
+
+	#[tokio::test]
+	async fn test_memcached_driver() {
+		let mut cache = Cache::<MemcachedDriver>::new(Config {
+			servers: vec![env::var("MEMCACHED_URL").expect("MEMCACHED_URL not set")],
+			..Default::default()
+		})
+		.await
+		.unwrap();
+
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+		assert!(!cache.has("foo").await.unwrap());
+
+		cache
+			.put("foo", &"bar", Duration::from_secs(10))
+			.await
+			.unwrap();
+
+		assert_eq!(
+			cache.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+		assert!(cache.has("foo").await.unwrap());
+		assert!(matches!(cache.ttl("foo").await.unwrap(), Some(Ttl::In(_))));
+
+		assert!(cache.forget("foo").await.unwrap());
+		assert!(!cache.forget("foo").await.unwrap());
+
+		// A cancelled request doesn't leave its response for the next one to read.
+		cache.forever("foo", "bar").await.unwrap();
+		let _ = tokio::time::timeout(Duration::ZERO, cache.get::<String>("baz")).await;
+		assert_eq!(
+			cache.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+		cache.forget("foo").await.unwrap();
+
+		assert_eq!(cache.increment("hits", 2).await.unwrap(), 2);
+		assert_eq!(cache.increment("hits", 3).await.unwrap(), 5);
+		cache.forget("hits").await.unwrap();
+	}
+}
@@ -1,44 +1,650 @@
-use super::Driver;
+use super::{
+	format, schema, Driver, EntryMeta, FlushWhere, Format, GetOutcome, OnCorrupt, Overflow, Ttl,
+};
 use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "memory-sweep")]
+use std::sync::Weak;
 use std::{
-	collections::HashMap,
-	time::{Duration, SystemTime},
+	collections::{BTreeMap, HashMap},
+	fmt,
+	sync::{Arc, Mutex, MutexGuard, PoisonError},
+	time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+	/// Track when entries were created and how many times they've been read.
+	pub track_metadata: bool,
+	/// A version stored alongside every value. Values stored with a different version are treated as misses.
+	pub schema_version: Option<u32>,
+	/// What `get` does with entries that can't be deserialized.
+	pub on_corrupt: OnCorrupt,
+	/// The format values are serialized with.
+	pub format: Format,
+	/// The maximum total size of the stored (serialized) values, in bytes.
+	/// When storing a value would exceed it, entries are evicted (according to `eviction_policy`) until it fits.
+	pub max_bytes: Option<usize>,
+	/// The maximum number of entries to store.
+	/// When storing a value would exceed it, entries are evicted (according to `eviction_policy`) until it fits.
+	pub max_entries: Option<usize>,
+	/// The largest (serialized) value that can be stored, in bytes. Storing a larger one fails with
+	/// [`Error::ValueTooLarge`] instead of evicting other entries to make room for it.
+	pub max_value_bytes: Option<usize>,
+	/// Which entries to evict first when the cache grows over `max_bytes` or `max_entries`.
+	pub eviction_policy: EvictionPolicy,
+	/// A grace period after an entry's expiry during which it's still considered fresh, to absorb clock skew.
+	/// Entries may be served for up to this long past their TTL.
+	pub clock_skew_tolerance: Duration,
+	/// How often to remove expired entries in the background, or `None` to only remove them as entries are written
+	/// (or through [`MemoryDriver::remove_expired`]). Requires a Tokio runtime, and the background task stops once
+	/// every clone of the driver is dropped.
+	#[cfg(feature = "memory-sweep")]
+	pub sweep_interval: Option<Duration>,
+}
+
+config_builder! {
+	track_metadata: bool,
+	schema_version: Option<u32>,
+	on_corrupt: OnCorrupt,
+	format: Format,
+	max_bytes: Option<usize>,
+	max_entries: Option<usize>,
+	max_value_bytes: Option<usize>,
+	eviction_policy: EvictionPolicy,
+	clock_skew_tolerance: Duration,
+	#[cfg(feature = "memory-sweep")]
+	sweep_interval: Option<Duration>,
+}
+
+/// The order in which the memory driver evicts entries once it's full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+	/// Evict the least recently used entries first.
+	#[default]
+	Lru,
+	/// Evict the least frequently used entries first, breaking ties by recency.
+	/// Keeps a stable hot set cached when large scans would otherwise push it out, at the cost of evicting
+	/// newly-stored entries first until they've been read a few times.
+	Lfu,
+	/// Evict the oldest entries first, regardless of how they're accessed.
+	Fifo,
+}
+
+struct Entry {
+	data: Vec<u8>,
+	hits: u64,
+	uses: u64,
+	last_used: u64,
+	inserted_at: u64,
+	created_at: SystemTime,
+	expires_at: Option<SystemTime>,
+}
+
+impl Entry {
+	fn new(data: Vec<u8>, expires_at: Option<SystemTime>, tick: u64) -> Self {
+		Self {
+			data,
+			expires_at,
+			hits: 0,
+			uses: 1,
+			last_used: tick,
+			inserted_at: tick,
+			created_at: SystemTime::now(),
+		}
+	}
+
+	fn is_expired(&self, now: SystemTime) -> bool {
+		self.expires_at.is_some_and(|expires_at| expires_at < now)
+	}
+
+	const fn touch(&mut self, tick: u64) {
+		self.uses += 1;
+		self.last_used = tick;
+	}
+
+	/// The key entries are evicted by, lowest first. Every tick of the clock is only used once, so no two entries
+	/// share a rank.
+	const fn eviction_rank(&self, policy: EvictionPolicy) -> (u64, u64) {
+		match policy {
+			EvictionPolicy::Lru => (self.last_used, 0),
+			EvictionPolicy::Lfu => (self.uses, self.last_used),
+			EvictionPolicy::Fifo => (self.inserted_at, 0),
+		}
+	}
+}
+
+#[derive(Clone)]
 #[allow(clippy::module_name_repetitions)]
 /// A driver that stores values in memory.
+///
+/// Clones share the same entries, so a cache can be cloned into each task that needs it instead of being locked as a
+/// whole. Every operation briefly locks the entries, so they never block each other for long.
 pub struct MemoryDriver {
-	cache: HashMap<String, (Vec<u8>, Option<SystemTime>)>,
+	store: Arc<Mutex<Store>>,
 }
 
-impl Driver for MemoryDriver {
-	type Config = ();
-	type Error = Error;
+struct Store {
+	track_metadata: bool,
+	schema_version: Option<u32>,
+	on_corrupt: OnCorrupt,
+	format: Format,
+	max_bytes: Option<usize>,
+	max_entries: Option<usize>,
+	max_value_bytes: Option<usize>,
+	eviction_policy: EvictionPolicy,
+	clock_skew_tolerance: Duration,
+	/// The summed size of every stored value.
+	bytes: usize,
+	/// How many entries have been evicted to stay within the size limits.
+	evictions: u64,
+	/// A logical clock, advanced on every access to order entries by recency.
+	clock: u64,
+	/// How many writes happened since expired entries were last swept.
+	writes_since_sweep: usize,
+	cache: HashMap<String, Entry>,
+	/// Every key, ordered by its entry's eviction rank, so the next entry to evict doesn't have to be searched for.
+	order: BTreeMap<(u64, u64), String>,
+}
 
-	async fn new((): Self::Config) -> Result<Self, Self::Error> {
-		Ok(Self {
-			cache: HashMap::new(),
-		})
+impl fmt::Debug for MemoryDriver {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let store = self.store();
+
+		f.debug_struct("MemoryDriver")
+			.field("track_metadata", &store.track_metadata)
+			.field("schema_version", &store.schema_version)
+			.field("on_corrupt", &store.on_corrupt)
+			.field("format", &store.format)
+			.field("max_bytes", &store.max_bytes)
+			.field("max_entries", &store.max_entries)
+			.field("max_value_bytes", &store.max_value_bytes)
+			.field("eviction_policy", &store.eviction_policy)
+			.field("clock_skew_tolerance", &store.clock_skew_tolerance)
+			.field("entries", &store.cache.len())
+			.field("bytes", &store.bytes)
+			.field("evictions", &store.evictions)
+			.finish_non_exhaustive()
 	}
+}
 
-	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
-		let Some((data, expires_at)) = self.cache.get(key) else {
+impl MemoryDriver {
+	/// Remove all expired entries from the cache, returning how many were removed.
+	pub fn remove_expired(&mut self) -> usize {
+		self.store().remove_expired()
+	}
+
+	/// The summed size of every stored (serialized) value, in bytes.
+	#[must_use]
+	pub fn current_bytes(&self) -> usize {
+		self.store().bytes
+	}
+
+	/// How many entries have been evicted to stay within `max_bytes` or `max_entries`.
+	#[must_use]
+	pub fn evictions(&self) -> u64 {
+		self.store().evictions
+	}
+
+	fn store(&self) -> MutexGuard<'_, Store> {
+		// Entries are never left half-updated, so they're still usable if another thread panicked while holding them.
+		self.store.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+/// Periodically remove expired entries, until every clone of the driver is dropped.
+#[cfg(feature = "memory-sweep")]
+async fn sweep_expired(store: Weak<Mutex<Store>>, interval: Duration) {
+	loop {
+		tokio::time::sleep(interval).await;
+
+		// Only a weak handle is kept between ticks, so the task doesn't keep the entries alive on its own.
+		let Some(store) = store.upgrade() else {
+			break;
+		};
+		store
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.remove_expired();
+	}
+}
+
+impl Store {
+	fn remove_expired(&mut self) -> usize {
+		let now = self.expiry_cutoff();
+		let policy = self.eviction_policy;
+		let len = self.cache.len();
+
+		self.cache.retain(|_, entry| {
+			let expired = entry.is_expired(now);
+			if expired {
+				self.bytes -= entry.data.len();
+				self.order.remove(&entry.eviction_rank(policy));
+			}
+
+			!expired
+		});
+
+		len - self.cache.len()
+	}
+
+	/// The point in time entries must expire after to still be considered fresh.
+	fn expiry_cutoff(&self) -> SystemTime {
+		SystemTime::now()
+			.checked_sub(self.clock_skew_tolerance)
+			.unwrap_or(UNIX_EPOCH)
+	}
+
+	/// Remove expired entries once there have been as many writes as there are entries since the last sweep,
+	/// so the cache doesn't grow unboundedly while keeping writes amortized O(1).
+	fn sweep(&mut self) {
+		self.writes_since_sweep += 1;
+
+		if self.writes_since_sweep >= self.cache.len() {
+			self.writes_since_sweep = 0;
+			self.remove_expired();
+		}
+	}
+
+	const fn tick(&mut self) -> u64 {
+		self.clock += 1;
+
+		self.clock
+	}
+
+	/// Record a use of an entry, moving it to its new place in the eviction order.
+	fn touch(&mut self, key: &str) {
+		let tick = self.tick();
+		let policy = self.eviction_policy;
+		let Some(entry) = self.cache.get_mut(key) else {
+			return;
+		};
+
+		let rank = entry.eviction_rank(policy);
+		entry.touch(tick);
+
+		if let Some(key) = self.order.remove(&rank) {
+			self.order.insert(entry.eviction_rank(policy), key);
+		}
+	}
+
+	/// Serialize a value to be stored, checking it against `max_value_bytes`.
+	fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, Error> {
+		let data = schema::wrap(self.schema_version, self.format.serialize(value)?);
+
+		match self.max_value_bytes {
+			Some(limit) if data.len() > limit => Err(Error::ValueTooLarge {
+				size: data.len(),
+				limit,
+			}),
+			_ => Ok(data),
+		}
+	}
+
+	fn insert(&mut self, key: &str, data: Vec<u8>, expires_at: Option<SystemTime>) {
+		self.remove(key);
+
+		let entry = Entry::new(data, expires_at, self.tick());
+		self.bytes += entry.data.len();
+		self.order
+			.insert(entry.eviction_rank(self.eviction_policy), key.to_owned());
+		self.cache.insert(key.to_owned(), entry);
+
+		self.evict();
+	}
+
+	fn remove(&mut self, key: &str) -> Option<Entry> {
+		let entry = self.cache.remove(key)?;
+		self.bytes -= entry.data.len();
+		self.order
+			.remove(&entry.eviction_rank(self.eviction_policy));
+
+		Some(entry)
+	}
+
+	fn is_over_capacity(&self) -> bool {
+		self.max_bytes
+			.is_some_and(|max_bytes| self.bytes > max_bytes)
+			|| self
+				.max_entries
+				.is_some_and(|max_entries| self.cache.len() > max_entries)
+	}
+
+	/// Evict entries according to the eviction policy until the cache fits within `max_bytes` and `max_entries`.
+	fn evict(&mut self) {
+		while self.is_over_capacity() {
+			let Some((_, key)) = self.order.pop_first() else {
+				break;
+			};
+
+			if let Some(entry) = self.cache.remove(&key) {
+				self.bytes -= entry.data.len();
+			}
+			self.evictions += 1;
+		}
+	}
+
+	/// Read the stored bytes for an entry, recording the hit.
+	fn read(&mut self, key: &str) -> Option<&[u8]> {
+		if self.cache.get(key)?.is_expired(self.expiry_cutoff()) {
+			// Expired entries are left for the next writes to clean up, so reads stay cheap.
+			return None;
+		}
+
+		self.touch(key);
+
+		let entry = self.cache.get_mut(key)?;
+		if self.track_metadata {
+			entry.hits += 1;
+		}
+
+		schema::unwrap(self.schema_version, &entry.data)
+	}
+
+	/// Read and deserialize an entry, evicting it instead if it's corrupt and `on_corrupt` says to.
+	fn get<T: DeserializeOwned>(&mut self, key: &str) -> Result<Option<T>, format::Error> {
+		let format = self.format;
+		let Some(data) = self.read(key) else {
 			return Ok(None);
 		};
 
-		if let Some(expires_at) = expires_at {
-			if expires_at < &SystemTime::now() {
-				// We would ideally clean up expired values here, but that would require a mutable reference to self,
-				// which provides a worse developer experience than just letting the cache grow.
-				return Ok(None);
+		match format.deserialize(data) {
+			Ok(value) => Ok(Some(value)),
+			Err(_) if self.on_corrupt == OnCorrupt::Evict => {
+				self.forget(key);
+
+				Ok(None)
+			},
+			Err(error) => Err(error),
+		}
+	}
+
+	/// Read and deserialize an entry, returning the stored bytes if it can't be deserialized.
+	fn try_get<T: DeserializeOwned>(&mut self, key: &str) -> GetOutcome<T> {
+		let format = self.format;
+		let Some(data) = self.read(key) else {
+			return GetOutcome::Miss;
+		};
+
+		format
+			.deserialize(data)
+			.map_or_else(|_| GetOutcome::Corrupt(data.to_vec()), GetOutcome::Hit)
+	}
+
+	fn ttl(&self, key: &str) -> Option<Ttl> {
+		let entry = self.cache.get(key).filter(|entry| {
+			!entry.is_expired(self.expiry_cutoff())
+				&& schema::unwrap(self.schema_version, &entry.data).is_some()
+		})?;
+
+		Some(entry.expires_at.map_or(Ttl::Forever, |expires_at| {
+			Ttl::In(
+				expires_at
+					.duration_since(SystemTime::now())
+					.unwrap_or(Duration::ZERO),
+			)
+		}))
+	}
+
+	fn put<T: Serialize>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expires_at: Option<SystemTime>,
+	) -> Result<(), Error> {
+		let data = self.encode(value)?;
+
+		self.sweep();
+		self.insert(key, data, expires_at);
+
+		Ok(())
+	}
+
+	fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expires_at: Option<SystemTime>,
+	) -> Result<i64, Error> {
+		self.sweep();
+
+		let cutoff = self.expiry_cutoff();
+
+		if let Some(entry) = self
+			.cache
+			.get_mut(key)
+			.filter(|entry| !entry.is_expired(cutoff))
+		{
+			if let Some(data) = schema::unwrap(self.schema_version, &entry.data) {
+				let value = self
+					.format
+					.deserialize::<i64>(data)?
+					.checked_add(by)
+					.ok_or(Overflow)?;
+				let data = schema::wrap(self.schema_version, self.format.serialize(&value)?);
+
+				self.bytes = self.bytes - entry.data.len() + data.len();
+				entry.data = data;
+				self.touch(key);
+				self.evict();
+
+				return Ok(value);
+			}
+		}
+
+		let data = self.encode(&by)?;
+		self.insert(key, data, expires_at);
+
+		Ok(by)
+	}
+
+	fn replace<T: Serialize + DeserializeOwned>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expires_at: Option<SystemTime>,
+	) -> Result<Option<T>, Error> {
+		let previous = self.read(key).map(<[u8]>::to_vec);
+		self.put(key, value, expires_at)?;
+
+		Ok(previous
+			.map(|data| self.format.deserialize(&data))
+			.transpose()?)
+	}
+
+	/// Whether a key holds a value that hasn't expired, stored with the current schema version.
+	fn contains(&self, key: &str) -> bool {
+		self.cache.get(key).is_some_and(|entry| {
+			!entry.is_expired(self.expiry_cutoff())
+				&& schema::unwrap(self.schema_version, &entry.data).is_some()
+		})
+	}
+
+	fn add<T: Serialize>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expires_at: Option<SystemTime>,
+	) -> Result<bool, Error> {
+		if self.contains(key) {
+			return Ok(false);
+		}
+
+		self.put(key, value, expires_at)?;
+
+		Ok(true)
+	}
+
+	fn compare_and_swap<T: Serialize + DeserializeOwned + PartialEq>(
+		&mut self,
+		key: &str,
+		expected: &T,
+		new: &T,
+		expires_at: Option<SystemTime>,
+	) -> Result<bool, Error> {
+		let format = self.format;
+		let Some(data) = self.read(key) else {
+			return Ok(false);
+		};
+
+		if format.deserialize::<T>(data)? != *expected {
+			return Ok(false);
+		}
+
+		self.put(key, new, expires_at)?;
+
+		Ok(true)
+	}
+
+	fn get_or_put<T: Serialize + DeserializeOwned>(
+		&mut self,
+		key: &str,
+		value: T,
+		expires_at: Option<SystemTime>,
+	) -> Result<T, Error> {
+		let format = self.format;
+		if let Some(data) = self.read(key) {
+			return Ok(format.deserialize(data)?);
+		}
+
+		self.put(key, &value, expires_at)?;
+
+		Ok(value)
+	}
+
+	fn set_expiry(&mut self, key: &str, expires_at: Option<SystemTime>) -> bool {
+		let cutoff = self.expiry_cutoff();
+
+		let Some(entry) = self
+			.cache
+			.get_mut(key)
+			.filter(|entry| !entry.is_expired(cutoff))
+		else {
+			return false;
+		};
+
+		entry.expires_at = expires_at;
+
+		true
+	}
+
+	fn forget(&mut self, key: &str) -> bool {
+		let cutoff = self.expiry_cutoff();
+		let removed = self
+			.remove(key)
+			.is_some_and(|entry| !entry.is_expired(cutoff));
+
+		self.sweep();
+
+		removed
+	}
+
+	fn clear(&mut self) {
+		self.cache.clear();
+		self.order.clear();
+		self.bytes = 0;
+		self.writes_since_sweep = 0;
+	}
+
+	fn keys(&self, prefix: &str) -> Vec<String> {
+		let now = self.expiry_cutoff();
+
+		self.cache
+			.iter()
+			.filter(|(key, entry)| key.starts_with(prefix) && !entry.is_expired(now))
+			.map(|(key, _)| key.clone())
+			.collect()
+	}
+
+	fn remove_where(&mut self, predicate: impl Fn(&str) -> bool) -> usize {
+		let policy = self.eviction_policy;
+		let len = self.cache.len();
+
+		self.cache.retain(|key, entry| {
+			let matches = predicate(key);
+			if matches {
+				self.bytes -= entry.data.len();
+				self.order.remove(&entry.eviction_rank(policy));
 			}
+
+			!matches
+		});
+
+		len - self.cache.len()
+	}
+}
+
+impl Driver for MemoryDriver {
+	type Config = Config;
+	type Error = Error;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		let store = Store {
+			bytes: 0,
+			cache: HashMap::new(),
+			order: BTreeMap::new(),
+			clock: 0,
+			writes_since_sweep: 0,
+			evictions: 0,
+			max_bytes: config.max_bytes,
+			max_entries: config.max_entries,
+			max_value_bytes: config.max_value_bytes,
+			eviction_policy: config.eviction_policy,
+			clock_skew_tolerance: config.clock_skew_tolerance,
+			track_metadata: config.track_metadata,
+			schema_version: config.schema_version,
+			on_corrupt: config.on_corrupt,
+			format: config.format,
+		};
+
+		let store = Arc::new(Mutex::new(store));
+
+		#[cfg(feature = "memory-sweep")]
+		if let Some(interval) = config.sweep_interval {
+			tokio::spawn(sweep_expired(Arc::downgrade(&store), interval));
 		}
 
-		Ok(Some(bitcode::deserialize(data)?))
+		Ok(Self { store })
+	}
+
+	fn format(&self) -> Option<Format> {
+		Some(self.store().format)
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		Ok(self.store().get(key)?)
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		Ok(self.store().try_get(key))
 	}
 
 	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
-		Ok(self.cache.contains_key(key))
+		Ok(self.store().contains(key))
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		Ok(self.store().ttl(key))
+	}
+
+	async fn metadata(&self, key: &str) -> Result<Option<EntryMeta>, Self::Error> {
+		let store = self.store();
+
+		if !store.track_metadata {
+			return Ok(None);
+		}
+
+		Ok(store
+			.cache
+			.get(key)
+			.filter(|entry| !entry.is_expired(store.expiry_cutoff()))
+			.map(|entry| EntryMeta {
+				created_at: entry.created_at,
+				hits: entry.hits,
+			}))
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		Ok(self.store().keys(prefix.unwrap_or_default()))
 	}
 
 	async fn put<T: Serialize + Sync>(
@@ -47,41 +653,133 @@ impl Driver for MemoryDriver {
 		value: &T,
 		duration: Option<Duration>,
 	) -> Result<(), Self::Error> {
-		let data = bitcode::serialize(value)?;
 		let expires_at = duration.map(|duration| SystemTime::now() + duration);
 
-		self.cache.insert(key.to_owned(), (data, expires_at));
+		self.store().put(key, value, expires_at)
+	}
 
-		Ok(())
+	async fn put_until<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		deadline: SystemTime,
+	) -> Result<(), Self::Error> {
+		self.store().put(key, value, Some(deadline))
+	}
+
+	async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		expiry: Option<Duration>,
+	) -> Result<T, Self::Error> {
+		let expires_at = expiry.map(|expiry| SystemTime::now() + expiry);
+
+		self.store().get_or_put(key, value, expires_at)
+	}
+
+	async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<Option<T>, Self::Error> {
+		let expires_at = expiry.map(|expiry| SystemTime::now() + expiry);
+
+		self.store().replace(key, value, expires_at)
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		let expires_at = expiry.map(|expiry| SystemTime::now() + expiry);
+
+		self.store().add(key, value, expires_at)
+	}
+
+	async fn compare_and_swap<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+		new: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		let expires_at = expiry.map(|expiry| SystemTime::now() + expiry);
+
+		self.store()
+			.compare_and_swap(key, expected, new, expires_at)
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		let expires_at = expiry.map(|expiry| SystemTime::now() + expiry);
+
+		self.store().increment(key, by, expires_at)
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		let expires_at = expiry.map(|expiry| SystemTime::now() + expiry);
+
+		Ok(self.store().set_expiry(key, expires_at))
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		Ok(self.store().forget(key))
 	}
 
-	async fn forget(&mut self, key: &str) -> Result<(), Self::Error> {
-		self.cache.remove(key);
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		self.store().remove_where(|key| key.starts_with(prefix));
 
 		Ok(())
 	}
 
+	async fn purge_expired(&mut self) -> Result<usize, Self::Error> {
+		Ok(self.remove_expired())
+	}
+
 	async fn flush(&mut self) -> Result<(), Self::Error> {
-		self.cache.clear();
+		self.store().clear();
 
 		Ok(())
 	}
 }
 
+impl FlushWhere for MemoryDriver {
+	fn flush_where(&mut self, predicate: impl Fn(&str) -> bool) -> usize {
+		self.store().remove_where(predicate)
+	}
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-	#[error("failed to deserialize data")]
-	DeserializationError(#[from] bitcode::Error),
+	#[error(transparent)]
+	Serialization(#[from] format::Error),
+	#[error("the value is {size} bytes, over the limit of {limit} bytes")]
+	ValueTooLarge { size: usize, limit: usize },
+	#[error(transparent)]
+	Overflow(#[from] Overflow),
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use crate::Cache;
+	use crate::{drivers::format::Raw, Cache, CacheStats, Expiry, FormatError, RememberError};
+	use serde::Deserialize;
+	use std::{
+		convert::Infallible,
+		sync::atomic::{AtomicU64, Ordering},
+	};
 
 	#[tokio::test]
 	async fn test_memory_driver() {
-		let mut cache = Cache::<MemoryDriver>::new(()).await.unwrap();
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
 
 		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
 		assert!(!cache.has("foo").await.unwrap());
@@ -94,9 +792,1459 @@ mod tests {
 		assert_eq!(cache.get("foo").await.unwrap(), Some("bar".to_string()));
 		assert!(cache.has("foo").await.unwrap());
 
-		cache.forget("foo").await.unwrap();
+		assert!(cache.forget("foo").await.unwrap());
 
 		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
 		assert!(!cache.has("foo").await.unwrap());
+		assert!(!cache.forget("foo").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_scoped_prefix() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		cache.forever("foo", "root").await.unwrap();
+
+		let mut scoped = cache.with_prefix("users:");
+		assert_eq!(scoped.prefix(), "users:");
+		assert_eq!(scoped.get::<String>("foo").await.unwrap(), None);
+
+		scoped.forever("foo", "scoped").await.unwrap();
+		assert_eq!(scoped.with_prefix("1:").prefix(), "users:1:");
+
+		assert_eq!(cache.get("foo").await.unwrap(), Some("root".to_string()));
+		assert_eq!(
+			cache.get("users:foo").await.unwrap(),
+			Some("scoped".to_string())
+		);
+
+		// Flushing a scope leaves the keys outside of it alone.
+		cache.with_prefix("users:").flush().await.unwrap();
+		assert_eq!(cache.get::<String>("users:foo").await.unwrap(), None);
+		assert_eq!(cache.get("foo").await.unwrap(), Some("root".to_string()));
+	}
+
+	#[tokio::test]
+	async fn test_memory_try_get() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		assert_eq!(
+			cache.try_get::<String>("foo").await.unwrap(),
+			GetOutcome::Miss
+		);
+
+		cache.forever("foo", "bar").await.unwrap();
+
+		assert_eq!(
+			cache.try_get::<String>("foo").await.unwrap(),
+			GetOutcome::Hit("bar".to_string())
+		);
+		assert!(matches!(
+			cache.try_get::<Vec<String>>("foo").await.unwrap(),
+			GetOutcome::Corrupt(_)
+		));
+	}
+
+	#[tokio::test]
+	async fn test_memory_on_corrupt() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+		cache.forever("foo", "bar").await.unwrap();
+		assert!(cache.get::<Vec<String>>("foo").await.is_err());
+		assert!(cache.has("foo").await.unwrap());
+
+		let mut cache = Cache::<MemoryDriver>::new(Config {
+			on_corrupt: OnCorrupt::Evict,
+			..Default::default()
+		})
+		.await
+		.unwrap();
+		cache.forever("foo", "bar").await.unwrap();
+		assert_eq!(cache.get::<Vec<String>>("foo").await.unwrap(), None);
+		assert!(!cache.has("foo").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_schema_version() {
+		let mut driver = MemoryDriver::new(Config {
+			schema_version: Some(1),
+			..Default::default()
+		})
+		.await
+		.unwrap();
+
+		driver.put("foo", &"bar", None).await.unwrap();
+		assert_eq!(
+			driver.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+
+		driver.store().schema_version = Some(2);
+		assert_eq!(driver.get::<String>("foo").await.unwrap(), None);
+	}
+
+	#[tokio::test]
+	async fn test_memory_ttl() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		assert_eq!(cache.ttl("foo").await.unwrap(), None);
+
+		cache.forever("foo", "bar").await.unwrap();
+		assert_eq!(cache.ttl("foo").await.unwrap(), Some(Ttl::Forever));
+
+		cache
+			.put("foo", &"bar", Duration::from_secs(10))
+			.await
+			.unwrap();
+		let Some(Ttl::In(remaining)) = cache.ttl("foo").await.unwrap() else {
+			panic!("expected the item to expire");
+		};
+		assert!(remaining <= Duration::from_secs(10) && remaining > Duration::from_secs(9));
+
+		cache
+			.put("foo", &"bar", Duration::from_millis(1))
+			.await
+			.unwrap();
+		tokio::time::sleep(Duration::from_millis(5)).await;
+		assert_eq!(cache.ttl("foo").await.unwrap(), None);
+	}
+
+	#[tokio::test]
+	async fn test_memory_touch() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		assert!(!cache.touch("foo", Duration::from_secs(10)).await.unwrap());
+
+		cache
+			.put("foo", &"bar", Duration::from_millis(20))
+			.await
+			.unwrap();
+		assert!(cache.touch("foo", Expiry::Forever).await.unwrap());
+		assert_eq!(cache.ttl("foo").await.unwrap(), Some(Ttl::Forever));
+
+		assert!(cache.touch("foo", Duration::from_millis(1)).await.unwrap());
+		tokio::time::sleep(Duration::from_millis(5)).await;
+		assert!(!cache.touch("foo", Duration::from_secs(10)).await.unwrap());
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+	}
+
+	#[tokio::test]
+	async fn test_memory_format() {
+		let mut driver = MemoryDriver::new(Config {
+			format: Format::Json,
+			..Default::default()
+		})
+		.await
+		.unwrap();
+
+		driver.put("foo", &vec![1, 2], None).await.unwrap();
+
+		assert_eq!(driver.store().cache["foo"].data, b"[1,2]");
+		assert_eq!(driver.get("foo").await.unwrap(), Some(vec![1, 2]));
+	}
+
+	#[tokio::test]
+	async fn test_memory_metadata() {
+		let mut cache = Cache::<MemoryDriver>::new(Config {
+			track_metadata: true,
+			..Default::default()
+		})
+		.await
+		.unwrap();
+
+		assert!(cache.metadata("foo").await.unwrap().is_none());
+
+		cache.forever("foo", "bar").await.unwrap();
+		cache.get::<String>("foo").await.unwrap();
+		cache.get::<String>("foo").await.unwrap();
+
+		let metadata = cache.metadata("foo").await.unwrap().unwrap();
+		assert_eq!(metadata.hits, 2);
+		assert!(metadata.created_at <= SystemTime::now());
+	}
+
+	#[tokio::test]
+	async fn test_memory_remove_expired() {
+		let mut driver = MemoryDriver::new(Config::default()).await.unwrap();
+
+		driver.put("baz", &"qux", None).await.unwrap();
+		driver
+			.put("foo", &"bar", Some(Duration::from_millis(1)))
+			.await
+			.unwrap();
+
+		tokio::time::sleep(Duration::from_millis(5)).await;
+
+		assert_eq!(driver.remove_expired(), 1);
+		assert_eq!(driver.remove_expired(), 0);
+		assert!(driver.has("baz").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_expired_entries() {
+		let mut driver = MemoryDriver::new(Config::default()).await.unwrap();
+
+		driver
+			.put("foo", &"bar", Some(Duration::from_millis(1)))
+			.await
+			.unwrap();
+		tokio::time::sleep(Duration::from_millis(5)).await;
+
+		assert_eq!(driver.get::<String>("foo").await.unwrap(), None);
+		assert!(!driver.has("foo").await.unwrap());
+
+		// Writes sweep the expired entry away.
+		driver.put("baz", &"qux", None).await.unwrap();
+		assert_eq!(driver.store().cache.len(), 1);
+	}
+
+	#[tokio::test]
+	async fn test_memory_counter() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		// Reading a counter that doesn't exist doesn't create it.
+		assert_eq!(cache.counter("hits").get().await.unwrap(), 0);
+		assert!(!cache.has("counter:hits").await.unwrap());
+
+		let mut counter = cache.counter("hits");
+		assert_eq!(counter.inc(2).await.unwrap(), 2);
+		assert_eq!(counter.dec(1).await.unwrap(), 1);
+
+		counter.reset().await.unwrap();
+
+		assert_eq!(counter.get().await.unwrap(), 0);
+	}
+
+	#[tokio::test]
+	async fn test_memory_increment() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		assert_eq!(cache.increment("views", 3).await.unwrap(), 3);
+		assert_eq!(cache.decrement("views", 1).await.unwrap(), 2);
+		assert_eq!(cache.get::<i64>("views").await.unwrap(), Some(2));
+
+		assert_eq!(cache.decrement("credits", 5).await.unwrap(), -5);
+
+		cache.forever("name", "bar").await.unwrap();
+		assert!(cache.increment("name", 1).await.is_err());
+
+		cache.increment("max", i64::MAX).await.unwrap();
+		assert!(matches!(
+			cache.increment("max", 1).await,
+			Err(Error::Overflow(_))
+		));
+		assert_eq!(cache.get::<i64>("max").await.unwrap(), Some(i64::MAX));
+		assert!(matches!(
+			cache.decrement("min", i64::MIN).await,
+			Err(Error::Overflow(_))
+		));
+		assert!(matches!(
+			cache.counter("min").dec(i64::MIN).await,
+			Err(Error::Overflow(_))
+		));
+	}
+
+	#[tokio::test]
+	async fn test_memory_key_encoder() {
+		struct Lowercase;
+
+		impl crate::KeyEncoder for Lowercase {
+			fn encode<'a>(&self, key: &'a str) -> std::borrow::Cow<'a, str> {
+				key.to_lowercase().into()
+			}
+		}
+
+		let mut cache = Cache::<MemoryDriver>::new(Config::default())
+			.await
+			.unwrap()
+			.with_key_encoder(Lowercase);
+
+		cache.forever("Foo", "bar").await.unwrap();
+
+		assert_eq!(cache.get::<String>("FOO").await.unwrap().unwrap(), "bar");
+		assert!(cache.driver.has("foo").await.unwrap());
+		assert!(cache.forget("fOo").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_warm() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		let entries = (0..10)
+			.map(|i| {
+				let loader = move || async move {
+					if i == 3 {
+						Err("database unavailable")
+					} else {
+						Ok(i * 2)
+					}
+				};
+
+				let expiry = Duration::from_secs(if i % 2 == 0 { 10 } else { 60 });
+
+				(format!("item:{i}"), expiry, loader)
+			})
+			.collect();
+
+		let summary = cache.warm(entries, 4).await.unwrap();
+
+		assert_eq!(summary.loaded.len(), 9);
+		assert_eq!(
+			summary.failed,
+			vec![("item:3".to_string(), "database unavailable")]
+		);
+		assert_eq!(cache.get::<i32>("item:5").await.unwrap(), Some(10));
+		assert_eq!(cache.get::<i32>("item:8").await.unwrap(), Some(16));
+		assert!(!cache.has("item:3").await.unwrap());
+
+		let Some(Ttl::In(remaining)) = cache.ttl("item:5").await.unwrap() else {
+			panic!("item:5 should expire");
+		};
+		assert!(remaining > Duration::from_secs(10));
+	}
+
+	#[tokio::test]
+	async fn test_memory_key_normalization() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default())
+			.await
+			.unwrap()
+			.with_key_normalization(crate::KeyNormalization::Trim)
+			.with_key_normalization(crate::KeyNormalization::Lowercase);
+
+		cache.forever(" Foo ", "bar").await.unwrap();
+
+		assert_eq!(cache.get::<String>("FOO").await.unwrap().unwrap(), "bar");
+		assert!(cache.driver.has("foo").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_max_bytes() {
+		let mut driver = MemoryDriver::new(Config {
+			max_bytes: Some(64),
+			..Default::default()
+		})
+		.await
+		.unwrap();
+
+		driver.put("a", &[0_u8; 20], None).await.unwrap();
+		driver.put("b", &[0_u8; 20], None).await.unwrap();
+		assert_eq!(driver.current_bytes(), 40);
+
+		// Reading `a` makes `b` the least recently used entry.
+		driver.get::<[u8; 20]>("a").await.unwrap();
+		driver.put("c", &[0_u8; 30], None).await.unwrap();
+
+		assert!(driver.has("a").await.unwrap());
+		assert!(!driver.has("b").await.unwrap());
+		assert!(driver.has("c").await.unwrap());
+		assert_eq!(driver.current_bytes(), 50);
+
+		driver.forget("a").await.unwrap();
+		assert_eq!(driver.current_bytes(), 30);
+	}
+
+	#[tokio::test]
+	async fn test_memory_max_entries() {
+		let mut driver = MemoryDriver::new(Config {
+			max_entries: Some(3),
+			..Default::default()
+		})
+		.await
+		.unwrap();
+
+		for key in ["a", "b", "c"] {
+			driver.put(key, &key, None).await.unwrap();
+		}
+		assert_eq!(driver.evictions(), 0);
+
+		driver.put("d", &"d", None).await.unwrap();
+
+		assert!(!driver.has("a").await.unwrap());
+		for key in ["b", "c", "d"] {
+			assert!(driver.has(key).await.unwrap());
+		}
+		assert_eq!(driver.store().cache.len(), 3);
+		assert_eq!(driver.evictions(), 1);
+
+		// Removed entries leave the eviction order too, so the next eviction picks an entry that's still there.
+		driver.forget("b").await.unwrap();
+		driver.get::<String>("c").await.unwrap();
+		driver.put("e", &"e", None).await.unwrap();
+		driver.put("f", &"f", None).await.unwrap();
+
+		assert!(!driver.has("d").await.unwrap());
+		for key in ["c", "e", "f"] {
+			assert!(driver.has(key).await.unwrap());
+		}
+		assert_eq!(driver.store().order.len(), 3);
+		assert_eq!(driver.evictions(), 2);
+	}
+
+	#[tokio::test]
+	async fn test_memory_eviction_policies() {
+		async fn survivors(eviction_policy: EvictionPolicy) -> Vec<&'static str> {
+			let mut driver = MemoryDriver::new(Config {
+				eviction_policy,
+				max_bytes: Some(30),
+				..Default::default()
+			})
+			.await
+			.unwrap();
+
+			driver.put("hot", &[0_u8; 10], None).await.unwrap();
+			for _ in 0..3 {
+				driver.get::<[u8; 10]>("hot").await.unwrap();
+			}
+
+			// A scan over keys that are read once, which pushes the hot key out of an LRU cache.
+			for key in ["scan:1", "scan:2", "scan:3"] {
+				driver.put(key, &[0_u8; 10], None).await.unwrap();
+				driver.get::<[u8; 10]>(key).await.unwrap();
+			}
+
+			let mut survivors = Vec::new();
+			for key in ["hot", "scan:1", "scan:2", "scan:3"] {
+				if driver.has(key).await.unwrap() {
+					survivors.push(key);
+				}
+			}
+
+			survivors
+		}
+
+		assert_eq!(
+			survivors(EvictionPolicy::Lru).await,
+			["scan:1", "scan:2", "scan:3"]
+		);
+		assert_eq!(
+			survivors(EvictionPolicy::Lfu).await,
+			["hot", "scan:1", "scan:2"]
+		);
+		assert_eq!(
+			survivors(EvictionPolicy::Fifo).await,
+			["scan:1", "scan:2", "scan:3"]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_memory_get_or_put() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		assert_eq!(
+			cache.get_or_put("foo", 1, Expiry::Forever).await.unwrap(),
+			1
+		);
+		assert_eq!(
+			cache.get_or_put("foo", 2, Expiry::Forever).await.unwrap(),
+			1
+		);
+		assert_eq!(cache.get::<i32>("foo").await.unwrap(), Some(1));
+	}
+
+	#[tokio::test]
+	async fn test_memory_flush_where() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		cache.forever("users:1", "a").await.unwrap();
+		cache.forever("users:2", "b").await.unwrap();
+		cache.forever("posts:1", "c").await.unwrap();
+
+		assert_eq!(cache.with_prefix("users:").flush_where(|key| key == "2"), 1);
+		assert_eq!(cache.flush_where(|key| key.ends_with(":1")), 2);
+
+		assert!(!cache.has("users:1").await.unwrap());
+		assert!(!cache.has("users:2").await.unwrap());
+		assert!(!cache.has("posts:1").await.unwrap());
+		assert_eq!(cache.driver.current_bytes(), 0);
+	}
+
+	#[tokio::test]
+	async fn test_memory_clock_skew_tolerance() {
+		let mut driver = MemoryDriver::new(Config {
+			clock_skew_tolerance: Duration::from_secs(1),
+			..Default::default()
+		})
+		.await
+		.unwrap();
+
+		driver
+			.put("foo", &"bar", Some(Duration::from_millis(1)))
+			.await
+			.unwrap();
+		tokio::time::sleep(Duration::from_millis(5)).await;
+
+		assert!(driver.get::<String>("foo").await.unwrap().is_some());
+		assert_eq!(driver.remove_expired(), 0);
+
+		driver.store().clock_skew_tolerance = Duration::ZERO;
+		assert!(driver.get::<String>("foo").await.unwrap().is_none());
+	}
+
+	#[tokio::test]
+	async fn test_memory_remember_with_previous() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+		let expiry = Duration::from_millis(20);
+
+		let total = cache
+			.remember_with_previous("total", expiry, |previous: Option<u32>| async move {
+				assert_eq!(previous, None);
+				10
+			})
+			.await
+			.unwrap();
+		assert_eq!(total, 10);
+
+		tokio::time::sleep(Duration::from_millis(25)).await;
+		assert_eq!(cache.get::<u32>("total").await.unwrap(), None);
+
+		let total = cache
+			.remember_with_previous("total", expiry, |previous: Option<u32>| async move {
+				previous.unwrap_or_default() + 5
+			})
+			.await
+			.unwrap();
+		assert_eq!(total, 15);
+
+		// Forgetting the item leaves the copy (in its reserved namespace) for the next call.
+		assert!(cache.forget("total").await.unwrap());
+		assert!(cache.driver.has("__previous:total").await.unwrap());
+		let total = cache
+			.remember_with_previous("total", expiry, |previous: Option<u32>| async move {
+				previous.unwrap_or_default() + 1
+			})
+			.await
+			.unwrap();
+		assert_eq!(total, 16);
+	}
+
+	#[tokio::test]
+	async fn test_memory_remember_with_previous_stale_window() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default())
+			.await
+			.unwrap()
+			.with_stale_window(Duration::from_millis(10));
+		let expiry = Duration::from_millis(10);
+
+		cache
+			.remember_with_previous("total", expiry, |_: Option<u32>| async { 10 })
+			.await
+			.unwrap();
+
+		tokio::time::sleep(Duration::from_millis(25)).await;
+
+		let total = cache
+			.remember_with_previous("total", expiry, |previous: Option<u32>| async move {
+				assert_eq!(previous, None);
+				5
+			})
+			.await
+			.unwrap();
+		assert_eq!(total, 5);
+
+		assert_eq!(cache.flush_where(|key| key == "total"), 1);
+	}
+
+	#[tokio::test]
+	async fn test_memory_remember_with() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		let value = cache
+			.remember_with("foo", Duration::from_secs(10), || async {
+				Ok::<_, Infallible>("bar".to_string())
+			})
+			.await
+			.unwrap();
+		assert_eq!(value, "bar");
+
+		let value = cache
+			.remember_with::<String, Infallible, _, _>("foo", Duration::from_secs(10), || async {
+				unreachable!("the loader shouldn't run on a hit")
+			})
+			.await
+			.unwrap();
+		assert_eq!(value, "bar");
+
+		let value = cache
+			.remember_forever_with("baz", || async { Ok::<_, Infallible>(42_u32) })
+			.await
+			.unwrap();
+		assert_eq!(value, 42);
+		assert_eq!(
+			cache
+				.remember_forever_with::<u32, Infallible, _, _>("baz", || async {
+					unreachable!("the loader shouldn't run on a hit")
+				})
+				.await
+				.unwrap(),
+			42
+		);
+
+		let result = cache
+			.remember_with::<u32, _, _, _>("failing", Duration::from_secs(10), || async {
+				Err("upstream unavailable")
+			})
+			.await;
+		assert!(matches!(
+			result,
+			Err(RememberError::Producer("upstream unavailable"))
+		));
+		assert!(!cache.has("failing").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_get_many() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		assert_eq!(
+			cache.get_many::<u32>(&[]).await.unwrap(),
+			Vec::<Option<u32>>::new()
+		);
+
+		cache
+			.put_many(&[("a", 1_u32), ("b", 2)], Duration::from_secs(10))
+			.await
+			.unwrap();
+		cache
+			.with_prefix("users:")
+			.put_many(&[("a", 3_u32)], Expiry::Forever)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			cache.get_many::<u32>(&["b", "missing", "a"]).await.unwrap(),
+			vec![Some(2), None, Some(1)]
+		);
+		assert_eq!(
+			cache.get_map::<u32>(&["b", "missing", "a"]).await.unwrap(),
+			HashMap::from([("a".to_string(), 1), ("b".to_string(), 2)])
+		);
+		assert_eq!(
+			cache
+				.with_prefix("users:")
+				.get_many::<u32>(&["a", "b"])
+				.await
+				.unwrap(),
+			vec![Some(3), None]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_memory_get_many_with_foreign_values() {
+		let config = Config::builder().format(Format::Json).build();
+		let mut cache = Cache::<MemoryDriver>::new(config.clone()).await.unwrap();
+
+		cache.forever("a", 1_u32).await.unwrap();
+		cache.forever("b", "not a number").await.unwrap();
+		cache.forever("c", 3_u32).await.unwrap();
+
+		assert_eq!(
+			cache
+				.get_many::<u32>(&["a", "b", "missing", "c"])
+				.await
+				.unwrap(),
+			vec![Some(1), None, None, Some(3)]
+		);
+
+		let mut strict = Cache::<MemoryDriver>::new(config)
+			.await
+			.unwrap()
+			.with_strict_get_many();
+		strict.forever("a", 1_u32).await.unwrap();
+		strict.forever("b", "not a number").await.unwrap();
+		assert!(strict.get_many::<u32>(&["a", "b"]).await.is_err());
+	}
+
+	#[tokio::test]
+	#[cfg(feature = "stream")]
+	async fn test_memory_streaming() {
+		use tokio::io::AsyncReadExt;
+
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		assert!(cache.get_stream("blob").await.unwrap().is_none());
+
+		// Drivers that can't stream read the whole blob, and store it as-is.
+		cache
+			.with_prefix("files:")
+			.put_stream("blob", b"hello world".as_slice(), Expiry::Forever)
+			.await
+			.unwrap();
+
+		let mut streamed = Vec::new();
+		cache
+			.with_prefix("files:")
+			.get_stream("blob")
+			.await
+			.unwrap()
+			.unwrap()
+			.read_to_end(&mut streamed)
+			.await
+			.unwrap();
+		assert_eq!(streamed, b"hello world");
+		assert!(cache.has("files:blob").await.unwrap());
+
+		cache
+			.put_stream("files:blob", b"".as_slice(), Duration::ZERO)
+			.await
+			.unwrap();
+		assert!(cache.get_stream("files:blob").await.unwrap().is_none());
+	}
+
+	#[tokio::test]
+	async fn test_memory_dump_and_restore() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+		cache.forever("a", 1_u32).await.unwrap();
+		cache
+			.put("b", &"two", Duration::from_secs(60))
+			.await
+			.unwrap();
+
+		let mut entries = cache.dump().await.unwrap();
+		entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries[0].2, None);
+		assert!(entries[1]
+			.2
+			.is_some_and(|remaining| remaining <= Duration::from_secs(60)));
+
+		let mut restored = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+		restored.restore(entries).await.unwrap();
+
+		assert_eq!(restored.get::<u32>("a").await.unwrap(), Some(1));
+		assert_eq!(
+			restored.get::<String>("b").await.unwrap(),
+			Some("two".to_string())
+		);
+		assert_eq!(restored.ttl("a").await.unwrap(), Some(Ttl::Forever));
+		assert!(matches!(restored.ttl("b").await.unwrap(), Some(Ttl::In(_))));
+	}
+
+	#[tokio::test]
+	async fn test_memory_put_with_format() {
+		#[derive(Debug, PartialEq, Serialize, Deserialize)]
+		struct Flattened {
+			id: u32,
+			#[serde(flatten)]
+			extra: HashMap<String, String>,
+		}
+
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+		let value = Flattened {
+			id: 1,
+			extra: HashMap::from([("name".to_string(), "amnesia".to_string())]),
+		};
+
+		cache
+			.put_with_format("user", &value, Format::Json, Expiry::Forever)
+			.await
+			.unwrap();
+		assert_eq!(
+			cache.get_with_format::<Flattened>("user").await.unwrap(),
+			Some(value)
+		);
+		assert_eq!(
+			cache.get_with_format::<Flattened>("missing").await.unwrap(),
+			None
+		);
+
+		cache.forever("unknown", Raw(vec![9])).await.unwrap();
+		assert!(matches!(
+			cache.get_with_format::<Flattened>("unknown").await,
+			Err(FormatError::UnknownFormat(Some(9)))
+		));
+	}
+
+	#[tokio::test]
+	async fn test_memory_get_migrating() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		cache.forever("user_1", "alice").await.unwrap();
+
+		assert_eq!(
+			cache
+				.get_migrating::<String>("users:1", "user_1", Expiry::Forever)
+				.await
+				.unwrap(),
+			Some("alice".to_string())
+		);
+		assert!(cache.has("users:1").await.unwrap());
+		assert!(!cache.has("user_1").await.unwrap());
+
+		assert_eq!(
+			cache
+				.get_migrating::<String>("users:2", "user_2", Expiry::Forever)
+				.await
+				.unwrap(),
+			None
+		);
+	}
+
+	#[tokio::test]
+	async fn test_memory_millisecond_expiry() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		cache.put_millis("foo", &"bar", 1500).await.unwrap();
+
+		tokio::time::sleep(Duration::from_secs(1)).await;
+		assert!(cache.get::<String>("foo").await.unwrap().is_some());
+
+		tokio::time::sleep(Duration::from_millis(600)).await;
+		assert!(cache.get::<String>("foo").await.unwrap().is_none());
+	}
+
+	#[tokio::test]
+	async fn test_memory_get_many_or_compute() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+		cache.forever("a", 1).await.unwrap();
+
+		let values = cache
+			.get_many_or_compute(
+				&["a", "b", "c", "b"],
+				Duration::from_secs(10),
+				|missing| async move {
+					assert_eq!(missing, ["b", "c"]);
+
+					vec![("b".to_string(), 2)]
+				},
+			)
+			.await
+			.unwrap();
+
+		assert_eq!(values, [Some(1), Some(2), None, Some(2)]);
+		assert_eq!(cache.get::<i32>("b").await.unwrap(), Some(2));
+	}
+
+	#[tokio::test]
+	async fn test_memory_version_prefix() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default())
+			.await
+			.unwrap()
+			.with_version_prefix("v1:");
+
+		cache.forever("foo", "old").await.unwrap();
+		assert!(cache.driver.has("v1:foo").await.unwrap());
+
+		cache.version_prefix = Some("v2:".to_string());
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+	}
+
+	#[tokio::test]
+	async fn test_memory_forget_if() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+		cache.forever("lock", "owner-a".to_string()).await.unwrap();
+
+		assert!(!cache
+			.forget_if("lock", &"owner-b".to_string())
+			.await
+			.unwrap());
+		assert!(cache.has("lock").await.unwrap());
+
+		assert!(cache
+			.forget_if("lock", &"owner-a".to_string())
+			.await
+			.unwrap());
+		assert!(!cache.has("lock").await.unwrap());
+		assert!(!cache
+			.forget_if("lock", &"owner-a".to_string())
+			.await
+			.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_clones_share_entries() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+		let mut clone = cache.clone();
+
+		tokio::spawn(async move { clone.forever("foo", "bar").await.unwrap() })
+			.await
+			.unwrap();
+		assert_eq!(
+			cache.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+
+		cache.forget("foo").await.unwrap();
+		assert_eq!(cache.clone().get::<String>("foo").await.unwrap(), None);
+	}
+
+	#[tokio::test]
+	async fn test_memory_tags() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		cache
+			.tags(&["posts", "user:1"])
+			.forever("first", "hello")
+			.await
+			.unwrap();
+		cache
+			.tags(&["posts"])
+			.forever("second", "world")
+			.await
+			.unwrap();
+		cache.tags(&["users"]).forever("third", "!").await.unwrap();
+		cache.forever("untagged", "?").await.unwrap();
+
+		assert_eq!(
+			cache
+				.tags(&["user:1", "posts"])
+				.get::<String>("first")
+				.await
+				.unwrap(),
+			Some("hello".to_string())
+		);
+		assert!(!cache.has("first").await.unwrap());
+
+		cache.tags(&["posts"]).flush().await.unwrap();
+
+		assert!(!cache.tags(&["posts", "user:1"]).has("first").await.unwrap());
+		assert!(!cache.tags(&["posts"]).has("second").await.unwrap());
+		assert!(cache.tags(&["users"]).has("third").await.unwrap());
+		assert!(cache.has("untagged").await.unwrap());
+
+		// Tags are kept apart, even when one of them contains the separator.
+		cache.tags(&["a,b"]).forever("key", 1).await.unwrap();
+		cache.tags(&["a", "b"]).forever("key", 2).await.unwrap();
+		assert_eq!(cache.tags(&["a,b"]).get("key").await.unwrap(), Some(1));
+		assert_eq!(cache.tags(&["b", "a"]).get("key").await.unwrap(), Some(2));
+	}
+
+	#[tokio::test]
+	async fn test_memory_put_tagged() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		cache
+			.put_tagged("profile", &"alice", Expiry::Forever, &["users", "user:1"])
+			.await
+			.unwrap();
+		assert_eq!(
+			cache
+				.tags(&["user:1", "users"])
+				.get::<String>("profile")
+				.await
+				.unwrap(),
+			Some("alice".to_string())
+		);
+
+		// Storing the key with other tags stores a separate item, which the old tags don't see.
+		cache
+			.put_tagged("profile", &"bob", Expiry::Forever, &["users"])
+			.await
+			.unwrap();
+		assert_eq!(
+			cache
+				.tags(&["users", "user:1"])
+				.get::<String>("profile")
+				.await
+				.unwrap(),
+			Some("alice".to_string())
+		);
+		assert_eq!(
+			cache
+				.tags(&["users"])
+				.get::<String>("profile")
+				.await
+				.unwrap(),
+			Some("bob".to_string())
+		);
+
+		cache.flush_tags(&["users"]).await.unwrap();
+		assert!(!cache
+			.tags(&["users", "user:1"])
+			.has("profile")
+			.await
+			.unwrap());
+		assert!(!cache.tags(&["users"]).has("profile").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_lock() {
+		let cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		let mut first = cache.lock("job", Duration::from_secs(10));
+		let mut second = cache.lock("job", Duration::from_secs(10));
+
+		assert!(first.try_acquire().await.unwrap());
+		assert!(!second.try_acquire().await.unwrap());
+
+		// Only the holder can release the lock.
+		assert!(!second.release().await.unwrap());
+		assert!(first.release().await.unwrap());
+		assert!(second.try_acquire().await.unwrap());
+
+		drop(second);
+		let mut third = cache.lock("job", Duration::from_secs(10));
+		third
+			.acquire(|| tokio::time::sleep(Duration::from_millis(10)))
+			.await
+			.unwrap();
+		assert!(third.is_acquired());
+	}
+
+	#[tokio::test]
+	async fn test_memory_single_flight() {
+		let cache = Cache::<MemoryDriver>::new(Config::default())
+			.await
+			.unwrap()
+			.with_single_flight();
+		let loads = Arc::new(AtomicU64::new(0));
+
+		let tasks = (0..10)
+			.map(|_| {
+				let mut cache = cache.clone();
+				let loads = loads.clone();
+
+				tokio::spawn(async move {
+					cache
+						.remember_with("report", Duration::from_secs(10), || async move {
+							loads.fetch_add(1, Ordering::Relaxed);
+							tokio::time::sleep(Duration::from_millis(20)).await;
+
+							Ok::<_, Infallible>("done".to_string())
+						})
+						.await
+						.unwrap()
+				})
+			})
+			.collect::<Vec<_>>();
+
+		for task in tasks {
+			assert_eq!(task.await.unwrap(), "done");
+		}
+		assert_eq!(loads.load(Ordering::Relaxed), 1);
+	}
+
+	#[tokio::test]
+	async fn test_memory_forget_many() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		cache
+			.put_many(&[("a", 1), ("b", 2), ("c", 3)], Expiry::Forever)
+			.await
+			.unwrap();
+		cache.forget_many(&["a", "c", "missing"]).await.unwrap();
+
+		assert_eq!(
+			cache.get_many::<i32>(&["a", "b", "c"]).await.unwrap(),
+			vec![None, Some(2), None]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_memory_forget_prefix() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		cache
+			.put_many(
+				&[
+					("user:1:posts", 1),
+					("user:1:profile", 2),
+					("user:12:posts", 3),
+				],
+				Expiry::Forever,
+			)
+			.await
+			.unwrap();
+		cache.forget_prefix("user:1:").await.unwrap();
+
+		assert_eq!(
+			cache
+				.get_many::<i32>(&["user:1:posts", "user:1:profile", "user:12:posts"])
+				.await
+				.unwrap(),
+			vec![None, None, Some(3)]
+		);
+
+		let mut scoped = cache.with_prefix("user:");
+		scoped.forget_prefix("12:").await.unwrap();
+		assert!(!cache.has("user:12:posts").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_keys() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default())
+			.await
+			.unwrap()
+			.with_version_prefix("v2:");
+
+		cache
+			.put_many(
+				&[("user:1", 1), ("user:2", 2), ("post:1", 3)],
+				Expiry::Forever,
+			)
+			.await
+			.unwrap();
+		cache
+			.put("user:3", &4, Duration::from_millis(1))
+			.await
+			.unwrap();
+		tokio::time::sleep(Duration::from_millis(5)).await;
+
+		let mut keys = cache.keys(None).await.unwrap();
+		keys.sort();
+		assert_eq!(keys, ["post:1", "user:1", "user:2"]);
+
+		let mut keys = cache.keys(Some("user:")).await.unwrap();
+		keys.sort();
+		assert_eq!(keys, ["user:1", "user:2"]);
+
+		let mut scoped = cache.with_prefix("tenant:");
+		scoped.forever("user:1", 5).await.unwrap();
+		assert_eq!(scoped.keys(None).await.unwrap(), ["user:1"]);
+	}
+
+	#[tokio::test]
+	async fn test_memory_add() {
+		let cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		let tasks = (0..10)
+			.map(|i| {
+				let mut cache = cache.clone();
+				tokio::spawn(async move { cache.add("lock", i, Expiry::Forever).await.unwrap() })
+			})
+			.collect::<Vec<_>>();
+
+		let mut added = 0;
+		for task in tasks {
+			added += usize::from(task.await.unwrap());
+		}
+		assert_eq!(added, 1);
+
+		let mut cache = cache;
+		cache
+			.put("expired", &1, Duration::from_millis(1))
+			.await
+			.unwrap();
+		tokio::time::sleep(Duration::from_millis(5)).await;
+		assert!(cache.add("expired", 2, Expiry::Forever).await.unwrap());
+		assert_eq!(cache.get::<i32>("expired").await.unwrap(), Some(2));
+	}
+
+	#[tokio::test]
+	async fn test_memory_replace() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		assert_eq!(
+			cache.replace("hits", &1, Expiry::Forever).await.unwrap(),
+			None
+		);
+		assert_eq!(
+			cache.replace("hits", &2, Expiry::Forever).await.unwrap(),
+			Some(1)
+		);
+		assert_eq!(cache.get::<i32>("hits").await.unwrap(), Some(2));
+
+		// Storing an item that expires right away removes it, but still returns the previous one.
+		assert_eq!(
+			cache.replace("hits", &3, Duration::ZERO).await.unwrap(),
+			Some(2)
+		);
+		assert!(!cache.has("hits").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_stats() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default())
+			.await
+			.unwrap()
+			.with_metrics();
+		assert_eq!(cache.stats().hit_rate(), None);
+
+		cache.put("foo", &"bar", Expiry::Forever).await.unwrap();
+		cache.get::<String>("foo").await.unwrap();
+		cache.get::<String>("missing").await.unwrap();
+		cache.get_many::<String>(&["foo", "missing"]).await.unwrap();
+		cache.clone().forget("foo").await.unwrap();
+
+		let stats = cache.stats();
+		assert_eq!(
+			stats,
+			CacheStats {
+				hits: 2,
+				misses: 2,
+				writes: 1,
+				deletes: 1,
+			}
+		);
+		assert_eq!(stats.hit_rate(), Some(0.5));
+
+		cache.has("foo").await.unwrap();
+		cache.forget("missing").await.unwrap();
+		cache.forget_many(&["foo", "missing"]).await.unwrap();
+		cache.put("foo", &"bar", Duration::ZERO).await.unwrap();
+		cache
+			.put_many(&[("foo", "bar"), ("missing", "bar")], Duration::ZERO)
+			.await
+			.unwrap();
+		assert_eq!(
+			cache.stats(),
+			CacheStats {
+				hits: 2,
+				misses: 3,
+				writes: 1,
+				deletes: 7,
+			}
+		);
+
+		let plain = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+		plain.get::<String>("foo").await.unwrap();
+		assert_eq!(plain.stats(), CacheStats::default());
+	}
+
+	#[tokio::test]
+	async fn test_memory_purge_expired() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		cache.forever("foo", 1).await.unwrap();
+		cache
+			.put("bar", &2, Duration::from_millis(1))
+			.await
+			.unwrap();
+		cache
+			.with_prefix("scoped:")
+			.put("baz", &3, Duration::from_millis(1))
+			.await
+			.unwrap();
+		tokio::time::sleep(Duration::from_millis(5)).await;
+
+		assert_eq!(cache.purge_expired().await.unwrap(), 2);
+		assert_eq!(cache.purge_expired().await.unwrap(), 0);
+		assert_eq!(cache.get::<i32>("foo").await.unwrap(), Some(1));
+	}
+
+	#[cfg(feature = "memory-sweep")]
+	#[tokio::test]
+	async fn test_memory_sweep_interval() {
+		let mut driver = MemoryDriver::new(Config {
+			sweep_interval: Some(Duration::from_millis(10)),
+			..Default::default()
+		})
+		.await
+		.unwrap();
+
+		driver
+			.put("foo", &"bar", Some(Duration::from_millis(1)))
+			.await
+			.unwrap();
+		tokio::time::sleep(Duration::from_millis(50)).await;
+		assert!(driver.store().cache.is_empty());
+
+		// The sweeper only holds a weak handle, so dropping the driver frees the entries.
+		let store = Arc::downgrade(&driver.store);
+		drop(driver);
+		assert!(store.upgrade().is_none());
+	}
+
+	#[tokio::test]
+	async fn test_memory_config_builder() {
+		let config = Config::builder()
+			.max_entries(1)
+			.format(Format::Json)
+			.build();
+		assert_eq!(config.max_entries, Some(1));
+		assert_eq!(config.format, Format::Json);
+		assert_eq!(config.eviction_policy, EvictionPolicy::default());
+
+		let mut cache = Cache::<MemoryDriver>::new(config).await.unwrap();
+		cache.forever("foo", 1).await.unwrap();
+		cache.forever("bar", 2).await.unwrap();
+		assert!(!cache.has("foo").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_namespace() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+		let mut users = cache.namespace("users");
+		let mut admins = users.namespace("admins");
+
+		users.forever("1", "Ada").await.unwrap();
+		admins.forever("1", "Grace").await.unwrap();
+		cache.forever("1", "root").await.unwrap();
+
+		assert_eq!(users.get::<String>("1").await.unwrap().unwrap(), "Ada");
+		assert_eq!(admins.get::<String>("1").await.unwrap().unwrap(), "Grace");
+		assert_eq!(
+			cache
+				.get::<String>("users:admins:1")
+				.await
+				.unwrap()
+				.unwrap(),
+			"Grace"
+		);
+		assert_eq!(admins.keys(None).await.unwrap(), ["1"]);
+
+		// Flushing a namespace only removes the items in it (and in the ones nested under it).
+		users.flush().await.unwrap();
+		assert!(!admins.has("1").await.unwrap());
+		assert!(cache.has("1").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_get_or() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+		cache.forever("hits", 3).await.unwrap();
+
+		assert_eq!(cache.get_or("hits", 0).await.unwrap(), 3);
+		assert_eq!(cache.get_or("missing", 7).await.unwrap(), 7);
+		assert_eq!(
+			cache
+				.get_or_else::<i32, _>("hits", || unreachable!(
+					"the default shouldn't be computed on a hit"
+				))
+				.await
+				.unwrap(),
+			3
+		);
+		assert_eq!(cache.get_or_else("missing", || 9).await.unwrap(), 9);
+		assert_eq!(cache.get_or_default::<i32>("missing").await.unwrap(), 0);
+
+		// Fallbacks aren't stored.
+		assert!(!cache.has("missing").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_put_until() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		cache
+			.put_until(
+				"foo",
+				&"bar",
+				SystemTime::now() + Duration::from_millis(300),
+			)
+			.await
+			.unwrap();
+		assert_eq!(
+			cache.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+		assert!(matches!(
+			cache.ttl("foo").await.unwrap(),
+			Some(Ttl::In(remaining)) if remaining <= Duration::from_millis(300)
+		));
+
+		tokio::time::sleep(Duration::from_millis(400)).await;
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+
+		cache.forever("foo", "bar").await.unwrap();
+		cache
+			.put_until("foo", &"baz", SystemTime::now() - Duration::from_secs(1))
+			.await
+			.unwrap();
+		assert!(!cache.has("foo").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_compare_and_swap() {
+		let mut cache = Cache::<MemoryDriver>::new(Config::default()).await.unwrap();
+
+		assert!(!cache
+			.compare_and_swap("state", &1, &2, Duration::from_secs(10))
+			.await
+			.unwrap());
+		assert!(!cache.has("state").await.unwrap());
+
+		cache.forever("state", 1).await.unwrap();
+		assert!(!cache
+			.compare_and_swap("state", &3, &4, Duration::from_secs(10))
+			.await
+			.unwrap());
+		assert_eq!(cache.get::<i32>("state").await.unwrap(), Some(1));
+
+		assert!(cache
+			.compare_and_swap("state", &1, &2, Duration::from_secs(10))
+			.await
+			.unwrap());
+		assert_eq!(cache.get::<i32>("state").await.unwrap(), Some(2));
+		assert!(matches!(
+			cache.ttl("state").await.unwrap(),
+			Some(Ttl::In(_))
+		));
+
+		// Swapping in a value that expires right away removes it.
+		assert!(cache
+			.compare_and_swap("state", &2, &3, Duration::ZERO)
+			.await
+			.unwrap());
+		assert!(!cache.has("state").await.unwrap());
+	}
+
+	#[cfg(feature = "stream")]
+	#[tokio::test]
+	async fn test_memory_scan() {
+		use futures_util::StreamExt;
+
+		let mut cache = Cache::<MemoryDriver>::new(Config::default())
+			.await
+			.unwrap()
+			.namespace("users");
+
+		cache.forever("1", 1).await.unwrap();
+		cache.forever("2", 2).await.unwrap();
+		cache.forever("3", "three").await.unwrap();
+
+		let mut entries = cache.scan::<i32>(None).collect::<Vec<_>>().await;
+		entries.sort_by_key(|entry| entry.as_ref().map(|(key, _)| key.clone()).ok());
+
+		assert_eq!(entries.len(), 3);
+		assert!(entries[0].is_err());
+		assert_eq!(entries[1].as_ref().unwrap(), &("1".to_string(), 1));
+		assert_eq!(entries[2].as_ref().unwrap(), &("2".to_string(), 2));
+
+		let entries = cache.scan::<i32>(Some("2")).collect::<Vec<_>>().await;
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries[0].as_ref().unwrap(), &("2".to_string(), 2));
+	}
+
+	#[tokio::test]
+	async fn test_memory_max_value_bytes() {
+		let mut cache = Cache::<MemoryDriver>::new(Config {
+			max_value_bytes: Some(64),
+			..Config::default()
+		})
+		.await
+		.unwrap();
+
+		cache.forever("small", vec![0_u8; 8]).await.unwrap();
+		assert!(matches!(
+			cache.forever("large", vec![0_u8; 1024]).await,
+			Err(Error::ValueTooLarge { limit: 64, size }) if size > 64
+		));
+		assert!(!cache.has("large").await.unwrap());
+		assert!(cache.has("small").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_put_jittered() {
+		use crate::{JitterSource, RandomJitter};
+
+		struct Fixed(Duration);
+
+		impl JitterSource for Fixed {
+			fn jitter(&self, _: Duration) -> Duration {
+				self.0
+			}
+		}
+
+		let mut cache = Cache::<MemoryDriver>::new(Config::default())
+			.await
+			.unwrap()
+			.with_jitter_source(Fixed(Duration::from_secs(10)));
+
+		cache
+			.put_jittered(
+				"foo",
+				&"bar",
+				Duration::from_secs(100),
+				Duration::from_secs(20),
+			)
+			.await
+			.unwrap();
+		assert!(matches!(
+			cache.ttl("foo").await.unwrap(),
+			Some(Ttl::In(remaining)) if remaining > Duration::from_secs(100) && remaining <= Duration::from_secs(110)
+		));
+
+		let jitter = RandomJitter.jitter(Duration::from_secs(10));
+		assert!(jitter < Duration::from_secs(10));
+		assert_eq!(RandomJitter.jitter(Duration::ZERO), Duration::ZERO);
 	}
 }
@@ -1,42 +1,253 @@
+#[cfg(feature = "stream")]
+use crate::StreamError;
+#[cfg(feature = "stream")]
+use format::Raw;
 use serde::{de::DeserializeOwned, Serialize};
-use std::{future::Future, time::Duration};
+use std::{
+	future::Future,
+	time::{Duration, SystemTime},
+};
+#[cfg(feature = "stream")]
+use tokio::io::{AsyncRead, AsyncReadExt};
 
+#[macro_use]
+mod builder;
+pub mod circuit_breaker;
+#[cfg(feature = "compression")]
+pub mod compressed;
 #[cfg(feature = "database")]
 pub mod database;
+#[cfg(feature = "dynamic")]
+pub mod dynamic;
 #[cfg(feature = "dynamodb")]
 pub mod dynamodb;
+#[cfg(feature = "encryption")]
+pub mod encrypted;
+pub mod fallback;
+#[cfg(feature = "file")]
+pub mod file;
+#[cfg(feature = "format")]
+pub mod format;
+#[cfg(any(feature = "redis", feature = "file", feature = "memcached"))]
+mod hash;
+#[cfg(feature = "memcached")]
+pub mod memcached;
 #[cfg(feature = "memory")]
 pub mod memory;
+#[cfg(feature = "moka")]
+pub mod moka;
 pub mod null;
+#[cfg(feature = "testing")]
+pub mod recording;
 #[cfg(feature = "redis")]
 pub mod redis;
+#[cfg(feature = "redis-cluster")]
+pub mod redis_cluster;
+#[cfg(feature = "retry")]
+pub mod retrying;
+#[cfg(feature = "s3")]
+pub mod s3;
+#[cfg(any(feature = "memory", feature = "redis", feature = "dynamodb"))]
+mod schema;
+pub mod scoped;
+#[cfg(feature = "redis")]
+pub mod sharded_redis;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+#[cfg(feature = "redis")]
+pub mod synced_tiered;
+pub mod tiered;
 
+pub use circuit_breaker::CircuitBreakerDriver;
+#[cfg(feature = "compression")]
+pub use compressed::CompressedDriver;
 #[cfg(feature = "database")]
 pub use database::DatabaseDriver;
+#[cfg(feature = "dynamic")]
+pub use dynamic::DynDriver;
 #[cfg(feature = "dynamodb")]
 pub use dynamodb::DynamoDBDriver;
+#[cfg(feature = "encryption")]
+pub use encrypted::EncryptedDriver;
+pub use fallback::FallbackDriver;
+#[cfg(feature = "file")]
+pub use file::FileDriver;
+#[cfg(feature = "format")]
+pub use format::Format;
+#[cfg(feature = "memcached")]
+pub use memcached::MemcachedDriver;
 #[cfg(feature = "memory")]
 pub use memory::MemoryDriver;
+#[cfg(feature = "moka")]
+pub use moka::MokaDriver;
 pub use null::NullDriver;
+#[cfg(feature = "testing")]
+pub use recording::RecordingDriver;
 #[cfg(feature = "redis")]
 pub use redis::RedisDriver;
+#[cfg(feature = "redis-cluster")]
+pub use redis_cluster::RedisClusterDriver;
+#[cfg(feature = "retry")]
+pub use retrying::RetryingDriver;
+#[cfg(feature = "s3")]
+pub use s3::S3Driver;
+pub use scoped::ScopedDriver;
+#[cfg(feature = "redis")]
+pub use sharded_redis::ShardedRedisDriver;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteDriver;
+#[cfg(feature = "redis")]
+pub use synced_tiered::SyncedTieredDriver;
+pub use tiered::TieredDriver;
+
+/// A blob streamed out of the cache by [`Driver::get_stream`].
+#[cfg(feature = "stream")]
+pub type BlobReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Metadata about an entry in the cache, for drivers that track it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryMeta {
+	/// When the entry was stored.
+	pub created_at: SystemTime,
+	/// How many times the entry has been read.
+	pub hits: u64,
+}
+
+/// The result of reading a value from the cache, distinguishing a miss from a value that couldn't be deserialized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GetOutcome<T> {
+	/// The value was found and deserialized.
+	Hit(T),
+	/// The value doesn't exist (or has expired).
+	Miss,
+	/// The value exists, but couldn't be deserialized. Contains the raw stored bytes.
+	Corrupt(Vec<u8>),
+}
+
+/// What a driver's `get` does with a stored value that can't be deserialized into the requested type (because it's
+/// corrupt, or was stored as a different type).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnCorrupt {
+	/// Return the deserialization error.
+	#[default]
+	Error,
+	/// Remove the entry and treat it as a miss, so the caller recomputes it.
+	Evict,
+}
+
+/// How long an entry has left before it expires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ttl {
+	/// The entry never expires.
+	Forever,
+	/// The entry expires after the given duration.
+	In(Duration),
+}
+
+/// The health of a driver's connection pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStatus {
+	/// The maximum number of connections the pool will open.
+	pub max_size: usize,
+	/// Connections that are open but not currently checked out.
+	pub idle: usize,
+	/// Connections that are currently checked out.
+	pub in_use: usize,
+	/// Callers currently waiting for a connection to become available.
+	pub waiting: usize,
+}
+
+/// The error returned when incrementing or decrementing a counter would take it outside the range of an `i64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("the counter would overflow.")]
+pub struct Overflow;
 
 /// Cache driver.
 pub trait Driver: Sized + Send + Sync {
-	type Error: Send;
+	/// The error returned by the driver's operations. Bounded so it can be boxed, or converted into `anyhow`-style
+	/// error types with `?`, and so counters that would overflow can be reported.
+	type Error: std::error::Error + From<Overflow> + Send + Sync + 'static;
 	type Config: Send;
 
 	fn new(config: Self::Config) -> impl Future<Output = Result<Self, Self::Error>> + Send;
 
+	/// The prefix prepended to every key by this driver.
+	#[allow(clippy::unnecessary_literal_bound)]
+	fn prefix(&self) -> &str {
+		""
+	}
+
+	/// The status of the driver's connection pool, or `None` if it doesn't use one.
+	fn pool_status(&self) -> Option<PoolStatus> {
+		None
+	}
+
+	/// Whether an error means the backend couldn't be reached (like a refused connection or a timeout), as opposed to
+	/// a problem with the operation itself (like a value that couldn't be deserialized).
+	/// The [`FallbackDriver`] treats operations that fail this way as misses.
+	fn is_unavailable(_error: &Self::Error) -> bool {
+		false
+	}
+
+	/// The format values are serialized with, if the driver stores them through a [`Format`].
+	/// `Box<dyn DynDriver>` serializes values with it too, so entries written through the box can be read without it.
+	#[cfg(feature = "format")]
+	fn format(&self) -> Option<Format> {
+		None
+	}
+
 	/// Get a value from the cache.
 	fn get<T: DeserializeOwned>(
 		&self,
 		key: &str,
 	) -> impl Future<Output = Result<Option<T>, Self::Error>> + Send;
 
+	/// Get a value from the cache, returning the raw bytes instead of an error if it can't be deserialized.
+	fn try_get<T: DeserializeOwned>(
+		&self,
+		key: &str,
+	) -> impl Future<Output = Result<GetOutcome<T>, Self::Error>> + Send;
+
+	/// Get many values from the cache, in the same order as `keys`.
+	/// Drivers that can fetch several keys in a single round trip should override this.
+	fn get_many<T: DeserializeOwned + Send>(
+		&self,
+		keys: &[&str],
+	) -> impl Future<Output = Result<Vec<Option<T>>, Self::Error>> + Send {
+		async move {
+			let mut values = Vec::with_capacity(keys.len());
+			for key in keys {
+				values.push(self.get(key).await?);
+			}
+
+			Ok(values)
+		}
+	}
+
 	/// Check if a value exists in the cache.
 	fn has(&self, key: &str) -> impl Future<Output = Result<bool, Self::Error>> + Send;
 
+	/// Get how long a value has left before it expires, or `None` if it doesn't exist.
+	fn ttl(&self, key: &str) -> impl Future<Output = Result<Option<Ttl>, Self::Error>> + Send;
+
+	/// Get the metadata for a value in the cache, if the driver tracks it.
+	fn metadata(
+		&self,
+		_key: &str,
+	) -> impl Future<Output = Result<Option<EntryMeta>, Self::Error>> + Send {
+		async { Ok(None) }
+	}
+
+	/// List the keys of the values in the cache (optionally only the ones starting with `prefix`), in no particular
+	/// order. Keys are returned without the driver's own prefix, as they were passed to it.
+	///
+	/// Like [`forget_prefix`](Driver::forget_prefix), this has to walk every entry on most drivers, so it's meant for
+	/// debugging and admin tooling rather than regular use.
+	fn keys(
+		&self,
+		prefix: Option<&str>,
+	) -> impl Future<Output = Result<Vec<String>, Self::Error>> + Send;
+
 	/// Put a value into the cache.
 	fn put<T: Serialize + Sync>(
 		&mut self,
@@ -45,9 +256,245 @@ pub trait Driver: Sized + Send + Sync {
 		expiry: Option<Duration>,
 	) -> impl Future<Output = Result<(), Self::Error>> + Send;
 
-	/// Remove a value from the cache.
-	fn forget(&mut self, key: &str) -> impl Future<Output = Result<(), Self::Error>> + Send;
+	/// Put a value into the cache until the given deadline, removing it instead if the deadline has already passed.
+	/// Drivers that store absolute expirations should override this, so the deadline isn't shifted by turning it into a
+	/// duration first.
+	fn put_until<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		data: &T,
+		deadline: SystemTime,
+	) -> impl Future<Output = Result<(), Self::Error>> + Send {
+		async move {
+			match deadline.duration_since(SystemTime::now()) {
+				Ok(remaining) if !remaining.is_zero() => self.put(key, data, Some(remaining)).await,
+				_ => self.forget(key).await.map(|_| ()),
+			}
+		}
+	}
+
+	/// Put many values into the cache, all with the same expiry.
+	/// Drivers that can store several keys in a single round trip should override this.
+	fn put_many<T: Serialize + Sync>(
+		&mut self,
+		entries: &[(&str, &T)],
+		expiry: Option<Duration>,
+	) -> impl Future<Output = Result<(), Self::Error>> + Send {
+		async move {
+			for (key, value) in entries {
+				self.put(key, *value, expiry).await?;
+			}
+
+			Ok(())
+		}
+	}
+
+	/// Stream a blob stored with [`put_stream`](Driver::put_stream) out of the cache.
+	/// Drivers that can read values in chunks (like the file and S3 drivers) should override this, instead of reading the
+	/// whole blob into memory first.
+	#[cfg(feature = "stream")]
+	fn get_stream(
+		&self,
+		key: &str,
+	) -> impl Future<Output = Result<Option<BlobReader>, Self::Error>> + Send {
+		async move {
+			Ok(self
+				.get::<Raw>(key)
+				.await?
+				.map(|Raw(data)| Box::new(std::io::Cursor::new(data)) as BlobReader))
+		}
+	}
+
+	/// Store the blob read from `reader` in the cache, as-is instead of serialized like other values.
+	/// Drivers that can write values in chunks (like the file and S3 drivers) should override this, instead of reading
+	/// the whole blob into memory first.
+	#[cfg(feature = "stream")]
+	fn put_stream<R: AsyncRead + Send + Unpin>(
+		&mut self,
+		key: &str,
+		mut reader: R,
+		expiry: Option<Duration>,
+	) -> impl Future<Output = Result<(), StreamError<Self::Error>>> + Send {
+		async move {
+			let mut data = Vec::new();
+			reader
+				.read_to_end(&mut data)
+				.await
+				.map_err(StreamError::Read)?;
+
+			self.put(key, &Raw(data), expiry)
+				.await
+				.map_err(StreamError::Cache)
+		}
+	}
+
+	/// Get a value from the cache, or store the given one if it doesn't exist yet, returning whichever ends up cached.
+	/// Drivers that can check and store in a single atomic operation should override this.
+	fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		expiry: Option<Duration>,
+	) -> impl Future<Output = Result<T, Self::Error>> + Send {
+		async move {
+			if let Some(cached) = self.get(key).await? {
+				return Ok(cached);
+			}
+
+			self.put(key, &value, expiry).await?;
+
+			Ok(value)
+		}
+	}
+
+	/// Store a value in the cache, returning the one it replaced.
+	/// Drivers that can swap values in a single atomic operation should override this.
+	fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> impl Future<Output = Result<Option<T>, Self::Error>> + Send {
+		async move {
+			let previous = self.get(key).await?;
+			self.put(key, value, expiry).await?;
+
+			Ok(previous)
+		}
+	}
+
+	/// Store a value in the cache only if it doesn't exist yet, returning whether it was stored.
+	/// Drivers that can check and store in a single atomic operation should override this, so only one of several
+	/// concurrent callers gets `true`.
+	fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> impl Future<Output = Result<bool, Self::Error>> + Send {
+		async move {
+			if self.has(key).await? {
+				return Ok(false);
+			}
+
+			self.put(key, value, expiry).await?;
+
+			Ok(true)
+		}
+	}
+
+	/// Store a value only if the current one is equal to `expected`, returning whether it was stored.
+	/// Drivers that can compare and write in a single atomic operation should override this, so only one of several
+	/// concurrent callers swapping the same value gets `true`.
+	fn compare_and_swap<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+		new: &T,
+		expiry: Option<Duration>,
+	) -> impl Future<Output = Result<bool, Self::Error>> + Send {
+		async move {
+			if self.get::<T>(key).await?.as_ref() != Some(expected) {
+				return Ok(false);
+			}
+
+			self.put(key, new, expiry).await?;
+
+			Ok(true)
+		}
+	}
+
+	/// Increment a numeric value in the cache, initializing it to zero if it doesn't exist.
+	/// The expiry is only applied when the value is created. Returns [`Overflow`] (leaving the value unchanged) if the
+	/// result wouldn't fit in an `i64`.
+	fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> impl Future<Output = Result<i64, Self::Error>> + Send;
+
+	/// Change when a value expires without rewriting it, returning whether it existed.
+	fn touch(
+		&mut self,
+		key: &str,
+		expiry: Option<Duration>,
+	) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+	/// Remove a value from the cache, returning whether it existed.
+	fn forget(&mut self, key: &str) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+	/// Remove many values from the cache.
+	/// Drivers that can remove several keys in a single round trip should override this.
+	fn forget_many(
+		&mut self,
+		keys: &[&str],
+	) -> impl Future<Output = Result<(), Self::Error>> + Send {
+		async move {
+			for key in keys {
+				self.forget(key).await?;
+			}
+
+			Ok(())
+		}
+	}
+
+	/// Remove every value whose key starts with `prefix`.
+	///
+	/// Most drivers can't look keys up by prefix, so this walks every entry (or every key on Redis, or the whole table
+	/// on `DynamoDB`), making it O(n) in the size of the cache rather than in the number of removed values.
+	fn forget_prefix(
+		&mut self,
+		prefix: &str,
+	) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+	/// Remove a value from the cache only if it's equal to `expected`, returning whether it was removed.
+	/// Drivers that can compare and delete in a single atomic operation should override this.
+	fn forget_if<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+	) -> impl Future<Output = Result<bool, Self::Error>> + Send {
+		async move {
+			if self.get::<T>(key).await?.as_ref() != Some(expected) {
+				return Ok(false);
+			}
+
+			self.forget(key).await
+		}
+	}
+
+	/// Remove every expired value from the cache, returning how many were removed.
+	///
+	/// Drivers that only skip expired values when reading them keep them around until they're overwritten, so this
+	/// gives long-lived processes a way to reclaim the space. Backends that expire values on their own (like Redis,
+	/// or `DynamoDB` with TTL enabled) have nothing to do, and return zero.
+	fn purge_expired(&mut self) -> impl Future<Output = Result<usize, Self::Error>> + Send {
+		async { Ok(0) }
+	}
 
 	/// Remove all values from the cache.
 	fn flush(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send;
 }
+
+/// A driver that can remove every entry whose key matches a predicate.
+///
+/// Only implemented by drivers that can cheaply enumerate their keys (like [`MemoryDriver`]), so
+/// [`Cache::flush_where`](crate::Cache::flush_where) isn't available on backends where it would require a full scan.
+pub trait FlushWhere: Driver {
+	/// Remove every entry whose key matches the predicate, returning how many were removed.
+	fn flush_where(&mut self, predicate: impl Fn(&str) -> bool) -> usize;
+}
+
+/// Whether an AWS request failed without a response, because the service couldn't be reached or took too long.
+#[cfg(any(feature = "dynamodb", feature = "s3"))]
+const fn is_aws_unavailable<E, R>(
+	error: &aws_smithy_runtime_api::client::result::SdkError<E, R>,
+) -> bool {
+	use aws_smithy_runtime_api::client::result::SdkError;
+
+	matches!(
+		error,
+		SdkError::DispatchFailure(_) | SdkError::TimeoutError(_)
+	)
+}
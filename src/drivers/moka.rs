@@ -0,0 +1,433 @@
+use super::{format, Driver, Format, GetOutcome, Overflow, Ttl};
+use moka::{future::Cache, ops::compute::Op, Expiry};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	fmt,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+	/// The maximum total size of the stored keys and (serialized) values, in bytes.
+	/// Once it's reached, the entries least likely to be read again are evicted.
+	pub max_capacity: Option<u64>,
+	/// Evict entries that haven't been read or written for this long, regardless of their own expiry.
+	pub time_to_idle: Option<Duration>,
+	/// The format values are serialized with.
+	pub format: Format,
+}
+
+config_builder! {
+	max_capacity: Option<u64>,
+	time_to_idle: Option<Duration>,
+	format: Format,
+}
+
+#[derive(Clone)]
+#[allow(clippy::module_name_repetitions)]
+/// A driver that stores values in memory using [`moka`], a concurrent cache.
+///
+/// Unlike [`MemoryDriver`](super::MemoryDriver), entries are never locked as a whole, and expired or evicted entries
+/// are cleaned up in the background. Clones share the same entries.
+pub struct MokaDriver {
+	format: Format,
+	cache: Cache<String, Entry>,
+}
+
+#[derive(Clone)]
+struct Entry {
+	data: Arc<[u8]>,
+	expires_at: Option<Instant>,
+}
+
+/// Expires entries at the time they were stored with.
+struct EntryExpiry;
+
+impl Expiry<String, Entry> for EntryExpiry {
+	fn expire_after_create(
+		&self,
+		_key: &String,
+		entry: &Entry,
+		created_at: Instant,
+	) -> Option<Duration> {
+		entry
+			.expires_at
+			.map(|expires_at| expires_at.saturating_duration_since(created_at))
+	}
+
+	fn expire_after_update(
+		&self,
+		_key: &String,
+		entry: &Entry,
+		updated_at: Instant,
+		_duration_until_expiry: Option<Duration>,
+	) -> Option<Duration> {
+		entry
+			.expires_at
+			.map(|expires_at| expires_at.saturating_duration_since(updated_at))
+	}
+}
+
+impl fmt::Debug for MokaDriver {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("MokaDriver")
+			.field("format", &self.format)
+			.field("entries", &self.cache.entry_count())
+			.field("bytes", &self.cache.weighted_size())
+			.finish_non_exhaustive()
+	}
+}
+
+impl Driver for MokaDriver {
+	type Error = Error;
+	type Config = Config;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		let mut builder = Cache::builder()
+			.weigher(|key: &String, entry: &Entry| {
+				u32::try_from(key.len() + entry.data.len()).unwrap_or(u32::MAX)
+			})
+			.expire_after(EntryExpiry);
+
+		if let Some(max_capacity) = config.max_capacity {
+			builder = builder.max_capacity(max_capacity);
+		}
+		if let Some(time_to_idle) = config.time_to_idle {
+			builder = builder.time_to_idle(time_to_idle);
+		}
+
+		Ok(Self {
+			format: config.format,
+			cache: builder.build(),
+		})
+	}
+
+	fn format(&self) -> Option<Format> {
+		Some(self.format)
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		let Some(entry) = self.cache.get(key).await else {
+			return Ok(None);
+		};
+
+		Ok(Some(self.format.deserialize(&entry.data)?))
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		let Some(entry) = self.cache.get(key).await else {
+			return Ok(GetOutcome::Miss);
+		};
+
+		Ok(self.format.deserialize(&entry.data).map_or_else(
+			|_| GetOutcome::Corrupt(entry.data.to_vec()),
+			GetOutcome::Hit,
+		))
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		Ok(self.cache.contains_key(key))
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		let Some(entry) = self.cache.get(key).await else {
+			return Ok(None);
+		};
+
+		Ok(Some(entry.expires_at.map_or(Ttl::Forever, |expires_at| {
+			Ttl::In(expires_at.saturating_duration_since(Instant::now()))
+		})))
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		let prefix = prefix.unwrap_or_default();
+		let now = Instant::now();
+
+		Ok(self
+			.cache
+			.iter()
+			.filter(|(key, entry)| {
+				key.starts_with(prefix)
+					&& entry.expires_at.is_none_or(|expires_at| expires_at > now)
+			})
+			.map(|(key, _)| key.to_string())
+			.collect())
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		let entry = Entry {
+			data: self.format.serialize(value)?.into(),
+			expires_at: expiry.map(|expiry| Instant::now() + expiry),
+		};
+
+		self.cache.insert(key.to_string(), entry).await;
+
+		Ok(())
+	}
+
+	async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		expiry: Option<Duration>,
+	) -> Result<T, Self::Error> {
+		let data = self.format.serialize(&value)?.into();
+
+		let entry = self
+			.cache
+			.entry_by_ref(key)
+			.or_insert_with(async {
+				Entry {
+					data,
+					expires_at: expiry.map(|expiry| Instant::now() + expiry),
+				}
+			})
+			.await;
+
+		if entry.is_fresh() {
+			return Ok(value);
+		}
+
+		Ok(self.format.deserialize(&entry.into_value().data)?)
+	}
+
+	async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<Option<T>, Self::Error> {
+		let entry = Entry {
+			data: self.format.serialize(value)?.into(),
+			expires_at: expiry.map(|expiry| Instant::now() + expiry),
+		};
+
+		let mut previous = None;
+		self.cache
+			.entry_by_ref(key)
+			.and_compute_with(|existing| {
+				previous = existing.map(moka::Entry::into_value);
+
+				async move { Op::Put(entry) }
+			})
+			.await;
+
+		Ok(previous
+			.map(|previous| self.format.deserialize(&previous.data))
+			.transpose()?)
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		let data = self.format.serialize(value)?.into();
+
+		let entry = self
+			.cache
+			.entry_by_ref(key)
+			.or_insert_with(async {
+				Entry {
+					data,
+					expires_at: expiry.map(|expiry| Instant::now() + expiry),
+				}
+			})
+			.await;
+
+		Ok(entry.is_fresh())
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		let format = self.format;
+
+		// Concurrent computations on the same key run one after the other, so no increment is lost.
+		let result = self
+			.cache
+			.entry_by_ref(key)
+			.and_try_compute_with(|existing| async move {
+				let (value, expires_at) = match existing {
+					Some(existing) => {
+						let existing = existing.into_value();
+
+						(
+							format
+								.deserialize::<i64>(&existing.data)?
+								.checked_add(by)
+								.ok_or(Overflow)?,
+							existing.expires_at,
+						)
+					},
+					None => (by, expiry.map(|expiry| Instant::now() + expiry)),
+				};
+
+				Ok::<_, Error>(Op::Put(Entry {
+					expires_at,
+					data: format.serialize(&value)?.into(),
+				}))
+			})
+			.await?;
+
+		let entry = result
+			.into_entry()
+			.expect("the computation always stores a value");
+
+		Ok(format.deserialize(&entry.into_value().data)?)
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		let result = self
+			.cache
+			.entry_by_ref(key)
+			.and_compute_with(|existing| async move {
+				existing.map_or(Op::Nop, |existing| {
+					Op::Put(Entry {
+						expires_at: expiry.map(|expiry| Instant::now() + expiry),
+						..existing.into_value()
+					})
+				})
+			})
+			.await;
+
+		Ok(result.into_entry().is_some())
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		Ok(self.cache.remove(key).await.is_some())
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		let keys = self
+			.cache
+			.iter()
+			.filter(|(key, _)| key.starts_with(prefix))
+			.map(|(key, _)| key)
+			.collect::<Vec<_>>();
+
+		for key in keys {
+			self.cache.invalidate(key.as_str()).await;
+		}
+
+		Ok(())
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		self.cache.invalidate_all();
+		self.cache.run_pending_tasks().await;
+
+		Ok(())
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error(transparent)]
+	Serialization(#[from] format::Error),
+	#[error(transparent)]
+	Overflow(#[from] Overflow),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Cache;
+
+	#[tokio::test]
+	async fn test_moka_driver() {
+		let mut cache = Cache::<MokaDriver>::new(Config::default()).await.unwrap();
+
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+		assert!(!cache.has("foo").await.unwrap());
+
+		cache
+			.put("foo", &"bar", Duration::from_secs(10))
+			.await
+			.unwrap();
+
+		assert_eq!(
+			cache.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+		assert!(cache.has("foo").await.unwrap());
+
+		assert!(cache.forget("foo").await.unwrap());
+		assert!(!cache.forget("foo").await.unwrap());
+		assert!(!cache.has("foo").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_moka_expiry() {
+		let mut cache = Cache::<MokaDriver>::new(Config::default()).await.unwrap();
+
+		cache
+			.put("foo", &"bar", Duration::from_millis(50))
+			.await
+			.unwrap();
+		cache.forever("baz", "qux").await.unwrap();
+		assert!(matches!(cache.ttl("foo").await.unwrap(), Some(Ttl::In(_))));
+		assert_eq!(cache.ttl("baz").await.unwrap(), Some(Ttl::Forever));
+
+		tokio::time::sleep(Duration::from_millis(100)).await;
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+		assert!(cache.has("baz").await.unwrap());
+
+		assert!(cache.touch("baz", Duration::from_millis(50)).await.unwrap());
+		assert!(!cache.touch("foo", Duration::from_millis(50)).await.unwrap());
+
+		tokio::time::sleep(Duration::from_millis(100)).await;
+		assert!(!cache.has("baz").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_moka_increment() {
+		let mut cache = Cache::<MokaDriver>::new(Config::default()).await.unwrap();
+
+		let tasks = (0..10)
+			.map(|_| {
+				let mut cache = cache.clone();
+				tokio::spawn(async move { cache.increment("hits", 1).await.unwrap() })
+			})
+			.collect::<Vec<_>>();
+		for task in tasks {
+			task.await.unwrap();
+		}
+
+		assert_eq!(cache.decrement("hits", 3).await.unwrap(), 7);
+	}
+
+	#[tokio::test]
+	async fn test_moka_replace() {
+		let mut cache = Cache::<MokaDriver>::new(Config::default()).await.unwrap();
+
+		assert_eq!(
+			cache
+				.replace("foo", &"bar".to_string(), Duration::from_secs(10))
+				.await
+				.unwrap(),
+			None
+		);
+		assert_eq!(
+			cache
+				.replace("foo", &"baz".to_string(), Duration::from_secs(10))
+				.await
+				.unwrap(),
+			Some("bar".to_string())
+		);
+		assert_eq!(
+			cache.get::<String>("foo").await.unwrap(),
+			Some("baz".to_string())
+		);
+	}
+}
@@ -1,14 +1,15 @@
-use super::Driver;
+use super::{Driver, GetOutcome, Overflow, Ttl};
 use serde::{de::DeserializeOwned, Serialize};
-use std::{convert::Infallible, time::Duration};
+use std::time::Duration;
 
+#[derive(Debug, Clone, Copy)]
 #[allow(clippy::module_name_repetitions)]
 /// A driver that does nothing.
 pub struct NullDriver;
 
 impl Driver for NullDriver {
 	type Config = ();
-	type Error = Infallible;
+	type Error = Overflow;
 
 	async fn new((): Self::Config) -> Result<Self, Self::Error> {
 		Ok(Self)
@@ -18,10 +19,18 @@ impl Driver for NullDriver {
 		Ok(None)
 	}
 
+	async fn try_get<T: DeserializeOwned>(&self, _key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		Ok(GetOutcome::Miss)
+	}
+
 	async fn has(&self, _key: &str) -> Result<bool, Self::Error> {
 		Ok(false)
 	}
 
+	async fn ttl(&self, _key: &str) -> Result<Option<Ttl>, Self::Error> {
+		Ok(None)
+	}
+
 	async fn put<T: Serialize + Sync>(
 		&mut self,
 		_: &str,
@@ -31,10 +40,31 @@ impl Driver for NullDriver {
 		Ok(())
 	}
 
-	async fn forget(&mut self, _: &str) -> Result<(), Self::Error> {
+	async fn increment(
+		&mut self,
+		_: &str,
+		by: i64,
+		_: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		Ok(by)
+	}
+
+	async fn touch(&mut self, _: &str, _: Option<Duration>) -> Result<bool, Self::Error> {
+		Ok(false)
+	}
+
+	async fn forget(&mut self, _: &str) -> Result<bool, Self::Error> {
+		Ok(false)
+	}
+
+	async fn forget_prefix(&mut self, _: &str) -> Result<(), Self::Error> {
 		Ok(())
 	}
 
+	async fn keys(&self, _: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		Ok(Vec::new())
+	}
+
 	async fn flush(&mut self) -> Result<(), Self::Error> {
 		Ok(())
 	}
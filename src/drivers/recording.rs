@@ -0,0 +1,285 @@
+use super::{Driver, GetOutcome, Overflow, Ttl};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	sync::{Arc, Mutex, MutexGuard, PoisonError},
+	time::Duration,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+	/// The log calls are recorded to. Keep a clone of it to inspect the calls made through a [`Cache`](crate::Cache).
+	pub recorder: Recorder,
+}
+
+config_builder! {
+	recorder: Recorder,
+}
+
+/// A driver operation, as recorded by the [`RecordingDriver`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+	Get,
+	TryGet,
+	Has,
+	Ttl,
+	Keys,
+	Put,
+	GetOrPut,
+	Replace,
+	Add,
+	CompareAndSwap,
+	Increment,
+	Touch,
+	Forget,
+	ForgetPrefix,
+	ForgetIf,
+	PurgeExpired,
+	Flush,
+}
+
+/// A shared log of the calls made to a [`RecordingDriver`], as `(operation, key)` pairs.
+///
+/// Operations on many keys are recorded once per key, operations on a prefix record the prefix, and operations that
+/// don't take a key (like `flush`) record an empty one.
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+	calls: Arc<Mutex<Vec<(Op, String)>>>,
+}
+
+impl Recorder {
+	/// Every call recorded so far, in order.
+	#[must_use]
+	pub fn calls(&self) -> Vec<(Op, String)> {
+		self.log().clone()
+	}
+
+	/// Whether `op` was called with `key`.
+	#[must_use]
+	pub fn called(&self, op: Op, key: &str) -> bool {
+		self.log()
+			.iter()
+			.any(|(recorded, recorded_key)| *recorded == op && recorded_key == key)
+	}
+
+	/// Forget every recorded call.
+	pub fn clear(&self) {
+		self.log().clear();
+	}
+
+	fn record(&self, op: Op, key: &str) {
+		self.log().push((op, key.to_string()));
+	}
+
+	fn log(&self) -> MutexGuard<'_, Vec<(Op, String)>> {
+		self.calls.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+#[allow(clippy::module_name_repetitions)]
+/// A driver that stores nothing (like the [`NullDriver`](super::NullDriver)), but records every call made to it.
+///
+/// Meant for tests that need to assert which cache operations some code attempted, without a real backend.
+/// Clones share the same [`Recorder`].
+pub struct RecordingDriver {
+	recorder: Recorder,
+}
+
+impl RecordingDriver {
+	/// The log of calls made to this driver.
+	#[must_use]
+	pub const fn recorder(&self) -> &Recorder {
+		&self.recorder
+	}
+
+	/// Every call made to this driver so far, in order.
+	#[must_use]
+	pub fn calls(&self) -> Vec<(Op, String)> {
+		self.recorder.calls()
+	}
+}
+
+impl Driver for RecordingDriver {
+	type Config = Config;
+	type Error = Overflow;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		Ok(Self {
+			recorder: config.recorder,
+		})
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		self.recorder.record(Op::Get, key);
+
+		Ok(None)
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		self.recorder.record(Op::TryGet, key);
+
+		Ok(GetOutcome::Miss)
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		self.recorder.record(Op::Has, key);
+
+		Ok(false)
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		self.recorder.record(Op::Ttl, key);
+
+		Ok(None)
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		self.recorder.record(Op::Keys, prefix.unwrap_or_default());
+
+		Ok(Vec::new())
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		_: &T,
+		_: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		self.recorder.record(Op::Put, key);
+
+		Ok(())
+	}
+
+	async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		_: Option<Duration>,
+	) -> Result<T, Self::Error> {
+		self.recorder.record(Op::GetOrPut, key);
+
+		Ok(value)
+	}
+
+	async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		_: &T,
+		_: Option<Duration>,
+	) -> Result<Option<T>, Self::Error> {
+		self.recorder.record(Op::Replace, key);
+
+		Ok(None)
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		_: &T,
+		_: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		self.recorder.record(Op::Add, key);
+
+		Ok(true)
+	}
+
+	async fn compare_and_swap<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		_: &T,
+		_: &T,
+		_: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		self.recorder.record(Op::CompareAndSwap, key);
+
+		Ok(false)
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		_: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		self.recorder.record(Op::Increment, key);
+
+		Ok(by)
+	}
+
+	async fn touch(&mut self, key: &str, _: Option<Duration>) -> Result<bool, Self::Error> {
+		self.recorder.record(Op::Touch, key);
+
+		Ok(false)
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		self.recorder.record(Op::Forget, key);
+
+		Ok(false)
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		self.recorder.record(Op::ForgetPrefix, prefix);
+
+		Ok(())
+	}
+
+	async fn forget_if<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		_: &T,
+	) -> Result<bool, Self::Error> {
+		self.recorder.record(Op::ForgetIf, key);
+
+		Ok(false)
+	}
+
+	async fn purge_expired(&mut self) -> Result<usize, Self::Error> {
+		self.recorder.record(Op::PurgeExpired, "");
+
+		Ok(0)
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		self.recorder.record(Op::Flush, "");
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Cache;
+
+	#[tokio::test]
+	async fn test_recording_driver() {
+		let recorder = Recorder::default();
+		let mut cache = Cache::<RecordingDriver>::new(Config {
+			recorder: recorder.clone(),
+		})
+		.await
+		.unwrap();
+
+		cache
+			.put("foo", &"bar", Duration::from_secs(1))
+			.await
+			.unwrap();
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+		cache.forget_many(&["a", "b"]).await.unwrap();
+
+		assert!(recorder.called(Op::Put, "foo"));
+		assert_eq!(
+			recorder.calls(),
+			vec![
+				(Op::Put, "foo".to_string()),
+				(Op::Get, "foo".to_string()),
+				(Op::Forget, "a".to_string()),
+				(Op::Forget, "b".to_string()),
+			]
+		);
+
+		recorder.clear();
+		assert!(recorder.calls().is_empty());
+	}
+}
@@ -0,0 +1,653 @@
+use super::{
+	redis::{
+		escape_pattern, expiry_millis, redact_url, run_increment, Error, COMPARE_AND_SWAP_SCRIPT,
+		SCAN_COUNT,
+	},
+	schema, Driver, Format, GetOutcome, OnCorrupt, Ttl,
+};
+use futures_util::future::try_join_all;
+use redis::{
+	cluster::ClusterClient,
+	cluster_async::ClusterConnection,
+	cluster_routing::{Route, RoutingInfo, SingleNodeRoutingInfo, SlotAddr},
+	AsyncCommands, FromRedisValue, Value,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{collections::HashMap, fmt, time::Duration};
+
+/// Deletes an entry only if its stored value matches the expected one.
+const FORGET_IF_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+	return redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+pub struct Config {
+	/// A prefix prepended to every key. When set, `flush` only removes the keys under it instead of running `FLUSHDB`
+	/// on every primary, so the cluster can be shared.
+	pub prefix: String,
+	/// The URLs of the nodes to discover the cluster from. Only one needs to be reachable.
+	pub nodes: Vec<String>,
+	/// The format values are serialized with.
+	pub format: Format,
+	/// A version stored alongside every value. Values stored with a different version are treated as misses.
+	pub schema_version: Option<u32>,
+	/// What `get` does with entries that can't be deserialized.
+	pub on_corrupt: OnCorrupt,
+	/// The largest (serialized) value that can be stored, in bytes. Storing a larger one fails with
+	/// [`Error::ValueTooLarge`] instead.
+	pub max_value_bytes: Option<usize>,
+	/// Send reads to replicas as well as primaries, which spreads the load but may return stale values.
+	pub read_from_replicas: bool,
+}
+
+config_builder! {
+	prefix: String,
+	nodes: Vec<String>,
+	format: Format,
+	schema_version: Option<u32>,
+	on_corrupt: OnCorrupt,
+	max_value_bytes: Option<usize>,
+	read_from_replicas: bool,
+}
+
+impl fmt::Debug for Config {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let nodes = self
+			.nodes
+			.iter()
+			.map(|url| redact_url(url))
+			.collect::<Vec<_>>();
+
+		f.debug_struct("Config")
+			.field("prefix", &self.prefix)
+			.field("nodes", &nodes)
+			.field("format", &self.format)
+			.field("schema_version", &self.schema_version)
+			.field("on_corrupt", &self.on_corrupt)
+			.field("max_value_bytes", &self.max_value_bytes)
+			.field("read_from_replicas", &self.read_from_replicas)
+			.finish()
+	}
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			prefix: String::new(),
+			format: Format::default(),
+			schema_version: None,
+			on_corrupt: OnCorrupt::default(),
+			max_value_bytes: None,
+			read_from_replicas: false,
+			nodes: vec!["redis://localhost".to_string()],
+		}
+	}
+}
+
+#[derive(Clone)]
+#[allow(clippy::module_name_repetitions)]
+/// A driver that uses a Redis Cluster.
+///
+/// Keys are routed to the node that owns their slot, and commands on many keys are split across nodes. Unlike the
+/// [`RedisDriver`](super::RedisDriver), entry metadata isn't tracked, and listing or removing keys by prefix walks
+/// every primary with `SCAN`.
+pub struct RedisClusterDriver {
+	prefix: String,
+	format: Format,
+	schema_version: Option<u32>,
+	on_corrupt: OnCorrupt,
+	max_value_bytes: Option<usize>,
+	conn: ClusterConnection,
+}
+
+impl fmt::Debug for RedisClusterDriver {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("RedisClusterDriver")
+			.field("prefix", &self.prefix)
+			.field("format", &self.format)
+			.field("schema_version", &self.schema_version)
+			.field("on_corrupt", &self.on_corrupt)
+			.field("max_value_bytes", &self.max_value_bytes)
+			.finish_non_exhaustive()
+	}
+}
+
+impl RedisClusterDriver {
+	fn key(&self, key: &str) -> String {
+		format!("{}{key}", self.prefix)
+	}
+
+	/// Serialize a value to be stored, checking it against `max_value_bytes`.
+	fn serialize<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, Error> {
+		let data = self.encode(value)?;
+
+		match self.max_value_bytes {
+			Some(limit) if data.len() > limit => Err(Error::ValueTooLarge {
+				size: data.len(),
+				limit,
+			}),
+			_ => Ok(data),
+		}
+	}
+
+	/// Serialize a value the way it's stored, to compare it against the stored one.
+	fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, Error> {
+		Ok(schema::wrap(
+			self.schema_version,
+			self.format.serialize(value)?,
+		))
+	}
+
+	fn deserialize<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T, Error> {
+		Ok(self.format.deserialize(data)?)
+	}
+
+	/// Deserialize the value read from `key`, evicting it instead if it's corrupt and `on_corrupt` says to.
+	async fn decode<T: DeserializeOwned>(
+		&self,
+		key: &str,
+		data: &[u8],
+	) -> Result<Option<T>, Error> {
+		let error = match self.deserialize(data) {
+			Ok(value) => return Ok(Some(value)),
+			Err(error) => error,
+		};
+
+		if self.on_corrupt != OnCorrupt::Evict {
+			return Err(error);
+		}
+
+		self.conn.clone().del::<_, ()>(self.key(key)).await?;
+
+		Ok(None)
+	}
+
+	/// Read the stored bytes for an entry, without its schema version.
+	async fn read(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+		let data: Option<Vec<u8>> = self.conn.clone().get(self.key(key)).await?;
+
+		Ok(data
+			.as_deref()
+			.and_then(|data| schema::unwrap(self.schema_version, data))
+			.map(<[u8]>::to_vec))
+	}
+
+	/// Every (prefixed) key starting with `prefix`, across every primary.
+	///
+	/// `SCAN` walks each primary's keys a page at a time, unlike `KEYS`, which blocks it until it's gone through all of
+	/// them.
+	async fn matching_keys(&self, prefix: &str) -> Result<Vec<String>, Error> {
+		let pattern = format!("{}*", escape_pattern(&self.key(prefix)));
+
+		let mut keys = try_join_all(
+			self.primary_slots()
+				.await?
+				.into_iter()
+				.map(|slot| self.scan_primary(slot, &pattern)),
+		)
+		.await?
+		.concat();
+
+		// Each primary replies with its own keys (and `SCAN` can return a key more than once if it's written to while
+		// the scan is running), so sort them into a stable order.
+		keys.sort_unstable();
+		keys.dedup();
+
+		Ok(keys)
+	}
+
+	/// A slot owned by each primary, to route the commands that have to run on every one of them.
+	async fn primary_slots(&self) -> Result<Vec<u16>, Error> {
+		let ranges = self
+			.conn
+			.clone()
+			.route_command(
+				redis::cmd("CLUSTER").arg("SLOTS"),
+				RoutingInfo::SingleNode(SingleNodeRoutingInfo::Random),
+			)
+			.await?;
+
+		// Each range is `[start, end, [host, port, ...], replicas...]`, and a primary can own several of them.
+		let mut primaries = HashMap::new();
+		for range in Vec::<Vec<Value>>::from_redis_value(&ranges)? {
+			let (Some(start), Some(primary)) = (range.first(), range.get(2)) else {
+				continue;
+			};
+			let primary = Vec::<Value>::from_redis_value(primary)?;
+			let (Some(host), Some(port)) = (primary.first(), primary.get(1)) else {
+				continue;
+			};
+
+			primaries
+				.entry((
+					String::from_redis_value(host)?,
+					u16::from_redis_value(port)?,
+				))
+				.or_insert(u16::from_redis_value(start)?);
+		}
+
+		Ok(primaries.into_values().collect())
+	}
+
+	/// Every key matching `pattern` on the primary that owns `slot`.
+	async fn scan_primary(&self, slot: u16, pattern: &str) -> Result<Vec<String>, Error> {
+		let mut conn = self.conn.clone();
+		let routing = RoutingInfo::SingleNode(SingleNodeRoutingInfo::SpecificNode(Route::new(
+			slot,
+			SlotAddr::Master,
+		)));
+
+		let mut keys = Vec::new();
+		let mut cursor = 0_u64;
+		loop {
+			let page = conn
+				.route_command(
+					redis::cmd("SCAN")
+						.arg(cursor)
+						.arg("MATCH")
+						.arg(pattern)
+						.arg("COUNT")
+						.arg(SCAN_COUNT),
+					routing.clone(),
+				)
+				.await?;
+			let (next, page) = <(u64, Vec<String>)>::from_redis_value(&page)?;
+
+			keys.extend(page);
+			if next == 0 {
+				return Ok(keys);
+			}
+			cursor = next;
+		}
+	}
+
+	/// Build a `SET` command for an entry, with the given extra arguments (like `NX` or `GET`).
+	fn set_cmd(
+		&self,
+		key: &str,
+		data: Vec<u8>,
+		args: &[&str],
+		expiry: Option<Duration>,
+	) -> redis::Cmd {
+		let mut cmd = redis::cmd("SET");
+		cmd.arg(self.key(key)).arg(data).arg(args);
+
+		if let Some(expiry) = expiry {
+			cmd.arg("PX")
+				.arg(u64::try_from(expiry.as_millis()).unwrap_or(u64::MAX));
+		}
+
+		cmd
+	}
+}
+
+impl Driver for RedisClusterDriver {
+	type Error = Error;
+	type Config = Config;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		if config.nodes.is_empty() {
+			return Err(Error::NoNodes);
+		}
+
+		let mut builder = ClusterClient::builder(config.nodes);
+		if config.read_from_replicas {
+			builder = builder.read_from_replicas();
+		}
+
+		Ok(Self {
+			conn: builder.build()?.get_async_connection().await?,
+			prefix: config.prefix,
+			format: config.format,
+			schema_version: config.schema_version,
+			on_corrupt: config.on_corrupt,
+			max_value_bytes: config.max_value_bytes,
+		})
+	}
+
+	fn prefix(&self) -> &str {
+		&self.prefix
+	}
+
+	fn is_unavailable(error: &Self::Error) -> bool {
+		error.is_unavailable()
+	}
+
+	fn format(&self) -> Option<Format> {
+		Some(self.format)
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		let Some(data) = self.read(key).await? else {
+			return Ok(None);
+		};
+
+		self.decode(key, &data).await
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		let Some(data) = self.read(key).await? else {
+			return Ok(GetOutcome::Miss);
+		};
+
+		Ok(self
+			.deserialize(&data)
+			.map_or(GetOutcome::Corrupt(data), GetOutcome::Hit))
+	}
+
+	async fn get_many<T: DeserializeOwned + Send>(
+		&self,
+		keys: &[&str],
+	) -> Result<Vec<Option<T>>, Self::Error> {
+		if keys.is_empty() {
+			return Ok(Vec::new());
+		}
+
+		// The cluster client splits `MGET` into one command per slot, and puts the values back in order.
+		let data: Vec<Option<Vec<u8>>> = redis::cmd("MGET")
+			.arg(keys.iter().map(|key| self.key(key)).collect::<Vec<_>>())
+			.query_async(&mut self.conn.clone())
+			.await?;
+
+		let mut values = Vec::with_capacity(keys.len());
+		for (key, data) in keys.iter().zip(data) {
+			values.push(
+				match data
+					.as_deref()
+					.and_then(|data| schema::unwrap(self.schema_version, data))
+				{
+					None => None,
+					Some(data) => self.decode(key, data).await?,
+				},
+			);
+		}
+
+		Ok(values)
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		if self.schema_version.is_none() {
+			return Ok(self.conn.clone().exists(self.key(key)).await?);
+		}
+
+		// Values stored under another schema version are misses for `get`, so only the version is read to check it.
+		let version: Vec<u8> = self.conn.clone().getrange(self.key(key), 0, 3).await?;
+
+		Ok(schema::unwrap(self.schema_version, &version).is_some())
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		// `PTTL` returns -2 for missing keys, and -1 for keys without an expiry.
+		let ttl: i64 = self.conn.clone().pttl(self.key(key)).await?;
+
+		Ok(match ttl {
+			-2 => None,
+			-1 => Some(Ttl::Forever),
+			millis => Some(Ttl::In(Duration::from_millis(
+				u64::try_from(millis).unwrap_or_default(),
+			))),
+		})
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		Ok(self
+			.matching_keys(prefix.unwrap_or_default())
+			.await?
+			.into_iter()
+			.filter_map(|key| key.strip_prefix(&self.prefix).map(str::to_string))
+			.collect())
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		self.set_cmd(key, self.serialize(value)?, &[], expiry)
+			.query_async::<_, ()>(&mut self.conn)
+			.await?;
+
+		Ok(())
+	}
+
+	async fn put_many<T: Serialize + Sync>(
+		&mut self,
+		entries: &[(&str, &T)],
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		// The keys can live on different nodes, so each one is stored with its own command, sent concurrently.
+		let commands = entries
+			.iter()
+			.map(|(key, value)| Ok(self.set_cmd(key, self.serialize(value)?, &[], expiry)))
+			.collect::<Result<Vec<_>, Error>>()?;
+
+		try_join_all(commands.iter().map(|cmd| {
+			let mut conn = self.conn.clone();
+
+			async move { cmd.query_async::<_, ()>(&mut conn).await }
+		}))
+		.await?;
+
+		Ok(())
+	}
+
+	async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		expiry: Option<Duration>,
+	) -> Result<T, Self::Error> {
+		// `NX` together with `GET` (Redis 7+) only stores the value if the key is missing, returning the previous one.
+		let Some(previous) = self
+			.set_cmd(key, self.serialize(&value)?, &["NX", "GET"], expiry)
+			.query_async::<_, Option<Vec<u8>>>(&mut self.conn)
+			.await?
+		else {
+			return Ok(value);
+		};
+
+		let Some(data) = schema::unwrap(self.schema_version, &previous) else {
+			// The existing value was stored under another schema version, so it counts as missing.
+			self.put(key, &value, expiry).await?;
+
+			return Ok(value);
+		};
+
+		self.deserialize(data)
+	}
+
+	async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<Option<T>, Self::Error> {
+		let previous = self
+			.set_cmd(key, self.serialize(value)?, &["GET"], expiry)
+			.query_async::<_, Option<Vec<u8>>>(&mut self.conn)
+			.await?;
+
+		previous
+			.as_deref()
+			.and_then(|previous| schema::unwrap(self.schema_version, previous))
+			.map(|data| self.deserialize(data))
+			.transpose()
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		// `SET NX` replies with `OK` if it stored the value, and nil if the key already existed.
+		Ok(self
+			.set_cmd(key, self.serialize(value)?, &["NX"], expiry)
+			.query_async::<_, Option<String>>(&mut self.conn)
+			.await?
+			.is_some())
+	}
+
+	async fn compare_and_swap<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+		new: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		let swapped: u64 = redis::Script::new(COMPARE_AND_SWAP_SCRIPT)
+			.key(self.key(key))
+			.arg(self.encode(expected)?)
+			.arg(self.serialize(new)?)
+			.arg(expiry_millis(expiry))
+			.invoke_async(&mut self.conn)
+			.await?;
+
+		Ok(swapped != 0)
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		let key = self.key(key);
+
+		// Counters are stored serialized like any other value (so `get` can read them) rather than as Redis integers,
+		// so they're decoded, added to and written back by a script, in a single atomic step.
+		run_increment(
+			&mut self.conn,
+			&key,
+			self.format,
+			self.schema_version,
+			by,
+			expiry,
+		)
+		.await
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		let key = self.key(key);
+
+		if let Some(expiry) = expiry {
+			return Ok(self
+				.conn
+				.pexpire(key, i64::try_from(expiry.as_millis()).unwrap_or(i64::MAX))
+				.await?);
+		}
+
+		// `PERSIST` returns false for keys without an expiry, so check that the key exists instead.
+		let (exists,): (bool,) = redis::pipe()
+			.atomic()
+			.exists(&key)
+			.persist(&key)
+			.ignore()
+			.query_async(&mut self.conn)
+			.await?;
+
+		Ok(exists)
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		let deleted: u64 = self.conn.del(self.key(key)).await?;
+
+		Ok(deleted != 0)
+	}
+
+	async fn forget_many(&mut self, keys: &[&str]) -> Result<(), Self::Error> {
+		if keys.is_empty() {
+			return Ok(());
+		}
+
+		// The cluster client splits `DEL` into one command per slot.
+		let keys = keys.iter().map(|key| self.key(key)).collect::<Vec<_>>();
+		self.conn.del::<_, ()>(keys).await?;
+
+		Ok(())
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		let keys = self.matching_keys(prefix).await?;
+		if !keys.is_empty() {
+			self.conn.del::<_, ()>(keys).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn forget_if<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+	) -> Result<bool, Self::Error> {
+		let deleted: u64 = redis::Script::new(FORGET_IF_SCRIPT)
+			.key(self.key(key))
+			.arg(self.encode(expected)?)
+			.invoke_async(&mut self.conn)
+			.await?;
+
+		Ok(deleted != 0)
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		// With a prefix, the cluster may be shared with other caches (or other data), so only this cache's keys go.
+		if !self.prefix.is_empty() {
+			return self.forget_prefix("").await;
+		}
+
+		#[cfg(feature = "tracing")]
+		tracing::warn!(
+			"flushing a Redis cluster cache without a prefix, removing every key in the cluster"
+		);
+
+		// The cluster client sends `FLUSHDB` to every primary.
+		redis::cmd("FLUSHDB")
+			.query_async::<_, ()>(&mut self.conn)
+			.await?;
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::env;
+
+	use super::*;
+	use crate::Cache;
+
+	#[tokio::test]
+	async fn test_redis_cluster_driver() {
+		let mut cache = Cache::<RedisClusterDriver>::new(Config {
+			nodes: env::var("REDIS_CLUSTER_URLS")
+				.expect("REDIS_CLUSTER_URLS not set")
+				.split(',')
+				.map(str::to_string)
+				.collect(),
+			..Default::default()
+		})
+		.await
+		.unwrap();
+
+		cache.put("foo", &"bar", None).await.unwrap();
+		cache.put("baz", &"qux", None).await.unwrap();
+		assert_eq!(
+			cache
+				.get_many::<String>(&["foo", "baz", "missing"])
+				.await
+				.unwrap(),
+			vec![Some("bar".to_string()), Some("qux".to_string()), None]
+		);
+
+		cache.forget_many(&["foo", "baz"]).await.unwrap();
+		assert!(!cache.has("foo").await.unwrap());
+
+		cache.put("user:1:name", &"bar", None).await.unwrap();
+		cache.flush().await.unwrap();
+		assert!(!cache.has("user:1:name").await.unwrap());
+	}
+}
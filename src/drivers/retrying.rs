@@ -0,0 +1,481 @@
+#[cfg(feature = "format")]
+use super::Format;
+use super::{Driver, EntryMeta, GetOutcome, PoolStatus, Ttl};
+use crate::{JitterSource, RandomJitter};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	fmt,
+	time::{Duration, SystemTime},
+};
+
+pub struct Config<D: Driver> {
+	/// The config for the driver whose operations are retried.
+	pub inner: D::Config,
+	/// How many times an operation is attempted in total, including the first. With one, nothing is retried.
+	pub max_attempts: u32,
+	/// How long to wait before the first retry. Every retry after it waits twice as long as the one before.
+	pub base_delay: Duration,
+	/// The longest to wait before a retry, however many there were before it.
+	pub max_delay: Duration,
+	/// Which errors are worth retrying. Defaults to the ones the inner driver reports as its backend being unavailable
+	/// (see [`Driver::is_unavailable`]), so errors like a value that can't be deserialized are returned right away.
+	pub should_retry: fn(&D::Error) -> bool,
+}
+
+config_builder! {
+	<D: Driver>
+	inner: D::Config,
+	max_attempts: u32,
+	base_delay: Duration,
+	max_delay: Duration,
+}
+
+impl<D: Driver> ConfigBuilder<D> {
+	/// Set [`Config::should_retry`].
+	pub fn should_retry(mut self, should_retry: fn(&D::Error) -> bool) -> Self {
+		self.config.should_retry = should_retry;
+
+		self
+	}
+}
+
+impl<D: Driver> fmt::Debug for Config<D>
+where
+	D::Config: fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Config")
+			.field("inner", &self.inner)
+			.field("max_attempts", &self.max_attempts)
+			.field("base_delay", &self.base_delay)
+			.field("max_delay", &self.max_delay)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<D: Driver> Default for Config<D>
+where
+	D::Config: Default,
+{
+	fn default() -> Self {
+		Self {
+			inner: D::Config::default(),
+			max_attempts: 3,
+			base_delay: Duration::from_millis(50),
+			max_delay: Duration::from_secs(2),
+			should_retry: D::is_unavailable,
+		}
+	}
+}
+
+/// Run an operation on the inner driver, waiting and running it again for as long as it fails with an error that
+/// should be retried.
+macro_rules! retry {
+	($self:ident, $operation:expr) => {{
+		let mut attempt = 0;
+
+		loop {
+			match $operation.await {
+				Err(error) if $self.can_retry(&error, attempt) => {},
+				result => break result,
+			}
+
+			tokio::time::sleep($self.backoff(attempt)).await;
+			attempt += 1;
+		}
+	}};
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+/// A driver that retries another driver's operations when they fail with a transient error, like a network blip.
+///
+/// After an operation fails with an error `should_retry` accepts, it's run again after a delay that doubles with each
+/// retry (starting at `base_delay`, and capped at `max_delay`), until it succeeds, fails with any other error, or has
+/// been attempted `max_attempts` times, in which case its last error is returned. Each delay is randomized between
+/// half and all of it, so callers that failed together don't all retry at the same time.
+///
+/// [`increment`](Driver::increment) is never retried, since an attempt that timed out may still have been applied.
+/// Retrying [`add`](Driver::add) or [`compare_and_swap`](Driver::compare_and_swap) after such a timeout can report
+/// `false` for a write that happened.
+pub struct RetryingDriver<D: Driver> {
+	inner: D,
+	max_attempts: u32,
+	base_delay: Duration,
+	max_delay: Duration,
+	should_retry: fn(&D::Error) -> bool,
+}
+
+impl<D: Driver> RetryingDriver<D> {
+	/// The driver whose operations are retried.
+	pub const fn inner(&self) -> &D {
+		&self.inner
+	}
+
+	/// Whether an operation that failed on its `attempt`th retry (counting the first attempt as zero) should run again.
+	fn can_retry(&self, error: &D::Error, attempt: u32) -> bool {
+		attempt + 1 < self.max_attempts && (self.should_retry)(error)
+	}
+
+	/// How long to wait before retrying an operation that failed on its `attempt`th retry.
+	fn backoff(&self, attempt: u32) -> Duration {
+		let delay = self
+			.base_delay
+			.saturating_mul(2_u32.saturating_pow(attempt))
+			.min(self.max_delay);
+
+		delay / 2 + RandomJitter.jitter(delay / 2)
+	}
+}
+
+impl<D: Driver> Driver for RetryingDriver<D> {
+	type Error = D::Error;
+	type Config = Config<D>;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		Ok(Self {
+			inner: D::new(config.inner).await?,
+			max_attempts: config.max_attempts,
+			base_delay: config.base_delay,
+			max_delay: config.max_delay,
+			should_retry: config.should_retry,
+		})
+	}
+
+	fn prefix(&self) -> &str {
+		self.inner.prefix()
+	}
+
+	fn pool_status(&self) -> Option<PoolStatus> {
+		self.inner.pool_status()
+	}
+
+	fn is_unavailable(error: &Self::Error) -> bool {
+		D::is_unavailable(error)
+	}
+
+	#[cfg(feature = "format")]
+	fn format(&self) -> Option<Format> {
+		self.inner.format()
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		retry!(self, self.inner.get(key))
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		retry!(self, self.inner.try_get(key))
+	}
+
+	async fn get_many<T: DeserializeOwned + Send>(
+		&self,
+		keys: &[&str],
+	) -> Result<Vec<Option<T>>, Self::Error> {
+		retry!(self, self.inner.get_many(keys))
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		retry!(self, self.inner.has(key))
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		retry!(self, self.inner.ttl(key))
+	}
+
+	async fn metadata(&self, key: &str) -> Result<Option<EntryMeta>, Self::Error> {
+		retry!(self, self.inner.metadata(key))
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		retry!(self, self.inner.keys(prefix))
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		retry!(self, self.inner.put(key, value, expiry))
+	}
+
+	async fn put_until<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		deadline: SystemTime,
+	) -> Result<(), Self::Error> {
+		retry!(self, self.inner.put_until(key, value, deadline))
+	}
+
+	async fn put_many<T: Serialize + Sync>(
+		&mut self,
+		entries: &[(&str, &T)],
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		retry!(self, self.inner.put_many(entries, expiry))
+	}
+
+	async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		expiry: Option<Duration>,
+	) -> Result<T, Self::Error> {
+		// The inner driver's `get_or_put` takes the value, so it couldn't be passed to it again on a retry. Adding it
+		// instead keeps it around, and only one of several concurrent callers still stores theirs.
+		if retry!(self, self.inner.add(key, &value, expiry))? {
+			return Ok(value);
+		}
+
+		Ok(retry!(self, self.inner.get(key))?.unwrap_or(value))
+	}
+
+	async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<Option<T>, Self::Error> {
+		retry!(self, self.inner.replace(key, value, expiry))
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		retry!(self, self.inner.add(key, value, expiry))
+	}
+
+	async fn compare_and_swap<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+		new: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		retry!(
+			self,
+			self.inner.compare_and_swap(key, expected, new, expiry)
+		)
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		self.inner.increment(key, by, expiry).await
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		retry!(self, self.inner.touch(key, expiry))
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		retry!(self, self.inner.forget(key))
+	}
+
+	async fn forget_many(&mut self, keys: &[&str]) -> Result<(), Self::Error> {
+		retry!(self, self.inner.forget_many(keys))
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		retry!(self, self.inner.forget_prefix(prefix))
+	}
+
+	async fn forget_if<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+	) -> Result<bool, Self::Error> {
+		retry!(self, self.inner.forget_if(key, expected))
+	}
+
+	async fn purge_expired(&mut self) -> Result<usize, Self::Error> {
+		retry!(self, self.inner.purge_expired())
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		retry!(self, self.inner.flush())
+	}
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+	use super::*;
+	use crate::{
+		drivers::{memory, MemoryDriver, Overflow},
+		Cache,
+	};
+	use std::sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	};
+
+	#[derive(Debug, thiserror::Error)]
+	enum FlakyError {
+		#[error("the backend blipped")]
+		Blip,
+		#[error(transparent)]
+		Memory(#[from] memory::Error),
+		#[error(transparent)]
+		Overflow(#[from] Overflow),
+	}
+
+	/// A memory driver whose next `failures` operations fail with [`FlakyError::Blip`].
+	#[derive(Debug, Clone)]
+	struct FlakyDriver {
+		inner: MemoryDriver,
+		failures: Arc<AtomicUsize>,
+		attempts: Arc<AtomicUsize>,
+	}
+
+	impl FlakyDriver {
+		fn attempt(&self) -> Result<(), FlakyError> {
+			self.attempts.fetch_add(1, Ordering::SeqCst);
+
+			self.failures
+				.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |failures| {
+					failures.checked_sub(1)
+				})
+				.map_or(Ok(()), |_| Err(FlakyError::Blip))
+		}
+	}
+
+	impl Driver for FlakyDriver {
+		type Error = FlakyError;
+		type Config = memory::Config;
+
+		async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+			Ok(Self {
+				inner: MemoryDriver::new(config).await?,
+				failures: Arc::default(),
+				attempts: Arc::default(),
+			})
+		}
+
+		fn is_unavailable(error: &Self::Error) -> bool {
+			matches!(error, FlakyError::Blip)
+		}
+
+		async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+			self.attempt()?;
+			Ok(self.inner.get(key).await?)
+		}
+
+		async fn try_get<T: DeserializeOwned>(
+			&self,
+			key: &str,
+		) -> Result<GetOutcome<T>, Self::Error> {
+			self.attempt()?;
+			Ok(self.inner.try_get(key).await?)
+		}
+
+		async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+			self.attempt()?;
+			Ok(self.inner.has(key).await?)
+		}
+
+		async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+			self.attempt()?;
+			Ok(self.inner.ttl(key).await?)
+		}
+
+		async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+			self.attempt()?;
+			Ok(self.inner.keys(prefix).await?)
+		}
+
+		async fn put<T: Serialize + Sync>(
+			&mut self,
+			key: &str,
+			value: &T,
+			expiry: Option<Duration>,
+		) -> Result<(), Self::Error> {
+			self.attempt()?;
+			Ok(self.inner.put(key, value, expiry).await?)
+		}
+
+		async fn increment(
+			&mut self,
+			key: &str,
+			by: i64,
+			expiry: Option<Duration>,
+		) -> Result<i64, Self::Error> {
+			self.attempt()?;
+			Ok(self.inner.increment(key, by, expiry).await?)
+		}
+
+		async fn touch(
+			&mut self,
+			key: &str,
+			expiry: Option<Duration>,
+		) -> Result<bool, Self::Error> {
+			self.attempt()?;
+			Ok(self.inner.touch(key, expiry).await?)
+		}
+
+		async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+			self.attempt()?;
+			Ok(self.inner.forget(key).await?)
+		}
+
+		async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+			self.attempt()?;
+			Ok(self.inner.forget_prefix(prefix).await?)
+		}
+
+		async fn flush(&mut self) -> Result<(), Self::Error> {
+			self.attempt()?;
+			Ok(self.inner.flush().await?)
+		}
+	}
+
+	#[tokio::test]
+	async fn test_retries_transient_errors() {
+		let mut cache = Cache::<RetryingDriver<FlakyDriver>>::new(
+			Config::builder()
+				.base_delay(Duration::from_millis(1))
+				.build(),
+		)
+		.await
+		.unwrap();
+		let flaky = cache.driver.inner().clone();
+
+		// Fails twice, then succeeds on the last attempt.
+		flaky.failures.store(2, Ordering::SeqCst);
+		cache.put("foo", &"bar", None).await.unwrap();
+		assert_eq!(flaky.attempts.swap(0, Ordering::SeqCst), 3);
+
+		flaky.failures.store(1, Ordering::SeqCst);
+		assert_eq!(
+			cache.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+		assert_eq!(flaky.attempts.swap(0, Ordering::SeqCst), 2);
+
+		// Gives up once every attempt failed.
+		flaky.failures.store(3, Ordering::SeqCst);
+		assert!(matches!(
+			cache.get::<String>("foo").await,
+			Err(FlakyError::Blip)
+		));
+		assert_eq!(flaky.attempts.swap(0, Ordering::SeqCst), 3);
+
+		// Errors that aren't transient are returned right away.
+		assert!(matches!(
+			cache.get::<u64>("foo").await,
+			Err(FlakyError::Memory(_))
+		));
+		assert_eq!(flaky.attempts.swap(0, Ordering::SeqCst), 1);
+
+		// So are increments, which may have been applied before failing.
+		flaky.failures.store(1, Ordering::SeqCst);
+		assert!(cache.increment("count", 1).await.is_err());
+		assert_eq!(flaky.attempts.swap(0, Ordering::SeqCst), 1);
+	}
+}
@@ -0,0 +1,694 @@
+use std::{
+	collections::HashMap,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(feature = "stream")]
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use aws_sdk_s3::{
+	error::BuildError,
+	operation::{
+		get_object::{GetObjectError, GetObjectOutput},
+		head_object::HeadObjectError,
+	},
+	primitives::{ByteStream, ByteStreamError},
+	types::{Delete, ObjectIdentifier},
+};
+use aws_smithy_runtime_api::client::result::SdkError;
+use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "stream")]
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+#[cfg(feature = "stream")]
+use super::BlobReader;
+use super::{format, is_aws_unavailable, Driver, Format, GetOutcome, Overflow, Ttl};
+#[cfg(feature = "stream")]
+use crate::StreamError;
+
+/// The object metadata entry holding when an entry expires, in milliseconds since the Unix epoch.
+const EXPIRES_AT: &str = "expires-at";
+
+/// How much of a streamed blob is uploaded at once. Every part of a multipart upload but the last has to be at least
+/// 5 MiB.
+#[cfg(feature = "stream")]
+const PART_SIZE: usize = 8 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+	pub bucket: String,
+	pub prefix: String,
+	/// The format values are serialized with before being uploaded.
+	pub format: Format,
+	pub aws_config: aws_types::SdkConfig,
+}
+
+config_builder! {
+	bucket: String,
+	prefix: String,
+	format: Format,
+	aws_config: aws_types::SdkConfig,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			bucket: "cache".to_string(),
+			prefix: String::new(),
+			format: Format::default(),
+			aws_config: aws_types::SdkConfig::builder().build(),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+/// A driver that stores each entry as an object in an S3 bucket, for large values that are read infrequently.
+///
+/// Expiries are kept in each object's metadata, and expired objects are treated as missing when they're read. S3 won't
+/// delete them on its own, so set up a lifecycle rule on the bucket (or prefix) to clean them up eventually. Until
+/// then, they're still listed by [`keys`](Driver::keys).
+///
+/// S3 has no atomic operations, so [`increment`](Driver::increment) and [`touch`](Driver::touch) download the value
+/// and upload it again.
+pub struct S3Driver {
+	bucket: String,
+	prefix: String,
+	format: Format,
+	client: aws_sdk_s3::Client,
+}
+
+/// A downloaded object.
+struct Object {
+	data: Vec<u8>,
+	expires_at: Option<SystemTime>,
+}
+
+/// When an object expires, according to its metadata.
+fn expires_at(metadata: Option<&HashMap<String, String>>) -> Option<SystemTime> {
+	let millis = metadata?.get(EXPIRES_AT)?.parse::<u64>().ok()?;
+
+	Some(UNIX_EPOCH + Duration::from_millis(millis))
+}
+
+fn is_expired(expires_at: Option<SystemTime>) -> bool {
+	expires_at.is_some_and(|expires_at| expires_at <= SystemTime::now())
+}
+
+/// The metadata recording when an object expires, if it does.
+fn expiry_metadata(expires_at: Option<SystemTime>) -> Option<HashMap<String, String>> {
+	let millis = expires_at?.duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+	Some(HashMap::from([(
+		EXPIRES_AT.to_string(),
+		millis.to_string(),
+	)]))
+}
+
+/// Read the next part of a streamed blob, which is only shorter than [`PART_SIZE`] if it's the last one.
+#[cfg(feature = "stream")]
+async fn read_part(reader: &mut (impl AsyncRead + Unpin)) -> std::io::Result<Vec<u8>> {
+	let mut part = Vec::with_capacity(PART_SIZE);
+	reader.take(PART_SIZE as u64).read_to_end(&mut part).await?;
+
+	Ok(part)
+}
+
+impl S3Driver {
+	fn key(&self, key: &str) -> String {
+		format!("{}{key}", self.prefix)
+	}
+
+	/// Start downloading an object, if it exists and hasn't expired.
+	async fn fetch(&self, key: &str) -> Result<Option<GetObjectOutput>, Error> {
+		let response = match self
+			.client
+			.get_object()
+			.bucket(&self.bucket)
+			.key(self.key(key))
+			.send()
+			.await
+		{
+			Ok(response) => response,
+			Err(SdkError::ServiceError(error)) if error.err().is_no_such_key() => {
+				return Ok(None);
+			},
+			Err(error) => return Err(error.into()),
+		};
+
+		if is_expired(expires_at(response.metadata())) {
+			return Ok(None);
+		}
+
+		Ok(Some(response))
+	}
+
+	/// Download an object, if it exists and hasn't expired.
+	async fn download(&self, key: &str) -> Result<Option<Object>, Error> {
+		let Some(response) = self.fetch(key).await? else {
+			return Ok(None);
+		};
+
+		Ok(Some(Object {
+			expires_at: expires_at(response.metadata()),
+			data: response.body.collect().await?.into_bytes().to_vec(),
+		}))
+	}
+
+	/// Whether an object exists (even if it has expired), and when it expires.
+	async fn head(&self, key: &str) -> Result<Option<Option<SystemTime>>, Error> {
+		match self
+			.client
+			.head_object()
+			.bucket(&self.bucket)
+			.key(self.key(key))
+			.send()
+			.await
+		{
+			Ok(response) => Ok(Some(expires_at(response.metadata()))),
+			Err(SdkError::ServiceError(error)) if error.err().is_not_found() => Ok(None),
+			Err(error) => Err(error.into()),
+		}
+	}
+
+	async fn upload(
+		&self,
+		key: &str,
+		data: Vec<u8>,
+		expires_at: Option<SystemTime>,
+	) -> Result<(), Error> {
+		self.client
+			.put_object()
+			.bucket(&self.bucket)
+			.key(self.key(key))
+			.body(ByteStream::from(data))
+			.set_metadata(expiry_metadata(expires_at))
+			.send()
+			.await?;
+
+		Ok(())
+	}
+
+	/// Upload a streamed blob with a multipart upload, starting with its (already read) first part.
+	#[cfg(feature = "stream")]
+	async fn upload_parts(
+		&self,
+		key: &str,
+		mut part: Vec<u8>,
+		reader: &mut (impl AsyncRead + Unpin),
+		expires_at: Option<SystemTime>,
+	) -> Result<(), StreamError<Error>> {
+		let failed = |error: Error| StreamError::Cache(error);
+
+		let upload_id = self
+			.client
+			.create_multipart_upload()
+			.bucket(&self.bucket)
+			.key(self.key(key))
+			.set_metadata(expiry_metadata(expires_at))
+			.send()
+			.await
+			.map_err(|error| failed(error.into()))?
+			.upload_id
+			.unwrap_or_default();
+
+		let mut parts = Vec::new();
+		let uploaded = async {
+			while !part.is_empty() {
+				let part_number = i32::try_from(parts.len() + 1).unwrap_or(i32::MAX);
+				let response = self
+					.client
+					.upload_part()
+					.bucket(&self.bucket)
+					.key(self.key(key))
+					.upload_id(&upload_id)
+					.part_number(part_number)
+					.body(ByteStream::from(part))
+					.send()
+					.await
+					.map_err(|error| failed(error.into()))?;
+
+				parts.push(
+					CompletedPart::builder()
+						.set_e_tag(response.e_tag)
+						.part_number(part_number)
+						.build(),
+				);
+				part = read_part(reader).await.map_err(StreamError::Read)?;
+			}
+
+			self.client
+				.complete_multipart_upload()
+				.bucket(&self.bucket)
+				.key(self.key(key))
+				.upload_id(&upload_id)
+				.multipart_upload(
+					CompletedMultipartUpload::builder()
+						.set_parts(Some(parts))
+						.build(),
+				)
+				.send()
+				.await
+				.map_err(|error| failed(error.into()))?;
+
+			Ok(())
+		}
+		.await;
+
+		// Parts of an upload that's never completed are kept (and billed) until it's aborted.
+		if uploaded.is_err() {
+			self.client
+				.abort_multipart_upload()
+				.bucket(&self.bucket)
+				.key(self.key(key))
+				.upload_id(&upload_id)
+				.send()
+				.await
+				.ok();
+		}
+
+		uploaded
+	}
+
+	/// Every (prefixed) object key starting with `prefix`.
+	async fn list(&self, prefix: &str) -> Result<Vec<String>, Error> {
+		let mut keys = Vec::new();
+		let mut continuation_token = None;
+
+		loop {
+			let response = self
+				.client
+				.list_objects_v2()
+				.bucket(&self.bucket)
+				.prefix(self.key(prefix))
+				.set_continuation_token(continuation_token)
+				.send()
+				.await?;
+
+			keys.extend(
+				response
+					.contents()
+					.iter()
+					.filter_map(|object| object.key().map(ToString::to_string)),
+			);
+
+			match response.next_continuation_token() {
+				Some(token) => continuation_token = Some(token.to_string()),
+				None => return Ok(keys),
+			}
+		}
+	}
+
+	/// Delete the given (prefixed) object keys.
+	async fn delete(&self, keys: Vec<String>) -> Result<(), Error> {
+		// Batch deletes are limited to 1000 keys each.
+		for chunk in keys.chunks(1000) {
+			let objects = chunk
+				.iter()
+				.map(|key| ObjectIdentifier::builder().key(key).build())
+				.collect::<Result<Vec<_>, _>>()?;
+
+			let response = self
+				.client
+				.delete_objects()
+				.bucket(&self.bucket)
+				.delete(
+					Delete::builder()
+						.set_objects(Some(objects))
+						.quiet(true)
+						.build()?,
+				)
+				.send()
+				.await?;
+
+			// The request itself succeeds even if some of the keys couldn't be deleted, which are only listed here.
+			if let Some(error) = response.errors().first() {
+				return Err(Error::NotDeleted {
+					failed: response.errors().len(),
+					key: error.key().unwrap_or_default().to_string(),
+					message: error
+						.message()
+						.or_else(|| error.code())
+						.unwrap_or("unknown error")
+						.to_string(),
+				});
+			}
+		}
+
+		Ok(())
+	}
+}
+
+impl Driver for S3Driver {
+	type Error = Error;
+	type Config = Config;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		Ok(Self {
+			bucket: config.bucket,
+			prefix: config.prefix,
+			format: config.format,
+			client: aws_sdk_s3::Client::new(&config.aws_config),
+		})
+	}
+
+	fn prefix(&self) -> &str {
+		&self.prefix
+	}
+
+	fn is_unavailable(error: &Self::Error) -> bool {
+		error.is_unavailable()
+	}
+
+	fn format(&self) -> Option<Format> {
+		Some(self.format)
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		let Some(object) = self.download(key).await? else {
+			return Ok(None);
+		};
+
+		Ok(Some(self.format.deserialize(&object.data)?))
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		let Some(object) = self.download(key).await? else {
+			return Ok(GetOutcome::Miss);
+		};
+
+		Ok(self
+			.format
+			.deserialize(&object.data)
+			.map_or(GetOutcome::Corrupt(object.data), GetOutcome::Hit))
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		Ok(self
+			.head(key)
+			.await?
+			.is_some_and(|expires_at| !is_expired(expires_at)))
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		let Some(expires_at) = self.head(key).await? else {
+			return Ok(None);
+		};
+
+		Ok(expires_at.map_or(Some(Ttl::Forever), |expires_at| {
+			expires_at
+				.duration_since(SystemTime::now())
+				.ok()
+				.filter(|remaining| !remaining.is_zero())
+				.map(Ttl::In)
+		}))
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		Ok(self
+			.list(prefix.unwrap_or_default())
+			.await?
+			.into_iter()
+			.filter_map(|key| key.strip_prefix(&self.prefix).map(ToString::to_string))
+			.collect())
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		let expires_at = expiry.map(|expiry| SystemTime::now() + expiry);
+
+		self.upload(key, self.format.serialize(value)?, expires_at)
+			.await
+	}
+
+	async fn put_until<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		deadline: SystemTime,
+	) -> Result<(), Self::Error> {
+		// Like the other deadline paths, a deadline that has already passed removes the entry right away (which also
+		// covers deadlines before the Unix epoch, which can't be stored).
+		if deadline <= SystemTime::now() {
+			return self.forget(key).await.map(drop);
+		}
+
+		self.upload(key, self.format.serialize(value)?, Some(deadline))
+			.await
+	}
+
+	#[cfg(feature = "stream")]
+	async fn get_stream(&self, key: &str) -> Result<Option<BlobReader>, Self::Error> {
+		Ok(self
+			.fetch(key)
+			.await?
+			.map(|response| Box::new(Box::pin(response.body.into_async_read())) as BlobReader))
+	}
+
+	#[cfg(feature = "stream")]
+	async fn put_stream<R: AsyncRead + Send + Unpin>(
+		&mut self,
+		key: &str,
+		mut reader: R,
+		expiry: Option<Duration>,
+	) -> Result<(), StreamError<Self::Error>> {
+		let expires_at = expiry.map(|expiry| SystemTime::now() + expiry);
+
+		// Blobs that fit in a single part are uploaded in one request, and bigger ones part by part.
+		let part = read_part(&mut reader).await.map_err(StreamError::Read)?;
+		if part.len() < PART_SIZE {
+			return self
+				.upload(key, part, expires_at)
+				.await
+				.map_err(StreamError::Cache);
+		}
+
+		self.upload_parts(key, part, &mut reader, expires_at).await
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		// Keep the expiry of an existing counter, since the given one only applies when it's created.
+		let (value, expires_at) = match self.download(key).await? {
+			Some(object) => (
+				self.format
+					.deserialize::<i64>(&object.data)?
+					.checked_add(by)
+					.ok_or(Overflow)?,
+				object.expires_at,
+			),
+			None => (by, expiry.map(|expiry| SystemTime::now() + expiry)),
+		};
+
+		self.upload(key, self.format.serialize(&value)?, expires_at)
+			.await?;
+
+		Ok(value)
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		// Object metadata can't be changed in place, so the object is uploaded again with the new expiry.
+		let Some(object) = self.download(key).await? else {
+			return Ok(false);
+		};
+
+		let expires_at = expiry.map(|expiry| SystemTime::now() + expiry);
+		self.upload(key, object.data, expires_at).await?;
+
+		Ok(true)
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		// Deleting an object doesn't say whether it existed, so check first.
+		if self.head(key).await?.is_none() {
+			return Ok(false);
+		}
+
+		self.client
+			.delete_object()
+			.bucket(&self.bucket)
+			.key(self.key(key))
+			.send()
+			.await?;
+
+		Ok(true)
+	}
+
+	async fn forget_many(&mut self, keys: &[&str]) -> Result<(), Self::Error> {
+		self.delete(keys.iter().map(|key| self.key(key)).collect())
+			.await
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		let keys = self.list(prefix).await?;
+
+		self.delete(keys).await
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		self.forget_prefix("").await
+	}
+}
+
+impl Error {
+	/// Whether S3 couldn't be reached, or took too long to respond.
+	const fn is_unavailable(&self) -> bool {
+		match self {
+			Self::GetObject(error) => is_aws_unavailable(error),
+			Self::HeadObject(error) => is_aws_unavailable(error),
+			Self::PutObject(error) => is_aws_unavailable(error),
+			Self::DeleteObject(error) => is_aws_unavailable(error),
+			Self::DeleteObjects(error) => is_aws_unavailable(error),
+			Self::ListObjects(error) => is_aws_unavailable(error),
+			Self::CreateMultipartUpload(error) => is_aws_unavailable(error),
+			Self::UploadPart(error) => is_aws_unavailable(error),
+			Self::CompleteMultipartUpload(error) => is_aws_unavailable(error),
+			_ => false,
+		}
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error(transparent)]
+	Serialization(#[from] format::Error),
+	#[error(transparent)]
+	Build(#[from] BuildError),
+	#[error("failed to read object body: {0}")]
+	Body(#[from] ByteStreamError),
+	#[error(transparent)]
+	GetObject(
+		#[from]
+		SdkError<GetObjectError, aws_smithy_runtime_api::client::orchestrator::HttpResponse>,
+	),
+	#[error(transparent)]
+	HeadObject(
+		#[from]
+		SdkError<HeadObjectError, aws_smithy_runtime_api::client::orchestrator::HttpResponse>,
+	),
+	#[error(transparent)]
+	PutObject(
+		#[from]
+		SdkError<
+			aws_sdk_s3::operation::put_object::PutObjectError,
+			aws_smithy_runtime_api::client::orchestrator::HttpResponse,
+		>,
+	),
+	#[error(transparent)]
+	DeleteObject(
+		#[from]
+		SdkError<
+			aws_sdk_s3::operation::delete_object::DeleteObjectError,
+			aws_smithy_runtime_api::client::orchestrator::HttpResponse,
+		>,
+	),
+	#[error(transparent)]
+	DeleteObjects(
+		#[from]
+		SdkError<
+			aws_sdk_s3::operation::delete_objects::DeleteObjectsError,
+			aws_smithy_runtime_api::client::orchestrator::HttpResponse,
+		>,
+	),
+	#[error("failed to delete {failed} object(s), starting with {key:?}: {message}")]
+	NotDeleted {
+		failed: usize,
+		key: String,
+		message: String,
+	},
+	#[error(transparent)]
+	CreateMultipartUpload(
+		#[from]
+		SdkError<
+			aws_sdk_s3::operation::create_multipart_upload::CreateMultipartUploadError,
+			aws_smithy_runtime_api::client::orchestrator::HttpResponse,
+		>,
+	),
+	#[error(transparent)]
+	UploadPart(
+		#[from]
+		SdkError<
+			aws_sdk_s3::operation::upload_part::UploadPartError,
+			aws_smithy_runtime_api::client::orchestrator::HttpResponse,
+		>,
+	),
+	#[error(transparent)]
+	CompleteMultipartUpload(
+		#[from]
+		SdkError<
+			aws_sdk_s3::operation::complete_multipart_upload::CompleteMultipartUploadError,
+			aws_smithy_runtime_api::client::orchestrator::HttpResponse,
+		>,
+	),
+	#[error(transparent)]
+	ListObjects(
+		#[from]
+		SdkError<
+			aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Error,
+			aws_smithy_runtime_api::client::orchestrator::HttpResponse,
+		>,
+	),
+	#[error(transparent)]
+	Overflow(#[from] Overflow),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Cache;
+
+	#[test]
+	fn test_s3_expiry_metadata() {
+		let metadata = HashMap::from([(EXPIRES_AT.to_string(), "1000500".to_string())]);
+
+		assert_eq!(
+			expires_at(Some(&metadata)),
+			Some(UNIX_EPOCH + Duration::from_millis(1_000_500))
+		);
+		assert_eq!(expires_at(None), None);
+		assert!(is_expired(expires_at(Some(&metadata))));
+		assert!(!is_expired(None));
+
+		let deadline = UNIX_EPOCH + Duration::from_millis(1_000_500);
+		assert_eq!(expiry_metadata(Some(deadline)), Some(metadata));
+		assert_eq!(expiry_metadata(None), None);
+	}
+
+	#[tokio::test]
+	#[cfg(feature = "stream")]
+	async fn test_s3_read_part() {
+		let blob = vec![7; PART_SIZE + 10];
+		let mut reader = blob.as_slice();
+
+		assert_eq!(read_part(&mut reader).await.unwrap().len(), PART_SIZE);
+		assert_eq!(read_part(&mut reader).await.unwrap(), vec![7; 10]);
+		assert!(read_part(&mut reader).await.unwrap().is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_s3_driver() {
+		let mut cache = Cache::<S3Driver>::new(Config::default()).await.unwrap();
+
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+		assert!(!cache.has("foo").await.unwrap());
+
+		cache
+			.put("foo", &"bar", Duration::from_secs(10))
+			.await
+			.unwrap();
+
+		assert_eq!(
+			cache.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+		assert!(cache.forget("foo").await.unwrap());
+		assert!(!cache.has("foo").await.unwrap());
+	}
+}
@@ -0,0 +1,44 @@
+//! Schema versioning for byte-oriented drivers.
+//!
+//! When a driver is configured with a schema version, it's prepended to every stored value. Values stored with a
+//! different version (or without one) are treated as misses, so bumping the version lazily invalidates every entry.
+
+/// Prepend the schema version (if any) to a serialized value.
+pub fn wrap(version: Option<u32>, data: Vec<u8>) -> Vec<u8> {
+	let Some(version) = version else {
+		return data;
+	};
+
+	let mut wrapped = Vec::with_capacity(data.len() + 4);
+	wrapped.extend_from_slice(&version.to_le_bytes());
+	wrapped.extend_from_slice(&data);
+
+	wrapped
+}
+
+/// Strip the schema version from a stored value, returning `None` if it doesn't match the expected one.
+pub fn unwrap(version: Option<u32>, data: &[u8]) -> Option<&[u8]> {
+	let Some(version) = version else {
+		return Some(data);
+	};
+
+	let (stored, data) = data.split_first_chunk::<4>()?;
+
+	(u32::from_le_bytes(*stored) == version).then_some(data)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_schema_versioning() {
+		assert_eq!(wrap(None, vec![1, 2]), vec![1, 2]);
+		assert_eq!(unwrap(None, &[1, 2]), Some([1, 2].as_slice()));
+
+		let wrapped = wrap(Some(2), vec![1, 2]);
+		assert_eq!(unwrap(Some(2), &wrapped), Some([1, 2].as_slice()));
+		assert_eq!(unwrap(Some(3), &wrapped), None);
+		assert_eq!(unwrap(Some(2), &[1]), None);
+	}
+}
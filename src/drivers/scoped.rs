@@ -0,0 +1,270 @@
+#[cfg(feature = "stream")]
+use super::BlobReader;
+#[cfg(feature = "format")]
+use super::Format;
+use super::{Driver, EntryMeta, FlushWhere, GetOutcome, PoolStatus, Ttl};
+#[cfg(feature = "stream")]
+use crate::StreamError;
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::{Duration, SystemTime};
+#[cfg(feature = "stream")]
+use tokio::io::AsyncRead;
+
+/// A driver that prepends an extra prefix to every key before handing it to another driver.
+///
+/// Obtained through [`Cache::with_prefix`](crate::Cache::with_prefix), it borrows the underlying driver instead of
+/// opening a new connection. Flushing a scoped driver only removes the entries within its scope.
+#[derive(Debug)]
+pub struct ScopedDriver<'a, D: Driver> {
+	scope: String,
+	prefix: String,
+	driver: &'a mut D,
+}
+
+impl<'a, D: Driver> ScopedDriver<'a, D> {
+	pub(crate) fn new(driver: &'a mut D, scope: &str) -> Self {
+		Self {
+			scope: scope.to_string(),
+			prefix: format!("{}{scope}", driver.prefix()),
+			driver,
+		}
+	}
+
+	fn key(&self, key: &str) -> String {
+		format!("{}{key}", self.scope)
+	}
+}
+
+impl<'a, D: Driver> Driver for ScopedDriver<'a, D> {
+	type Error = D::Error;
+	type Config = (&'a mut D, String);
+
+	async fn new((driver, scope): Self::Config) -> Result<Self, Self::Error> {
+		Ok(Self::new(driver, &scope))
+	}
+
+	fn prefix(&self) -> &str {
+		&self.prefix
+	}
+
+	fn pool_status(&self) -> Option<PoolStatus> {
+		self.driver.pool_status()
+	}
+
+	fn is_unavailable(error: &Self::Error) -> bool {
+		D::is_unavailable(error)
+	}
+
+	#[cfg(feature = "format")]
+	fn format(&self) -> Option<Format> {
+		self.driver.format()
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		self.driver.get(&self.key(key)).await
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		self.driver.try_get(&self.key(key)).await
+	}
+
+	async fn get_many<T: DeserializeOwned + Send>(
+		&self,
+		keys: &[&str],
+	) -> Result<Vec<Option<T>>, Self::Error> {
+		let keys = keys.iter().map(|key| self.key(key)).collect::<Vec<_>>();
+		let keys = keys.iter().map(String::as_str).collect::<Vec<_>>();
+
+		self.driver.get_many(&keys).await
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		self.driver.has(&self.key(key)).await
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		self.driver.ttl(&self.key(key)).await
+	}
+
+	async fn metadata(&self, key: &str) -> Result<Option<EntryMeta>, Self::Error> {
+		self.driver.metadata(&self.key(key)).await
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		let keys = self
+			.driver
+			.keys(Some(&self.key(prefix.unwrap_or_default())))
+			.await?;
+
+		Ok(keys
+			.into_iter()
+			.filter_map(|key| key.strip_prefix(self.scope.as_str()).map(str::to_string))
+			.collect())
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		let key = self.key(key);
+
+		self.driver.put(&key, value, expiry).await
+	}
+
+	async fn put_until<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		deadline: SystemTime,
+	) -> Result<(), Self::Error> {
+		let key = self.key(key);
+
+		self.driver.put_until(&key, value, deadline).await
+	}
+
+	#[cfg(feature = "stream")]
+	async fn get_stream(&self, key: &str) -> Result<Option<BlobReader>, Self::Error> {
+		self.driver.get_stream(&self.key(key)).await
+	}
+
+	#[cfg(feature = "stream")]
+	async fn put_stream<R: AsyncRead + Send + Unpin>(
+		&mut self,
+		key: &str,
+		reader: R,
+		expiry: Option<Duration>,
+	) -> Result<(), StreamError<Self::Error>> {
+		self.driver.put_stream(&self.key(key), reader, expiry).await
+	}
+
+	async fn put_many<T: Serialize + Sync>(
+		&mut self,
+		entries: &[(&str, &T)],
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		let keys = entries
+			.iter()
+			.map(|(key, _)| self.key(key))
+			.collect::<Vec<_>>();
+		let entries = keys
+			.iter()
+			.zip(entries)
+			.map(|(key, (_, value))| (key.as_str(), *value))
+			.collect::<Vec<_>>();
+
+		self.driver.put_many(&entries, expiry).await
+	}
+
+	async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		expiry: Option<Duration>,
+	) -> Result<T, Self::Error> {
+		let key = self.key(key);
+
+		self.driver.get_or_put(&key, value, expiry).await
+	}
+
+	async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<Option<T>, Self::Error> {
+		let key = self.key(key);
+
+		self.driver.replace(&key, value, expiry).await
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		let key = self.key(key);
+
+		self.driver.add(&key, value, expiry).await
+	}
+
+	async fn compare_and_swap<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+		new: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		let key = self.key(key);
+
+		self.driver
+			.compare_and_swap(&key, expected, new, expiry)
+			.await
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		let key = self.key(key);
+
+		self.driver.increment(&key, by, expiry).await
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		let key = self.key(key);
+
+		self.driver.touch(&key, expiry).await
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		let key = self.key(key);
+
+		self.driver.forget(&key).await
+	}
+
+	async fn forget_many(&mut self, keys: &[&str]) -> Result<(), Self::Error> {
+		let keys = keys.iter().map(|key| self.key(key)).collect::<Vec<_>>();
+		let keys = keys.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+
+		self.driver.forget_many(&keys).await
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		let prefix = self.key(prefix);
+
+		self.driver.forget_prefix(&prefix).await
+	}
+
+	async fn forget_if<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+	) -> Result<bool, Self::Error> {
+		let key = self.key(key);
+
+		self.driver.forget_if(&key, expected).await
+	}
+
+	async fn purge_expired(&mut self) -> Result<usize, Self::Error> {
+		// Expired entries are never read back, so they're removed from every scope instead of only this one.
+		self.driver.purge_expired().await
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		self.driver.forget_prefix(&self.scope).await
+	}
+}
+
+impl<D: FlushWhere> FlushWhere for ScopedDriver<'_, D> {
+	fn flush_where(&mut self, predicate: impl Fn(&str) -> bool) -> usize {
+		let scope = &self.scope;
+
+		self.driver
+			.flush_where(|key| key.strip_prefix(scope.as_str()).is_some_and(&predicate))
+	}
+}
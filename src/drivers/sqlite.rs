@@ -0,0 +1,604 @@
+use super::{format, Driver, Format, GetOutcome, Overflow, Ttl};
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::{
+	sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions},
+	SqliteConnection,
+};
+use std::{
+	str::FromStr,
+	sync::{Arc, Weak},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone)]
+pub struct Config {
+	/// The path to the database file, which is created if it doesn't exist.
+	pub path: String,
+	/// The table cache entries are stored in, which is created if it doesn't exist. Only letters, digits and
+	/// underscores are allowed.
+	pub table: String,
+	/// The format values are serialized with.
+	pub format: Format,
+	/// How often to remove expired entries from the table in the background, or `None` to only remove them through
+	/// [`SqliteDriver::remove_expired`]. The background task stops once every clone of the driver is dropped.
+	pub purge_interval: Option<Duration>,
+}
+
+config_builder! {
+	path: String,
+	table: String,
+	format: Format,
+	purge_interval: Option<Duration>,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			table: "cache".to_string(),
+			format: Format::default(),
+			purge_interval: None,
+			path: std::env::temp_dir()
+				.join("amnesia.sqlite")
+				.to_string_lossy()
+				.into_owned(),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+/// A driver that stores cache entries in a `SQLite` database, without needing a database server.
+///
+/// Expired entries are skipped when reading, but stay in the table until they're overwritten or purged (either every
+/// `purge_interval`, or by calling [`SqliteDriver::remove_expired`]). Clones share the same connection pool.
+pub struct SqliteDriver {
+	table: String,
+	format: Format,
+	pool: SqlitePool,
+	/// Keeps the background purge task running for as long as any clone of the driver is alive.
+	_alive: Arc<()>,
+}
+
+/// The current time, as stored in the `expiration` column.
+fn now() -> i64 {
+	i64::try_from(
+		SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_millis(),
+	)
+	.unwrap_or(i64::MAX)
+}
+
+/// When an entry stored with the given expiry expires, as stored in the `expiration` column.
+fn expires_at(expiry: Duration) -> i64 {
+	now().saturating_add(i64::try_from(expiry.as_millis()).unwrap_or(i64::MAX))
+}
+
+/// Remove all expired entries from a table, returning how many were removed.
+async fn remove_expired(pool: &SqlitePool, table: &str) -> Result<u64, Error> {
+	let result = sqlx::query(&format!(
+		r#"DELETE FROM "{table}" WHERE expiration IS NOT NULL AND expiration <= ?"#
+	))
+	.bind(now())
+	.execute(pool)
+	.await?;
+
+	Ok(result.rows_affected())
+}
+
+/// Periodically remove expired entries from a table, until the driver is dropped.
+async fn purge(pool: SqlitePool, table: String, interval: Duration, alive: Weak<()>) {
+	loop {
+		tokio::time::sleep(interval).await;
+		if alive.strong_count() == 0 {
+			break;
+		}
+
+		// Expired entries are never read, so failing to remove them can wait until the next run.
+		remove_expired(&pool, &table).await.ok();
+	}
+}
+
+impl SqliteDriver {
+	/// Remove all expired entries from the table, returning how many were removed.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the entries can't be removed.
+	pub async fn remove_expired(&self) -> Result<u64, Error> {
+		remove_expired(&self.pool, &self.table).await
+	}
+
+	/// Read the stored bytes and expiration for an entry that hasn't expired.
+	async fn read(
+		&self,
+		conn: &mut SqliteConnection,
+		key: &str,
+	) -> Result<Option<(Vec<u8>, Option<i64>)>, Error> {
+		Ok(sqlx::query_as(&format!(
+			r#"SELECT value, expiration FROM "{}" WHERE key = ? AND (expiration IS NULL OR expiration > ?)"#,
+			self.table
+		))
+		.bind(key)
+		.bind(now())
+		.fetch_optional(conn)
+		.await?)
+	}
+
+	/// Store an entry, replacing any existing one.
+	async fn write(
+		&self,
+		conn: &mut SqliteConnection,
+		key: &str,
+		data: &[u8],
+		expiration: Option<i64>,
+	) -> Result<(), Error> {
+		sqlx::query(&format!(
+			r#"INSERT INTO "{}" (key, value, expiration) VALUES (?, ?, ?)
+			ON CONFLICT (key) DO UPDATE SET value = excluded.value, expiration = excluded.expiration"#,
+			self.table
+		))
+		.bind(key)
+		.bind(data)
+		.bind(expiration)
+		.execute(conn)
+		.await?;
+
+		Ok(())
+	}
+
+	/// Start a transaction that holds the write lock from the start, so entries can be read and updated atomically.
+	async fn transaction(&self) -> Result<sqlx::Transaction<'static, sqlx::Sqlite>, Error> {
+		Ok(self.pool.begin_with("BEGIN IMMEDIATE").await?)
+	}
+}
+
+impl Driver for SqliteDriver {
+	type Error = Error;
+	type Config = Config;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		// The table name ends up in the SQL itself (queries can't bind identifiers), so it's restricted to characters
+		// that can't escape it.
+		if config.table.is_empty()
+			|| !config
+				.table
+				.chars()
+				.all(|c| c.is_ascii_alphanumeric() || c == '_')
+		{
+			return Err(Error::InvalidTable(config.table));
+		}
+
+		let options = SqliteConnectOptions::from_str(&config.path)?.create_if_missing(true);
+		let pool = SqlitePoolOptions::new().connect_with(options).await?;
+
+		sqlx::query(&format!(
+			r#"CREATE TABLE IF NOT EXISTS "{}" (key TEXT PRIMARY KEY NOT NULL, value BLOB NOT NULL, expiration INTEGER)"#,
+			config.table
+		))
+		.execute(&pool)
+		.await?;
+
+		let alive = Arc::new(());
+		if let Some(interval) = config.purge_interval {
+			tokio::spawn(purge(
+				pool.clone(),
+				config.table.clone(),
+				interval,
+				Arc::downgrade(&alive),
+			));
+		}
+
+		Ok(Self {
+			pool,
+			_alive: alive,
+			table: config.table,
+			format: config.format,
+		})
+	}
+
+	fn format(&self) -> Option<Format> {
+		Some(self.format)
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		let mut conn = self.pool.acquire().await?;
+		let Some((data, _)) = self.read(&mut conn, key).await? else {
+			return Ok(None);
+		};
+
+		Ok(Some(self.format.deserialize(&data)?))
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		let mut conn = self.pool.acquire().await?;
+		let Some((data, _)) = self.read(&mut conn, key).await? else {
+			return Ok(GetOutcome::Miss);
+		};
+
+		Ok(self
+			.format
+			.deserialize(&data)
+			.map_or(GetOutcome::Corrupt(data), GetOutcome::Hit))
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		let exists = sqlx::query_scalar::<_, bool>(&format!(
+			r#"SELECT EXISTS (SELECT 1 FROM "{}" WHERE key = ? AND (expiration IS NULL OR expiration > ?))"#,
+			self.table
+		))
+		.bind(key)
+		.bind(now())
+		.fetch_one(&self.pool)
+		.await?;
+
+		Ok(exists)
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		let mut conn = self.pool.acquire().await?;
+		let Some((_, expiration)) = self.read(&mut conn, key).await? else {
+			return Ok(None);
+		};
+
+		Ok(Some(expiration.map_or(Ttl::Forever, |expiration| {
+			Ttl::In(Duration::from_millis(
+				u64::try_from(expiration - now()).unwrap_or_default(),
+			))
+		})))
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		Ok(sqlx::query_scalar(&format!(
+			r#"SELECT key FROM "{}" WHERE (expiration IS NULL OR expiration > ?1) AND substr(key, 1, length(?2)) = ?2"#,
+			self.table
+		))
+		.bind(now())
+		.bind(prefix.unwrap_or_default())
+		.fetch_all(&self.pool)
+		.await?)
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		let data = self.format.serialize(value)?;
+		let mut conn = self.pool.acquire().await?;
+
+		self.write(&mut conn, key, &data, expiry.map(expires_at))
+			.await
+	}
+
+	async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		expiry: Option<Duration>,
+	) -> Result<T, Self::Error> {
+		let mut transaction = self.transaction().await?;
+
+		if let Some((data, _)) = self.read(&mut transaction, key).await? {
+			return Ok(self.format.deserialize(&data)?);
+		}
+
+		let data = self.format.serialize(&value)?;
+		self.write(&mut transaction, key, &data, expiry.map(expires_at))
+			.await?;
+		transaction.commit().await?;
+
+		Ok(value)
+	}
+
+	async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<Option<T>, Self::Error> {
+		let mut transaction = self.transaction().await?;
+
+		let previous = self.read(&mut transaction, key).await?;
+		let data = self.format.serialize(value)?;
+		self.write(&mut transaction, key, &data, expiry.map(expires_at))
+			.await?;
+		transaction.commit().await?;
+
+		Ok(previous
+			.map(|(previous, _)| self.format.deserialize(&previous))
+			.transpose()?)
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		let mut transaction = self.transaction().await?;
+
+		if self.read(&mut transaction, key).await?.is_some() {
+			return Ok(false);
+		}
+
+		let data = self.format.serialize(value)?;
+		self.write(&mut transaction, key, &data, expiry.map(expires_at))
+			.await?;
+		transaction.commit().await?;
+
+		Ok(true)
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		let mut transaction = self.transaction().await?;
+
+		let (value, expiration) = match self.read(&mut transaction, key).await? {
+			Some((data, expiration)) => (
+				self.format
+					.deserialize::<i64>(&data)?
+					.checked_add(by)
+					.ok_or(Overflow)?,
+				expiration,
+			),
+			None => (by, expiry.map(expires_at)),
+		};
+
+		let data = self.format.serialize(&value)?;
+		self.write(&mut transaction, key, &data, expiration).await?;
+		transaction.commit().await?;
+
+		Ok(value)
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		let result = sqlx::query(&format!(
+			r#"UPDATE "{}" SET expiration = ? WHERE key = ? AND (expiration IS NULL OR expiration > ?)"#,
+			self.table
+		))
+		.bind(expiry.map(expires_at))
+		.bind(key)
+		.bind(now())
+		.execute(&self.pool)
+		.await?;
+
+		Ok(result.rows_affected() != 0)
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		let result = sqlx::query(&format!(
+			r#"DELETE FROM "{}" WHERE key = ? AND (expiration IS NULL OR expiration > ?)"#,
+			self.table
+		))
+		.bind(key)
+		.bind(now())
+		.execute(&self.pool)
+		.await?;
+
+		Ok(result.rows_affected() != 0)
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		// Compared with `substr` rather than `LIKE`, which ignores case and treats `%` and `_` in the prefix as wildcards.
+		sqlx::query(&format!(
+			r#"DELETE FROM "{}" WHERE substr(key, 1, length(?1)) = ?1"#,
+			self.table
+		))
+		.bind(prefix)
+		.execute(&self.pool)
+		.await?;
+
+		Ok(())
+	}
+
+	async fn purge_expired(&mut self) -> Result<usize, Self::Error> {
+		Ok(usize::try_from(self.remove_expired().await?).unwrap_or(usize::MAX))
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		sqlx::query(&format!(r#"DELETE FROM "{}""#, self.table))
+			.execute(&self.pool)
+			.await?;
+
+		Ok(())
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error(transparent)]
+	Sqlite(#[from] sqlx::Error),
+	#[error(transparent)]
+	Serialization(#[from] format::Error),
+	#[error("invalid table name {0:?}, only letters, digits and underscores are allowed.")]
+	InvalidTable(String),
+	#[error(transparent)]
+	Overflow(#[from] Overflow),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Cache, Expiry};
+	use std::process;
+
+	fn config(name: &str) -> Config {
+		let path = std::env::temp_dir().join(format!("amnesia-{name}-{}.sqlite", process::id()));
+		std::fs::remove_file(&path).ok();
+
+		Config {
+			path: path.to_string_lossy().into_owned(),
+			..Default::default()
+		}
+	}
+
+	async fn cache(name: &str) -> Cache<SqliteDriver> {
+		Cache::new(config(name)).await.unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_sqlite_driver() {
+		let mut cache = cache("driver").await;
+
+		assert_eq!(cache.get::<String>("foo").await.unwrap(), None);
+		assert!(!cache.has("foo").await.unwrap());
+
+		cache
+			.put("foo", &"bar", Duration::from_secs(10))
+			.await
+			.unwrap();
+
+		assert_eq!(
+			cache.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+		assert!(cache.has("foo").await.unwrap());
+		assert!(matches!(cache.ttl("foo").await.unwrap(), Some(Ttl::In(_))));
+
+		cache.forever("foo", "baz").await.unwrap();
+		assert_eq!(
+			cache.get::<String>("foo").await.unwrap(),
+			Some("baz".to_string())
+		);
+		assert_eq!(cache.ttl("foo").await.unwrap(), Some(Ttl::Forever));
+
+		assert!(cache.forget("foo").await.unwrap());
+		assert!(!cache.forget("foo").await.unwrap());
+
+		cache.forever("foo", "bar").await.unwrap();
+		cache.flush().await.unwrap();
+		assert!(!cache.has("foo").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_sqlite_table_name() {
+		let driver = SqliteDriver::new(Config {
+			table: "amnesia_cache_2".to_string(),
+			..config("table")
+		})
+		.await
+		.unwrap();
+		assert_eq!(driver.table, "amnesia_cache_2");
+
+		for table in [
+			"",
+			"cache\"; DROP TABLE users; --",
+			"cache.entries",
+			"cache entries",
+		] {
+			assert!(matches!(
+				SqliteDriver::new(Config {
+					table: table.to_string(),
+					..config("table")
+				})
+				.await,
+				Err(Error::InvalidTable(name)) if name == table
+			));
+		}
+	}
+
+	#[tokio::test]
+	async fn test_sqlite_expiry() {
+		let mut driver = SqliteDriver::new(config("expiry")).await.unwrap();
+
+		driver
+			.put("foo", &"bar", Some(Duration::from_millis(50)))
+			.await
+			.unwrap();
+		driver.put("baz", &"qux", None).await.unwrap();
+
+		tokio::time::sleep(Duration::from_millis(100)).await;
+		assert!(!driver.has("foo").await.unwrap());
+		assert_eq!(driver.get::<String>("foo").await.unwrap(), None);
+
+		assert_eq!(driver.remove_expired().await.unwrap(), 1);
+		assert!(driver.has("baz").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_sqlite_purge_interval() {
+		let mut driver = SqliteDriver::new(Config {
+			purge_interval: Some(Duration::from_millis(50)),
+			..config("purge")
+		})
+		.await
+		.unwrap();
+
+		driver
+			.put("foo", &"bar", Some(Duration::from_millis(10)))
+			.await
+			.unwrap();
+
+		tokio::time::sleep(Duration::from_millis(150)).await;
+		assert_eq!(driver.remove_expired().await.unwrap(), 0);
+	}
+
+	#[tokio::test]
+	async fn test_sqlite_increment() {
+		let mut cache = cache("increment").await;
+
+		let tasks = (0..10)
+			.map(|_| {
+				let mut cache = cache.clone();
+				tokio::spawn(async move { cache.increment("hits", 1).await.unwrap() })
+			})
+			.collect::<Vec<_>>();
+		for task in tasks {
+			task.await.unwrap();
+		}
+
+		assert_eq!(cache.decrement("hits", 3).await.unwrap(), 7);
+		assert_eq!(
+			cache
+				.get_or_put("hits", 0_i64, Duration::from_secs(10))
+				.await
+				.unwrap(),
+			7
+		);
+	}
+
+	#[tokio::test]
+	async fn test_sqlite_forget_prefix() {
+		let mut cache = cache("forget-prefix").await;
+
+		cache
+			.put_many(
+				&[
+					("user:1:posts", 1),
+					("USER:1:profile", 2),
+					("user_1:posts", 3),
+				],
+				Expiry::Forever,
+			)
+			.await
+			.unwrap();
+		cache.forget_prefix("user:1:").await.unwrap();
+
+		// Prefixes are matched exactly, rather than as a case-insensitive pattern.
+		assert_eq!(
+			cache
+				.get_many::<i32>(&["user:1:posts", "USER:1:profile", "user_1:posts"])
+				.await
+				.unwrap(),
+			vec![None, Some(2), Some(3)]
+		);
+		cache.forget_prefix("user_").await.unwrap();
+		assert!(!cache.has("user_1:posts").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_sqlite_add() {
+		let mut cache = cache("add").await;
+
+		assert!(cache.add("foo", 1, Expiry::Forever).await.unwrap());
+		assert!(!cache.add("foo", 2, Expiry::Forever).await.unwrap());
+		assert_eq!(cache.get::<i32>("foo").await.unwrap(), Some(1));
+	}
+}
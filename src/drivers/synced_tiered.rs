@@ -0,0 +1,542 @@
+//! A tiered driver that keeps L1 consistent across processes, by broadcasting invalidations over Redis pub/sub.
+//!
+//! Invalidations are published as plain text, as `{origin} {kind} {key}`: `origin` identifies the driver that made
+//! the change (so it can ignore its own messages), `kind` is `key`, `prefix` or `flush`, and `key` is the (driver
+//! level) key or prefix that changed, left empty for `flush`.
+
+#[cfg(feature = "format")]
+use super::Format;
+use super::{
+	tiered::{self, TieredDriver},
+	Driver, EntryMeta, GetOutcome, Overflow, PoolStatus, Ttl,
+};
+use futures_util::StreamExt;
+use redis::{aio::MultiplexedConnection, AsyncCommands};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	fmt,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc, Weak,
+	},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// How long to wait before resubscribing after losing the connection to Redis.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+pub struct Config<A: Driver, B: Driver> {
+	/// The config for the two tiers.
+	pub tiered: tiered::Config<A, B>,
+	/// The Redis server invalidations are published on. Usually the same one backing L2.
+	pub redis_url: String,
+	/// The channel invalidations are published on. Every process sharing an L2 should use the same one.
+	pub channel: String,
+}
+
+config_builder! {
+	<A: Driver, B: Driver>
+	tiered: tiered::Config<A, B>,
+	redis_url: String,
+	channel: String,
+}
+
+impl<A: Driver, B: Driver> fmt::Debug for Config<A, B>
+where
+	A::Config: fmt::Debug,
+	B::Config: fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Config")
+			.field("tiered", &self.tiered)
+			.field("redis_url", &super::redis::redact_url(&self.redis_url))
+			.field("channel", &self.channel)
+			.finish()
+	}
+}
+
+impl<A: Driver, B: Driver> Default for Config<A, B>
+where
+	A::Config: Default,
+	B::Config: Default,
+{
+	fn default() -> Self {
+		Self {
+			tiered: tiered::Config::default(),
+			redis_url: "redis://localhost".to_string(),
+			channel: "amnesia:invalidations".to_string(),
+		}
+	}
+}
+
+#[derive(Clone)]
+#[allow(clippy::module_name_repetitions)]
+/// A [`TieredDriver`] for running across several processes, which evicts entries from its L1 when another process
+/// changes them.
+///
+/// Every write and removal is published on a Redis channel after it's been applied, and a background Tokio task
+/// removes the changed entries from this process' L1. If the subscription drops, the task resubscribes and flushes
+/// L1, since it may have missed invalidations in the meantime. The task stops (at the next message) once every clone
+/// of the driver is dropped.
+///
+/// Reads that copy an entry from L2 into L1 drop the copy again if an invalidation arrived while they were running, so
+/// racing a write in another process can't leave a stale value in L1.
+///
+/// Since invalidations are delivered asynchronously, other processes may still serve the previous value for a short
+/// while after a write.
+pub struct SyncedTieredDriver<A: Driver, B: Driver> {
+	tiered: TieredDriver<A, B>,
+	origin: Arc<str>,
+	channel: String,
+	conn: MultiplexedConnection,
+	/// Keeps the background task running, which stops once every clone of the driver (and so this) is dropped.
+	_alive: Arc<()>,
+}
+
+impl<A: Driver + fmt::Debug, B: Driver + fmt::Debug> fmt::Debug for SyncedTieredDriver<A, B> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("SyncedTieredDriver")
+			.field("tiered", &self.tiered)
+			.field("origin", &self.origin)
+			.field("channel", &self.channel)
+			.finish_non_exhaustive()
+	}
+}
+
+/// What an invalidation applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+	Key(String),
+	Prefix(String),
+	Flush,
+}
+
+impl Target {
+	fn encode(&self, origin: &str) -> String {
+		match self {
+			Self::Key(key) => format!("{origin} key {key}"),
+			Self::Prefix(prefix) => format!("{origin} prefix {prefix}"),
+			Self::Flush => format!("{origin} flush "),
+		}
+	}
+
+	/// Parse a published invalidation into its origin and target.
+	fn decode(message: &str) -> Option<(&str, Self)> {
+		let mut parts = message.splitn(3, ' ');
+		let (origin, kind, key) = (parts.next()?, parts.next()?, parts.next()?);
+
+		let target = match kind {
+			"key" => Self::Key(key.to_string()),
+			"prefix" => Self::Prefix(key.to_string()),
+			"flush" => Self::Flush,
+			_ => return None,
+		};
+
+		Some((origin, target))
+	}
+
+	async fn apply<D: Driver>(&self, l1: &mut D) -> Result<(), D::Error> {
+		match self {
+			Self::Key(key) => l1.forget(key).await.map(|_| ()),
+			Self::Prefix(prefix) => l1.forget_prefix(prefix).await,
+			Self::Flush => l1.flush().await,
+		}
+	}
+}
+
+/// A name for this driver that's unique across processes, so it can recognize its own invalidations.
+fn origin() -> String {
+	static NEXT: AtomicU64 = AtomicU64::new(0);
+
+	let started = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap()
+		.as_nanos();
+
+	format!(
+		"{:x}-{started:x}-{}",
+		std::process::id(),
+		NEXT.fetch_add(1, Ordering::Relaxed)
+	)
+}
+
+async fn subscribe(
+	client: &redis::Client,
+	channel: &str,
+) -> redis::RedisResult<redis::aio::PubSub> {
+	let mut pubsub = client.get_async_connection().await?.into_pubsub();
+	pubsub.subscribe(channel).await?;
+
+	Ok(pubsub)
+}
+
+/// Apply invalidations published by other processes to `l1`, until every clone of the driver is dropped.
+///
+/// `invalidations` is bumped before each eviction, so reads that were copying an entry from L2 at the time drop their
+/// (possibly stale) copy.
+async fn listen<A: Driver + 'static>(
+	client: redis::Client,
+	channel: String,
+	origin: Arc<str>,
+	mut pubsub: redis::aio::PubSub,
+	mut l1: A,
+	invalidations: Arc<AtomicU64>,
+	alive: Weak<()>,
+) {
+	loop {
+		let mut messages = pubsub.on_message();
+
+		while let Some(message) = messages.next().await {
+			if alive.strong_count() == 0 {
+				return;
+			}
+
+			let Ok(message) = message.get_payload::<String>() else {
+				continue;
+			};
+
+			if let Some((sender, target)) = Target::decode(&message) {
+				if sender != &*origin {
+					invalidations.fetch_add(1, Ordering::SeqCst);
+					// Failing to evict only leaves the entry until its L1 expiry, so there's nothing better to do.
+					let _ = target.apply(&mut l1).await;
+				}
+			}
+		}
+
+		drop(messages);
+
+		pubsub = loop {
+			if alive.strong_count() == 0 {
+				return;
+			}
+
+			tokio::time::sleep(RECONNECT_DELAY).await;
+
+			if let Ok(pubsub) = subscribe(&client, &channel).await {
+				break pubsub;
+			}
+		};
+
+		// Invalidations published while disconnected were missed, so any entry in L1 may be stale.
+		invalidations.fetch_add(1, Ordering::SeqCst);
+		let _ = l1.flush().await;
+	}
+}
+
+impl<A: Driver, B: Driver> SyncedTieredDriver<A, B> {
+	/// Tell the other processes that `target` changed.
+	async fn publish(&self, target: Target) -> Result<(), Error<A::Error, B::Error>> {
+		self.conn
+			.clone()
+			.publish::<_, _, ()>(&self.channel, target.encode(&self.origin))
+			.await?;
+
+		Ok(())
+	}
+}
+
+impl<A: Driver + Clone + 'static, B: Driver> Driver for SyncedTieredDriver<A, B> {
+	type Error = Error<A::Error, B::Error>;
+	type Config = Config<A, B>;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		let client = redis::Client::open(config.redis_url)?;
+
+		// Subscribe before anything is cached, so no invalidation is missed.
+		let pubsub = subscribe(&client, &config.channel).await?;
+		let conn = client.get_multiplexed_tokio_connection().await?;
+		let tiered = TieredDriver::new(config.tiered).await?;
+
+		let origin: Arc<str> = origin().into();
+		let alive = Arc::new(());
+
+		tokio::spawn(listen(
+			client,
+			config.channel.clone(),
+			origin.clone(),
+			pubsub,
+			tiered.l1().clone(),
+			tiered.invalidations(),
+			Arc::downgrade(&alive),
+		));
+
+		Ok(Self {
+			tiered,
+			origin,
+			channel: config.channel,
+			conn,
+			_alive: alive,
+		})
+	}
+
+	fn prefix(&self) -> &str {
+		self.tiered.prefix()
+	}
+
+	fn pool_status(&self) -> Option<PoolStatus> {
+		self.tiered.pool_status()
+	}
+
+	fn is_unavailable(error: &Self::Error) -> bool {
+		match error {
+			Error::Tiered(error) => TieredDriver::<A, B>::is_unavailable(error),
+			Error::Redis(error) => {
+				error.is_io_error() || error.is_connection_refusal() || error.is_timeout()
+			},
+			Error::Overflow(_) => false,
+		}
+	}
+
+	#[cfg(feature = "format")]
+	fn format(&self) -> Option<Format> {
+		self.tiered.format()
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		Ok(self.tiered.get(key).await?)
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		Ok(self.tiered.try_get(key).await?)
+	}
+
+	async fn get_many<T: DeserializeOwned + Send>(
+		&self,
+		keys: &[&str],
+	) -> Result<Vec<Option<T>>, Self::Error> {
+		Ok(self.tiered.get_many(keys).await?)
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		Ok(self.tiered.has(key).await?)
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		Ok(self.tiered.ttl(key).await?)
+	}
+
+	async fn metadata(&self, key: &str) -> Result<Option<EntryMeta>, Self::Error> {
+		Ok(self.tiered.metadata(key).await?)
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		Ok(self.tiered.keys(prefix).await?)
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		self.tiered.put(key, value, expiry).await?;
+
+		self.publish(Target::Key(key.to_string())).await
+	}
+
+	async fn put_until<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		deadline: SystemTime,
+	) -> Result<(), Self::Error> {
+		self.tiered.put_until(key, value, deadline).await?;
+
+		self.publish(Target::Key(key.to_string())).await
+	}
+
+	async fn put_many<T: Serialize + Sync>(
+		&mut self,
+		entries: &[(&str, &T)],
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		self.tiered.put_many(entries, expiry).await?;
+
+		for (key, _) in entries {
+			self.publish(Target::Key((*key).to_string())).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		expiry: Option<Duration>,
+	) -> Result<T, Self::Error> {
+		// Only stores a value when there wasn't one, so no other process can have a stale copy.
+		Ok(self.tiered.get_or_put(key, value, expiry).await?)
+	}
+
+	async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<Option<T>, Self::Error> {
+		let previous = self.tiered.replace(key, value, expiry).await?;
+		self.publish(Target::Key(key.to_string())).await?;
+
+		Ok(previous)
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		// Like `get_or_put`, only stores a value when there wasn't one.
+		Ok(self.tiered.add(key, value, expiry).await?)
+	}
+
+	async fn compare_and_swap<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+		new: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		if !self
+			.tiered
+			.compare_and_swap(key, expected, new, expiry)
+			.await?
+		{
+			return Ok(false);
+		}
+
+		self.publish(Target::Key(key.to_string())).await?;
+
+		Ok(true)
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		let value = self.tiered.increment(key, by, expiry).await?;
+		self.publish(Target::Key(key.to_string())).await?;
+
+		Ok(value)
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		let touched = self.tiered.touch(key, expiry).await?;
+		self.publish(Target::Key(key.to_string())).await?;
+
+		Ok(touched)
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		let forgotten = self.tiered.forget(key).await?;
+		self.publish(Target::Key(key.to_string())).await?;
+
+		Ok(forgotten)
+	}
+
+	async fn forget_many(&mut self, keys: &[&str]) -> Result<(), Self::Error> {
+		self.tiered.forget_many(keys).await?;
+
+		for key in keys {
+			self.publish(Target::Key((*key).to_string())).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		self.tiered.forget_prefix(prefix).await?;
+
+		self.publish(Target::Prefix(prefix.to_string())).await
+	}
+
+	async fn forget_if<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+	) -> Result<bool, Self::Error> {
+		if !self.tiered.forget_if(key, expected).await? {
+			return Ok(false);
+		}
+
+		self.publish(Target::Key(key.to_string())).await?;
+
+		Ok(true)
+	}
+
+	async fn purge_expired(&mut self) -> Result<usize, Self::Error> {
+		Ok(self.tiered.purge_expired().await?)
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		self.tiered.flush().await?;
+
+		self.publish(Target::Flush).await
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error<A, B> {
+	#[error(transparent)]
+	Tiered(#[from] tiered::Error<A, B>),
+	#[error("failed to publish or subscribe to invalidations: {0}")]
+	Redis(#[from] redis::RedisError),
+	#[error(transparent)]
+	Overflow(#[from] Overflow),
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+	use super::*;
+	use crate::drivers::{MemoryDriver, RedisDriver};
+
+	type Synced = SyncedTieredDriver<MemoryDriver, RedisDriver>;
+
+	#[test]
+	fn test_invalidation_encoding() {
+		for target in [
+			Target::Key("user:1 name".to_string()),
+			Target::Prefix("user:".to_string()),
+			Target::Flush,
+		] {
+			assert_eq!(
+				Target::decode(&target.encode("origin")),
+				Some(("origin", target))
+			);
+		}
+
+		assert_eq!(Target::decode("origin unknown key"), None);
+	}
+
+	#[tokio::test]
+	async fn test_synced_tiered_driver() {
+		let mut writer = Synced::new(Config::default()).await.unwrap();
+		let mut reader = Synced::new(Config::default()).await.unwrap();
+
+		writer.put("foo", &"bar", None).await.unwrap();
+		assert_eq!(
+			reader.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+		reader
+			.get_or_put("foo", "bar".to_string(), None)
+			.await
+			.unwrap();
+		assert!(reader.tiered.l1().has("foo").await.unwrap());
+
+		writer.put("foo", &"baz", None).await.unwrap();
+		tokio::time::sleep(Duration::from_millis(100)).await;
+
+		assert!(!reader.tiered.l1().has("foo").await.unwrap());
+		assert_eq!(
+			reader.get::<String>("foo").await.unwrap(),
+			Some("baz".to_string())
+		);
+
+		writer.flush().await.unwrap();
+	}
+}
@@ -0,0 +1,546 @@
+#[cfg(feature = "format")]
+use super::{format::Raw, Format};
+use super::{Driver, EntryMeta, GetOutcome, Overflow, PoolStatus, Ttl};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	fmt,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+	time::{Duration, SystemTime},
+};
+
+pub struct Config<A: Driver, B: Driver> {
+	/// The config for the fast (usually in-process) driver that's checked first.
+	pub l1: A::Config,
+	/// The config for the (usually remote) driver that holds every entry.
+	pub l2: B::Config,
+	/// The longest an entry is kept in L1, so it eventually picks up changes made to L2 by other processes.
+	pub l1_expiry: Option<Duration>,
+}
+
+config_builder! {
+	<A: Driver, B: Driver>
+	l1: A::Config,
+	l2: B::Config,
+	l1_expiry: Option<Duration>,
+}
+
+impl<A: Driver, B: Driver> fmt::Debug for Config<A, B>
+where
+	A::Config: fmt::Debug,
+	B::Config: fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Config")
+			.field("l1", &self.l1)
+			.field("l2", &self.l2)
+			.field("l1_expiry", &self.l1_expiry)
+			.finish()
+	}
+}
+
+impl<A: Driver, B: Driver> Default for Config<A, B>
+where
+	A::Config: Default,
+	B::Config: Default,
+{
+	fn default() -> Self {
+		Self {
+			l1: A::Config::default(),
+			l2: B::Config::default(),
+			l1_expiry: None,
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+/// A driver that layers a fast cache (L1) in front of a slower one (L2).
+///
+/// Reads check L1 first and fall back to L2, while writes and removals go to both. Entries found in L2 are copied
+/// into L1, through a clone of the L1 driver (so its clones need to share their entries, like this crate's drivers
+/// do). Reads copy the stored bytes as they are, so they only do it when both drivers serialize values with the same
+/// [`Format`](super::Format), while [`get_or_put`](Driver::get_or_put) always does. Counters are only kept in L2, so
+/// incrementing a key removes it from L1.
+pub struct TieredDriver<A: Driver, B: Driver> {
+	l1: A,
+	l2: B,
+	l1_expiry: Option<Duration>,
+	/// How many times entries were evicted from L1 because they changed somewhere else, so copies of entries read from
+	/// L2 in the meantime (which may be stale) aren't kept.
+	invalidations: Arc<AtomicU64>,
+}
+
+impl<A: Driver, B: Driver> TieredDriver<A, B> {
+	/// The fast driver that's checked first.
+	#[cfg(feature = "redis")]
+	pub(super) const fn l1(&self) -> &A {
+		&self.l1
+	}
+
+	/// The counter to bump before evicting entries from L1 that changed somewhere else.
+	#[cfg(feature = "redis")]
+	pub(super) fn invalidations(&self) -> Arc<AtomicU64> {
+		self.invalidations.clone()
+	}
+
+	/// Copy an entry found in L2 into L1, without outliving it in L2.
+	///
+	/// `invalidations` is the value of the counter from before the entry was read, so the copy is removed again if an
+	/// invalidation (which may have been for this entry) arrived since.
+	async fn copy_to_l1<T: Serialize + Sync>(
+		&self,
+		key: &str,
+		value: &T,
+		invalidations: u64,
+	) -> Result<(), Error<A::Error, B::Error>>
+	where
+		A: Clone,
+	{
+		let expiry = match self.l2.ttl(key).await.map_err(Error::L2)? {
+			Some(Ttl::Forever) => None,
+			Some(Ttl::In(remaining)) => Some(remaining),
+			None => return Ok(()),
+		};
+
+		let mut l1 = self.l1.clone();
+		l1.put(key, value, self.l1_expiry(expiry))
+			.await
+			.map_err(Error::L1)?;
+
+		// Checked after writing, since the eviction may have run just before the copy landed.
+		if self.invalidations.load(Ordering::SeqCst) != invalidations {
+			l1.forget(key).await.map_err(Error::L1)?;
+		}
+
+		Ok(())
+	}
+
+	/// The expiry for a copy of an entry kept in L1.
+	fn l1_expiry(&self, expiry: Option<Duration>) -> Option<Duration> {
+		match (expiry, self.l1_expiry) {
+			(Some(expiry), Some(l1_expiry)) => Some(expiry.min(l1_expiry)),
+			(expiry, l1_expiry) => expiry.or(l1_expiry),
+		}
+	}
+}
+
+impl<A: Driver + Clone, B: Driver> Driver for TieredDriver<A, B> {
+	type Error = Error<A::Error, B::Error>;
+	type Config = Config<A, B>;
+
+	async fn new(config: Self::Config) -> Result<Self, Self::Error> {
+		Ok(Self {
+			l1: A::new(config.l1).await.map_err(Error::L1)?,
+			l2: B::new(config.l2).await.map_err(Error::L2)?,
+			l1_expiry: config.l1_expiry,
+			invalidations: Arc::default(),
+		})
+	}
+
+	fn prefix(&self) -> &str {
+		self.l2.prefix()
+	}
+
+	fn pool_status(&self) -> Option<PoolStatus> {
+		self.l2.pool_status()
+	}
+
+	fn is_unavailable(error: &Self::Error) -> bool {
+		match error {
+			Error::L1(error) => A::is_unavailable(error),
+			Error::L2(error) => B::is_unavailable(error),
+			Error::Overflow(_) => false,
+		}
+	}
+
+	#[cfg(feature = "format")]
+	fn format(&self) -> Option<Format> {
+		self.l1
+			.format()
+			.filter(|format| self.l2.format() == Some(*format))
+	}
+
+	async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Self::Error> {
+		if let Some(value) = self.l1.get(key).await.map_err(Error::L1)? {
+			return Ok(Some(value));
+		}
+
+		#[cfg(feature = "format")]
+		if let Some(format) = self.format() {
+			let invalidations = self.invalidations.load(Ordering::SeqCst);
+			let Some(data) = self.l2.get::<Raw>(key).await.map_err(Error::L2)? else {
+				return Ok(None);
+			};
+			self.copy_to_l1(key, &data, invalidations).await?;
+
+			if let Ok(value) = format.deserialize(&data.0) {
+				return Ok(Some(value));
+			}
+
+			// Values that can't be deserialized are left to L2, which knows how to deal with them.
+			self.l1.clone().forget(key).await.map_err(Error::L1)?;
+		}
+
+		self.l2.get(key).await.map_err(Error::L2)
+	}
+
+	async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, Self::Error> {
+		match self.l1.try_get(key).await.map_err(Error::L1)? {
+			GetOutcome::Miss => {},
+			outcome => return Ok(outcome),
+		}
+
+		#[cfg(feature = "format")]
+		if let Some(format) = self.format() {
+			let invalidations = self.invalidations.load(Ordering::SeqCst);
+			let Some(data) = self.l2.get::<Raw>(key).await.map_err(Error::L2)? else {
+				return Ok(GetOutcome::Miss);
+			};
+			self.copy_to_l1(key, &data, invalidations).await?;
+
+			if let Ok(value) = format.deserialize(&data.0) {
+				return Ok(GetOutcome::Hit(value));
+			}
+
+			// Values that can't be deserialized are left to L2, which knows how to deal with them.
+			self.l1.clone().forget(key).await.map_err(Error::L1)?;
+		}
+
+		self.l2.try_get(key).await.map_err(Error::L2)
+	}
+
+	async fn has(&self, key: &str) -> Result<bool, Self::Error> {
+		Ok(self.l1.has(key).await.map_err(Error::L1)?
+			|| self.l2.has(key).await.map_err(Error::L2)?)
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<Ttl>, Self::Error> {
+		self.l2.ttl(key).await.map_err(Error::L2)
+	}
+
+	async fn metadata(&self, key: &str) -> Result<Option<EntryMeta>, Self::Error> {
+		self.l2.metadata(key).await.map_err(Error::L2)
+	}
+
+	async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, Self::Error> {
+		// L1 only holds a subset of L2's entries, so L2 has every key.
+		self.l2.keys(prefix).await.map_err(Error::L2)
+	}
+
+	async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<(), Self::Error> {
+		// Write to L2 first, so a failure never leaves a value only L1 knows about.
+		self.l2.put(key, value, expiry).await.map_err(Error::L2)?;
+
+		let expiry = self.l1_expiry(expiry);
+		self.l1.put(key, value, expiry).await.map_err(Error::L1)
+	}
+
+	async fn put_until<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		deadline: SystemTime,
+	) -> Result<(), Self::Error> {
+		self.l2
+			.put_until(key, value, deadline)
+			.await
+			.map_err(Error::L2)?;
+
+		// Only keep the exact deadline in L1 if it comes before the L1 expiry would.
+		match self.l1_expiry {
+			Some(l1_expiry) if SystemTime::now() + l1_expiry < deadline => {
+				self.l1.put(key, value, Some(l1_expiry)).await
+			},
+			_ => self.l1.put_until(key, value, deadline).await,
+		}
+		.map_err(Error::L1)
+	}
+
+	async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+		expiry: Option<Duration>,
+	) -> Result<T, Self::Error> {
+		if let Some(cached) = self.l1.get(key).await.map_err(Error::L1)? {
+			return Ok(cached);
+		}
+
+		let invalidations = self.invalidations.load(Ordering::SeqCst);
+		let value = self
+			.l2
+			.get_or_put(key, value, expiry)
+			.await
+			.map_err(Error::L2)?;
+		self.copy_to_l1(key, &value, invalidations).await?;
+
+		Ok(value)
+	}
+
+	async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<Option<T>, Self::Error> {
+		// L1 may hold a stale copy, so the previous value comes from L2.
+		let previous = self
+			.l2
+			.replace(key, value, expiry)
+			.await
+			.map_err(Error::L2)?;
+
+		let expiry = self.l1_expiry(expiry);
+		self.l1.put(key, value, expiry).await.map_err(Error::L1)?;
+
+		Ok(previous)
+	}
+
+	async fn add<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		// L2 decides whether the value is stored, and L1 only gets a copy if it was.
+		if !self.l2.add(key, value, expiry).await.map_err(Error::L2)? {
+			return Ok(false);
+		}
+
+		let expiry = self.l1_expiry(expiry);
+		self.l1.put(key, value, expiry).await.map_err(Error::L1)?;
+
+		Ok(true)
+	}
+
+	async fn compare_and_swap<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+		new: &T,
+		expiry: Option<Duration>,
+	) -> Result<bool, Self::Error> {
+		if !self
+			.l2
+			.compare_and_swap(key, expected, new, expiry)
+			.await
+			.map_err(Error::L2)?
+		{
+			// L2 holds something else, so whatever L1 has cached is likely stale.
+			self.l1.forget(key).await.map_err(Error::L1)?;
+
+			return Ok(false);
+		}
+
+		let expiry = self.l1_expiry(expiry);
+		self.l1.put(key, new, expiry).await.map_err(Error::L1)?;
+
+		Ok(true)
+	}
+
+	async fn increment(
+		&mut self,
+		key: &str,
+		by: i64,
+		expiry: Option<Duration>,
+	) -> Result<i64, Self::Error> {
+		let value = self
+			.l2
+			.increment(key, by, expiry)
+			.await
+			.map_err(Error::L2)?;
+		self.l1.forget(key).await.map_err(Error::L1)?;
+
+		Ok(value)
+	}
+
+	async fn touch(&mut self, key: &str, expiry: Option<Duration>) -> Result<bool, Self::Error> {
+		let touched = self.l2.touch(key, expiry).await.map_err(Error::L2)?;
+
+		let expiry = self.l1_expiry(expiry);
+		self.l1.touch(key, expiry).await.map_err(Error::L1)?;
+
+		Ok(touched)
+	}
+
+	async fn forget(&mut self, key: &str) -> Result<bool, Self::Error> {
+		let forgotten = self.l2.forget(key).await.map_err(Error::L2)?;
+
+		Ok(self.l1.forget(key).await.map_err(Error::L1)? || forgotten)
+	}
+
+	async fn forget_many(&mut self, keys: &[&str]) -> Result<(), Self::Error> {
+		self.l2.forget_many(keys).await.map_err(Error::L2)?;
+		self.l1.forget_many(keys).await.map_err(Error::L1)
+	}
+
+	async fn forget_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+		self.l2.forget_prefix(prefix).await.map_err(Error::L2)?;
+		self.l1.forget_prefix(prefix).await.map_err(Error::L1)
+	}
+
+	async fn forget_if<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+	) -> Result<bool, Self::Error> {
+		if !self.l2.forget_if(key, expected).await.map_err(Error::L2)? {
+			return Ok(false);
+		}
+
+		self.l1.forget(key).await.map_err(Error::L1)?;
+
+		Ok(true)
+	}
+
+	async fn purge_expired(&mut self) -> Result<usize, Self::Error> {
+		let removed = self.l2.purge_expired().await.map_err(Error::L2)?;
+
+		Ok(removed + self.l1.purge_expired().await.map_err(Error::L1)?)
+	}
+
+	async fn flush(&mut self) -> Result<(), Self::Error> {
+		self.l2.flush().await.map_err(Error::L2)?;
+		self.l1.flush().await.map_err(Error::L1)
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error<A, B> {
+	#[error("L1 cache error: {0}")]
+	L1(A),
+	#[error("L2 cache error: {0}")]
+	L2(B),
+	#[error(transparent)]
+	Overflow(#[from] Overflow),
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+	use super::*;
+	use crate::drivers::MemoryDriver;
+
+	type Tiered = TieredDriver<MemoryDriver, MemoryDriver>;
+
+	#[tokio::test]
+	async fn test_tiered_driver() {
+		let mut driver = Tiered::new(Config::default()).await.unwrap();
+
+		driver.put("foo", &"bar", None).await.unwrap();
+		assert!(driver.l1.has("foo").await.unwrap());
+		assert!(driver.l2.has("foo").await.unwrap());
+
+		driver.l1.forget("foo").await.unwrap();
+		assert_eq!(
+			driver.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+
+		assert!(driver.forget("foo").await.unwrap());
+		assert!(!driver.has("foo").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_tiered_get_or_put_populates_l1() {
+		let mut driver = Tiered::new(Config {
+			l1_expiry: Some(Duration::from_secs(5)),
+			..Default::default()
+		})
+		.await
+		.unwrap();
+
+		driver
+			.l2
+			.put("foo", &"bar", Some(Duration::from_secs(30)))
+			.await
+			.unwrap();
+
+		assert_eq!(
+			driver
+				.get_or_put("foo", "baz".to_string(), None)
+				.await
+				.unwrap(),
+			"bar"
+		);
+		assert_eq!(
+			driver.l1.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+
+		let Some(Ttl::In(remaining)) = driver.l1.ttl("foo").await.unwrap() else {
+			panic!("expected the L1 copy to expire");
+		};
+		assert!(remaining <= Duration::from_secs(5));
+	}
+
+	#[tokio::test]
+	async fn test_tiered_get_populates_l1() {
+		let driver = Tiered::new(Config::default()).await.unwrap();
+
+		let mut l2 = driver.l2.clone();
+		l2.put("foo", &"bar", Some(Duration::from_secs(30)))
+			.await
+			.unwrap();
+
+		assert_eq!(
+			driver.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+		assert_eq!(
+			driver.l1.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+
+		// Later reads are served from L1, even once L2 loses the entry.
+		l2.forget("foo").await.unwrap();
+		assert_eq!(
+			driver.get::<String>("foo").await.unwrap(),
+			Some("bar".to_string())
+		);
+		assert!(matches!(
+			driver.l1.ttl("foo").await.unwrap(),
+			Some(Ttl::In(_))
+		));
+	}
+
+	#[tokio::test]
+	async fn test_tiered_drops_copies_invalidated_during_read() {
+		let driver = Tiered::new(Config::default()).await.unwrap();
+		driver.l2.clone().put("foo", &"bar", None).await.unwrap();
+
+		// An invalidation arriving while the entry was being read from L2 means it may be stale.
+		let invalidations = driver.invalidations.load(Ordering::SeqCst);
+		driver.invalidations.fetch_add(1, Ordering::SeqCst);
+		driver
+			.copy_to_l1("foo", &"bar", invalidations)
+			.await
+			.unwrap();
+		assert!(!driver.l1.has("foo").await.unwrap());
+
+		let invalidations = driver.invalidations.load(Ordering::SeqCst);
+		driver
+			.copy_to_l1("foo", &"bar", invalidations)
+			.await
+			.unwrap();
+		assert!(driver.l1.has("foo").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_tiered_increment_invalidates_l1() {
+		let mut driver = Tiered::new(Config::default()).await.unwrap();
+
+		driver.put("hits", &1_i64, None).await.unwrap();
+		assert_eq!(driver.increment("hits", 2, None).await.unwrap(), 3);
+
+		assert!(!driver.l1.has("hits").await.unwrap());
+		assert_eq!(driver.get::<i64>("hits").await.unwrap(), Some(3));
+	}
+}
@@ -0,0 +1,71 @@
+use std::time::{Duration, SystemTime};
+
+/// How long an item should be kept in the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiry {
+	/// Expire the item after the given duration.
+	In(Duration),
+	/// Expire the item at the given point in time.
+	At(SystemTime),
+	/// Never expire the item.
+	Forever,
+	/// Use the cache's default expiry, or keep the item forever if there isn't one.
+	Default,
+}
+
+impl Expiry {
+	/// Resolve into the duration the item should be kept for, where `None` means forever.
+	/// Deadlines that have already passed resolve to a zero duration.
+	pub(crate) fn resolve(self, default: Option<Duration>) -> Option<Duration> {
+		match self {
+			Self::In(duration) => Some(duration),
+			Self::At(deadline) => Some(
+				deadline
+					.duration_since(SystemTime::now())
+					.unwrap_or(Duration::ZERO),
+			),
+			Self::Forever => None,
+			Self::Default => default,
+		}
+	}
+}
+
+impl From<Duration> for Expiry {
+	fn from(duration: Duration) -> Self {
+		Self::In(duration)
+	}
+}
+
+impl From<SystemTime> for Expiry {
+	fn from(deadline: SystemTime) -> Self {
+		Self::At(deadline)
+	}
+}
+
+impl From<Option<Duration>> for Expiry {
+	fn from(duration: Option<Duration>) -> Self {
+		duration.map_or(Self::Forever, Self::In)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_expiry_resolution() {
+		let default = Some(Duration::from_secs(30));
+
+		assert_eq!(
+			Expiry::from(Duration::from_secs(5)).resolve(default),
+			Some(Duration::from_secs(5))
+		);
+		assert_eq!(Expiry::Forever.resolve(default), None);
+		assert_eq!(Expiry::Default.resolve(default), default);
+		assert_eq!(Expiry::Default.resolve(None), None);
+		assert_eq!(
+			Expiry::At(SystemTime::now() - Duration::from_secs(1)).resolve(default),
+			Some(Duration::ZERO)
+		);
+	}
+}
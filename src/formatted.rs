@@ -0,0 +1,17 @@
+use crate::drivers::format;
+
+/// The error returned by [`Cache::put_with_format`](crate::Cache::put_with_format) and
+/// [`Cache::get_with_format`](crate::Cache::get_with_format), saying whether the cache or the format failed.
+#[derive(Debug, thiserror::Error)]
+pub enum FormatError<C> {
+	/// The driver failed to store or retrieve the item.
+	#[error(transparent)]
+	Cache(C),
+	/// The item couldn't be serialized (or deserialized) with its format.
+	#[error(transparent)]
+	Serialization(#[from] format::Error),
+	/// The item wasn't stored with [`Cache::put_with_format`](crate::Cache::put_with_format), or was stored with a
+	/// format that isn't enabled.
+	#[error("unknown format marker: {0:?}")]
+	UnknownFormat(Option<u8>),
+}
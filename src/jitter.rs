@@ -0,0 +1,28 @@
+use std::{collections::hash_map::RandomState, hash::BuildHasher, time::Duration};
+
+/// A source of the random offsets [`Cache::put_jittered`](crate::Cache::put_jittered) adds to expiries.
+///
+/// Set a deterministic one with [`Cache::with_jitter_source`](crate::Cache::with_jitter_source) to make tests
+/// repeatable.
+pub trait JitterSource: Send + Sync {
+	/// A random duration in `[0, max)`, or zero if `max` is zero.
+	fn jitter(&self, max: Duration) -> Duration;
+}
+
+/// The default jitter source, which picks offsets uniformly at random.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomJitter;
+
+impl JitterSource for RandomJitter {
+	fn jitter(&self, max: Duration) -> Duration {
+		let max = max.as_nanos();
+		if max == 0 {
+			return Duration::ZERO;
+		}
+
+		// Every `RandomState` is seeded differently, so hashing anything with a new one gives a random number.
+		let random = u128::from(RandomState::new().hash_one(0_u8));
+
+		Duration::from_nanos(u64::try_from(random % max).unwrap_or(u64::MAX))
+	}
+}
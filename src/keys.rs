@@ -0,0 +1,41 @@
+use std::borrow::Cow;
+
+/// Transforms the keys passed to a [`Cache`](crate::Cache) before they reach the driver.
+///
+/// Useful for enforcing key conventions (hashing, tenant prefixes, escaping reserved characters) in a single place.
+pub trait KeyEncoder: Send + Sync {
+	/// Encode a key into the form it should be stored under.
+	fn encode<'a>(&self, key: &'a str) -> Cow<'a, str>;
+}
+
+/// The default key encoder, which leaves keys untouched.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultKeyEncoder;
+
+impl KeyEncoder for DefaultKeyEncoder {
+	fn encode<'a>(&self, key: &'a str) -> Cow<'a, str> {
+		Cow::Borrowed(key)
+	}
+}
+
+/// A normalization applied to keys before they're encoded, so that equivalent keys map to the same entry.
+///
+/// Normalizing keys changes their identity: keys that were previously distinct (like `Foo` and `foo`) will collide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyNormalization {
+	/// Convert keys to lowercase.
+	Lowercase,
+	/// Remove leading and trailing whitespace from keys.
+	Trim,
+}
+
+impl KeyNormalization {
+	pub(crate) fn apply(self, key: Cow<'_, str>) -> Cow<'_, str> {
+		match (self, key) {
+			(Self::Trim, Cow::Borrowed(key)) => Cow::Borrowed(key.trim()),
+			(Self::Trim, Cow::Owned(key)) => Cow::Owned(key.trim().to_string()),
+			(Self::Lowercase, key) if !key.chars().any(char::is_uppercase) => key,
+			(Self::Lowercase, key) => Cow::Owned(key.to_lowercase()),
+		}
+	}
+}
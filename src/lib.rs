@@ -1,16 +1,103 @@
 #![warn(clippy::all, clippy::pedantic, clippy::nursery)]
+// `Duration::from_mins` and `Duration::from_hours` need Rust 1.91, so durations are spelled out in seconds instead.
+#![allow(unknown_lints, clippy::duration_suboptimal_units)]
 //! An expressive interface for interacting with a Cache.
 //! Inspired by [Laravel's Cache](https://laravel.com/docs/cache) facade.
 
-use drivers::Driver;
+#[cfg(feature = "format")]
+use drivers::{format::Raw, Format};
+use drivers::{Driver, EntryMeta, FlushWhere, GetOutcome, Overflow, PoolStatus, ScopedDriver, Ttl};
+use futures_util::{stream, StreamExt};
 use serde::{de::DeserializeOwned, Serialize};
-use std::time::Duration;
+use single_flight::SingleFlight;
+use stats::Metrics;
+use std::{
+	borrow::Cow,
+	collections::HashMap,
+	fmt,
+	future::Future,
+	sync::Arc,
+	time::{Duration, SystemTime},
+};
 
+#[cfg(feature = "stream")]
+pub use blob::StreamError;
+pub use counter::Counter;
+pub use expiry::Expiry;
+#[cfg(feature = "format")]
+pub use formatted::FormatError;
+pub use jitter::{JitterSource, RandomJitter};
+pub use keys::{DefaultKeyEncoder, KeyEncoder, KeyNormalization};
+pub use lock::Lock;
+pub use remember::RememberError;
+pub use stats::CacheStats;
+pub use tags::TaggedCache;
+pub use warm::WarmSummary;
+
+#[cfg(feature = "stream")]
+mod blob;
+mod counter;
 pub mod drivers;
+mod expiry;
+#[cfg(feature = "format")]
+mod formatted;
+mod jitter;
+mod keys;
+mod lock;
+mod remember;
+mod single_flight;
+mod stats;
+mod tags;
+mod trace;
+mod warm;
+
+/// The reserved namespace the copies [`Cache::remember_with_previous`] keeps of its values are stored under, in front
+/// of their item's (stored) key. Only `remember_with_previous` reads and writes it.
+const PREVIOUS_NAMESPACE: &str = "__previous:";
+
+/// An entry read by [`Cache::dump`]: its key, its stored value, and how long it has left (or `None` if it never
+/// expires).
+#[cfg(feature = "format")]
+pub type DumpedEntry = (String, Vec<u8>, Option<Duration>);
+
+/// How many entries [`Cache::scan`] and [`Cache::dump`] read at once. `DynamoDB` can't fetch more than 100 items in a
+/// single request.
+#[cfg(any(feature = "stream", feature = "format"))]
+const SCAN_BATCH_SIZE: usize = 100;
 
 /// Unified cache interface.
+///
+/// Cloning a cache clones its driver. Every driver shares its store (or connection details) between clones, so a clone
+/// can be handed to each task instead of wrapping the cache in a lock.
+#[derive(Clone)]
 pub struct Cache<D: Driver> {
 	driver: D,
+	default_expiry: Option<Duration>,
+	key_encoder: Arc<dyn KeyEncoder>,
+	jitter_source: Arc<dyn JitterSource>,
+	key_normalization: Vec<KeyNormalization>,
+	version_prefix: Option<String>,
+	namespace: String,
+	single_flight: Option<SingleFlight>,
+	metrics: Option<Arc<Metrics>>,
+	stale_window: Duration,
+	strict_get_many: bool,
+}
+
+impl<D: Driver + fmt::Debug> fmt::Debug for Cache<D> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Cache")
+			.field("driver", &self.driver)
+			.field("default_expiry", &self.default_expiry)
+			.field("key_normalization", &self.key_normalization)
+			.field("version_prefix", &self.version_prefix)
+			.field("namespace", &self.namespace)
+			.field("single_flight", &self.single_flight.is_some())
+			.field("metrics", &self.metrics.is_some())
+			.field("stale_window", &self.stale_window)
+			.field("strict_get_many", &self.strict_get_many)
+			.finish_non_exhaustive()
+	}
 }
 
 impl<D: Driver> Cache<D> {
@@ -21,17 +108,293 @@ impl<D: Driver> Cache<D> {
 	/// Returns an error if the driver fails to initialize.
 	pub async fn new(config: D::Config) -> Result<Self, D::Error> {
 		Ok(Self {
+			default_expiry: None,
 			driver: D::new(config).await?,
+			version_prefix: None,
+			namespace: String::new(),
+			single_flight: None,
+			metrics: None,
+			stale_window: Duration::from_secs(60 * 60),
+			strict_get_many: false,
+			key_normalization: Vec::new(),
+			key_encoder: Arc::new(DefaultKeyEncoder),
+			jitter_source: Arc::new(RandomJitter),
 		})
 	}
 
+	/// Set the encoder applied to every key before it's passed to the driver.
+	#[must_use]
+	pub fn with_key_encoder(mut self, encoder: impl KeyEncoder + 'static) -> Self {
+		self.key_encoder = Arc::new(encoder);
+
+		self
+	}
+
+	/// Set the source of the random offsets [`Cache::put_jittered`] adds to expiries, for example to make them
+	/// deterministic in tests.
+	#[must_use]
+	pub fn with_jitter_source(mut self, source: impl JitterSource + 'static) -> Self {
+		self.jitter_source = Arc::new(source);
+
+		self
+	}
+
+	/// Normalize every key before it's encoded and passed to the driver. Can be called multiple times to apply
+	/// several normalizations, in order.
+	///
+	/// Note that this changes key identity, so previously-distinct keys may now refer to the same entry.
+	#[must_use]
+	pub fn with_key_normalization(mut self, normalization: KeyNormalization) -> Self {
+		self.key_normalization.push(normalization);
+
+		self
+	}
+
+	/// Prepend a version to every key, so each release of an application gets a fresh namespace and never reads
+	/// values written by an incompatible previous version (for example, `concat!("v", env!("CARGO_PKG_VERSION"), ":")`).
+	/// Entries written under other versions aren't removed, and are left to expire on their own.
+	#[must_use]
+	pub fn with_version_prefix(mut self, version: impl Into<String>) -> Self {
+		self.version_prefix = Some(version.into());
+
+		self
+	}
+
+	/// Coalesce concurrent misses for the same key in [`Cache::remember_with`], so the loader only runs once and the
+	/// other callers read the value it stored. Applies across clones of the cache, but not across processes.
+	#[must_use]
+	pub fn with_single_flight(mut self) -> Self {
+		self.single_flight = Some(SingleFlight::default());
+
+		self
+	}
+
+	/// Count hits, misses, writes and deletes, so they can be read back with [`Cache::stats`].
+	/// The counters are shared between clones of the cache (and views created with [`Cache::with_prefix`]).
+	#[must_use]
+	pub fn with_metrics(mut self) -> Self {
+		self.metrics = Some(Arc::default());
+
+		self
+	}
+
+	/// Set how long [`Cache::remember_with_previous`] keeps a copy of a value after it expires, so the loader can
+	/// still build on it. Defaults to an hour.
+	#[must_use]
+	pub const fn with_stale_window(mut self, window: Duration) -> Self {
+		self.stale_window = window;
+
+		self
+	}
+
+	/// Make [`Cache::get_many`] fail as a whole when one of the items can't be deserialized, instead of treating that
+	/// item as a miss.
+	#[must_use]
+	pub const fn with_strict_get_many(mut self) -> Self {
+		self.strict_get_many = true;
+
+		self
+	}
+
+	/// Set the expiry used for items stored with [`Expiry::Default`].
+	#[must_use]
+	pub const fn with_default_expiry(mut self, expiry: Duration) -> Self {
+		self.default_expiry = Some(expiry);
+
+		self
+	}
+
+	/// The prefix prepended to every key stored through this cache.
+	pub fn prefix(&self) -> &str {
+		self.driver.prefix()
+	}
+
+	/// The status of the driver's connection pool, or `None` if it doesn't use one.
+	pub fn pool_status(&self) -> Option<PoolStatus> {
+		self.driver.pool_status()
+	}
+
+	/// A snapshot of the hits, misses, writes and deletes counted since the cache was created.
+	/// Only caches created with [`Cache::with_metrics`] count them, so this is all zeroes otherwise.
+	pub fn stats(&self) -> CacheStats {
+		self.metrics
+			.as_ref()
+			.map_or_else(CacheStats::default, |metrics| metrics.snapshot())
+	}
+
+	/// Create a view of the cache that prepends `prefix` to every key, on top of the existing prefix.
+	/// The view reuses the underlying driver (and its connection), so it borrows the cache mutably.
+	pub fn with_prefix(&mut self, prefix: &str) -> Cache<ScopedDriver<'_, D>> {
+		Cache {
+			default_expiry: self.default_expiry,
+			key_encoder: self.key_encoder.clone(),
+			jitter_source: self.jitter_source.clone(),
+			key_normalization: self.key_normalization.clone(),
+			version_prefix: self.version_prefix.clone(),
+			namespace: self.namespace.clone(),
+			single_flight: self.single_flight.clone(),
+			metrics: self.metrics.clone(),
+			stale_window: self.stale_window,
+			strict_get_many: self.strict_get_many,
+			driver: ScopedDriver::new(&mut self.driver, prefix),
+		}
+	}
+
 	/// Retrieve an item from the cache.
 	///
 	/// # Errors
 	///
 	/// Returns an error if the driver fails to retrieve the item.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key, hit = tracing::field::Empty)))]
 	pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, D::Error> {
-		self.driver.get(key).await
+		let value = self.driver.get(&self.key(key)).await?;
+		trace::record_hit(value.is_some());
+		self.record(|metrics| metrics.lookup(value.is_some()));
+
+		Ok(value)
+	}
+
+	/// Retrieve an item from the cache, or `default` if it doesn't exist. Unlike [`Cache::remember`], nothing is stored.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve the item.
+	pub async fn get_or<T: DeserializeOwned>(&self, key: &str, default: T) -> Result<T, D::Error> {
+		Ok(self.get(key).await?.unwrap_or(default))
+	}
+
+	/// Retrieve an item from the cache, or the value computed by `default` if it doesn't exist.
+	/// The default is only computed on a miss, and (unlike [`Cache::remember_with`]) isn't stored.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve the item.
+	pub async fn get_or_else<T: DeserializeOwned, F: FnOnce() -> T>(
+		&self,
+		key: &str,
+		default: F,
+	) -> Result<T, D::Error> {
+		Ok(self.get(key).await?.unwrap_or_else(default))
+	}
+
+	/// Retrieve an item from the cache, or the type's default value if it doesn't exist. Nothing is stored.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve the item.
+	pub async fn get_or_default<T: DeserializeOwned + Default>(
+		&self,
+		key: &str,
+	) -> Result<T, D::Error> {
+		Ok(self.get(key).await?.unwrap_or_default())
+	}
+
+	/// Retrieve many items from the cache at once, in the same order as `keys`.
+	/// Drivers that support it (like Redis and `DynamoDB`) fetch them in a single round trip.
+	///
+	/// Items that can't be deserialized (like keys holding data that wasn't stored through the cache) are returned as
+	/// `None` instead of failing the whole batch, unless the cache was created with [`Cache::with_strict_get_many`].
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve the items.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(keys = ?keys, hits = tracing::field::Empty)))]
+	pub async fn get_many<T: DeserializeOwned + Send>(
+		&self,
+		keys: &[&str],
+	) -> Result<Vec<Option<T>>, D::Error> {
+		let keys = keys.iter().map(|key| self.key(key)).collect::<Vec<_>>();
+		let keys = keys.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+
+		let values = match self.driver.get_many(&keys).await {
+			Ok(values) => values,
+			// A single bad value fails the whole batch, so the items are read one at a time to tell which ones it was.
+			Err(error) if !self.strict_get_many && !D::is_unavailable(&error) => {
+				let mut values = Vec::with_capacity(keys.len());
+				for key in &keys {
+					values.push(match self.driver.try_get(key).await? {
+						GetOutcome::Hit(value) => Some(value),
+						GetOutcome::Miss | GetOutcome::Corrupt(_) => None,
+					});
+				}
+
+				values
+			},
+			Err(error) => return Err(error),
+		};
+		trace::record_hits(&values);
+		self.record(|metrics| {
+			for value in &values {
+				metrics.lookup(value.is_some());
+			}
+		});
+
+		Ok(values)
+	}
+
+	/// Retrieve many items from the cache at once, keyed by the requested keys. Unlike [`Cache::get_many`], missing
+	/// items are left out instead of being `None`, which is handier when the order doesn't matter.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve the items.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(keys = ?keys)))]
+	pub async fn get_map<T: DeserializeOwned + Send>(
+		&self,
+		keys: &[&str],
+	) -> Result<HashMap<String, T>, D::Error> {
+		let values = self.get_many(keys).await?;
+
+		Ok(keys
+			.iter()
+			.zip(values)
+			.filter_map(|(key, value)| Some(((*key).to_string(), value?)))
+			.collect())
+	}
+
+	/// Retrieve an item from the cache, distinguishing a miss from a value that couldn't be deserialized.
+	/// This lets callers deal with corrupt entries (for example, by logging and evicting them) instead of erroring.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve the item.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key, hit = tracing::field::Empty)))]
+	pub async fn try_get<T: DeserializeOwned>(&self, key: &str) -> Result<GetOutcome<T>, D::Error> {
+		let outcome = self.driver.try_get(&self.key(key)).await?;
+		let hit = matches!(outcome, GetOutcome::Hit(_));
+		trace::record_hit(hit);
+		self.record(|metrics| metrics.lookup(hit));
+
+		Ok(outcome)
+	}
+
+	/// Retrieve an item from the cache, falling back to the key it was previously stored under.
+	/// Items found under the old key are moved to the new one, so key schemes can change without a cold cache.
+	///
+	/// Note that the first read of each migrated item costs an extra read, write and delete.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve, store or remove the item.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key, old_key = old_key)))]
+	pub async fn get_migrating<T: Serialize + DeserializeOwned + Sync>(
+		&mut self,
+		key: &str,
+		old_key: &str,
+		expiry: impl Into<Expiry>,
+	) -> Result<Option<T>, D::Error> {
+		if let Some(value) = self.get(key).await? {
+			return Ok(Some(value));
+		}
+
+		let Some(value) = self.get::<T>(old_key).await? else {
+			return Ok(None);
+		};
+
+		self.put(key, &value, expiry).await?;
+		self.forget(old_key).await?;
+
+		Ok(Some(value))
 	}
 
 	/// Check if an item exists in the cache.
@@ -39,8 +402,312 @@ impl<D: Driver> Cache<D> {
 	/// # Errors
 	///
 	/// Returns an error if the driver fails to check if the item exists.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key, hit = tracing::field::Empty)))]
 	pub async fn has(&self, key: &str) -> Result<bool, D::Error> {
-		self.driver.has(key).await
+		let exists = self.driver.has(&self.key(key)).await?;
+		trace::record_hit(exists);
+		self.record(|metrics| metrics.lookup(exists));
+
+		Ok(exists)
+	}
+
+	/// Retrieve how long an item has left before it expires, or `None` if it doesn't exist.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve the item's expiry.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
+	pub async fn ttl(&self, key: &str) -> Result<Option<Ttl>, D::Error> {
+		self.driver.ttl(&self.key(key)).await
+	}
+
+	/// Retrieve the metadata for an item in the cache, if the driver tracks it.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve the metadata.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
+	pub async fn metadata(&self, key: &str) -> Result<Option<EntryMeta>, D::Error> {
+		self.driver.metadata(&self.key(key)).await
+	}
+
+	/// List the keys currently in the cache (optionally only the ones starting with `prefix`), in no particular order.
+	/// Meant for debugging and admin tooling, since most drivers have to look at every entry to find them.
+	///
+	/// Keys are returned as they're stored: normalized and encoded, but without the version prefix.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to list its keys, or can't list them at all (like Memcached).
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(prefix = ?prefix)))]
+	pub async fn keys(&self, prefix: Option<&str>) -> Result<Vec<String>, D::Error> {
+		// Without a prefix, only list the keys written under the current version and namespace.
+		let key_prefix = self.key_prefix();
+		let prefix = prefix.map_or_else(
+			|| key_prefix.as_deref().map(Cow::Borrowed),
+			|prefix| Some(self.key(prefix)),
+		);
+		let keys = self.driver.keys(prefix.as_deref()).await?;
+
+		Ok(match key_prefix {
+			Some(key_prefix) => keys
+				.into_iter()
+				.filter_map(|key| key.strip_prefix(key_prefix.as_str()).map(str::to_string))
+				.collect(),
+			None => keys,
+		})
+	}
+
+	/// Stream every entry (key and value) whose key starts with `prefix`, or every entry written under the current
+	/// version and namespace if there's none. Meant for exporting the cache and admin tooling.
+	///
+	/// Keys are listed like [`Cache::keys`], and their values are then read in batches with the driver's
+	/// `get_many` (a single `MGET` on Redis, or `BatchGetItem` on `DynamoDB`). Entries that expire or are removed
+	/// before they're read are skipped, and values that can't be deserialized are yielded as errors without ending
+	/// the stream.
+	///
+	/// # Errors
+	///
+	/// Yields an error if the driver fails to list its keys (in which case it's the only item), can't list them at all
+	/// (like Memcached), or fails to read or deserialize a value.
+	#[cfg(feature = "stream")]
+	pub fn scan<'a, T: DeserializeOwned + Send + 'a>(
+		&'a self,
+		prefix: Option<&'a str>,
+	) -> impl futures_util::Stream<Item = Result<(String, T), D::Error>> + 'a {
+		use futures_util::TryStreamExt;
+
+		let key_prefix = self.key_prefix();
+
+		stream::once(async move {
+			let prefix = prefix.map_or_else(
+				|| key_prefix.as_deref().map(Cow::Borrowed),
+				|prefix| Some(self.key(prefix)),
+			);
+
+			self.driver.keys(prefix.as_deref()).await
+		})
+		.map_ok(move |keys| {
+			let batches = keys
+				.chunks(SCAN_BATCH_SIZE)
+				.map(<[String]>::to_vec)
+				.collect::<Vec<_>>();
+
+			stream::iter(batches)
+				.then(move |batch| self.scan_batch(batch))
+				.flat_map(stream::iter)
+		})
+		.try_flatten()
+	}
+
+	/// Read a batch of (stored) keys for [`Cache::scan`], returning the entries that still exist.
+	#[cfg(feature = "stream")]
+	async fn scan_batch<T: DeserializeOwned + Send>(
+		&self,
+		keys: Vec<String>,
+	) -> Vec<Result<(String, T), D::Error>> {
+		let refs = keys.iter().map(String::as_str).collect::<Vec<_>>();
+
+		let values = if let Ok(values) = self.driver.get_many::<T>(&refs).await {
+			values.into_iter().map(Ok).collect::<Vec<_>>()
+		} else {
+			// A single value that can't be deserialized fails the whole batch, so read them one by one to find it.
+			let mut values = Vec::with_capacity(refs.len());
+			for key in &refs {
+				values.push(self.driver.get::<T>(key).await);
+			}
+
+			values
+		};
+
+		let key_prefix = self.key_prefix().unwrap_or_default();
+
+		keys.into_iter()
+			.zip(values)
+			.filter_map(|(key, value)| match value {
+				Ok(value) => value.map(|value| {
+					let key = key.strip_prefix(key_prefix.as_str()).unwrap_or(&key);
+
+					Ok((key.to_string(), value))
+				}),
+				Err(error) => Some(Err(error)),
+			})
+			.collect()
+	}
+
+	/// Read every entry written under the current version and namespace, with its stored value and how long it has left
+	/// (or `None` if it never expires), so it can be copied into another cache (even on another driver) with
+	/// [`Cache::restore`].
+	///
+	/// Keys are listed like [`Cache::keys`], and values are returned as the driver stores them (serialized with its
+	/// format), so they can only be restored into a driver using the same format. Entries that expire or are removed
+	/// while they're being read are left out.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to list its keys, can't list them at all (like Memcached), or fails to read
+	/// an entry.
+	#[cfg(feature = "format")]
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub async fn dump(&self) -> Result<Vec<DumpedEntry>, D::Error> {
+		let key_prefix = self.key_prefix().unwrap_or_default();
+		let keys = self.keys(None).await?;
+
+		let mut entries = Vec::with_capacity(keys.len());
+		for batch in keys.chunks(SCAN_BATCH_SIZE) {
+			let stored = batch
+				.iter()
+				.map(|key| format!("{key_prefix}{key}"))
+				.collect::<Vec<_>>();
+			let refs = stored.iter().map(String::as_str).collect::<Vec<_>>();
+			let values = self.driver.get_many::<Raw>(&refs).await?;
+
+			for ((key, stored), value) in batch.iter().zip(&stored).zip(values) {
+				let Some(Raw(data)) = value else {
+					continue;
+				};
+
+				let expiry = match self.driver.ttl(stored).await? {
+					Some(Ttl::Forever) => None,
+					Some(Ttl::In(remaining)) => Some(remaining),
+					None => continue,
+				};
+
+				entries.push((key.clone(), data, expiry));
+			}
+		}
+
+		Ok(entries)
+	}
+
+	/// Store entries read with [`Cache::dump`] under the current version and namespace, each expiring after the time it
+	/// had left when it was dumped. Entries sharing an expiry are stored together with the driver's `put_many`.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to store the entries.
+	#[cfg(feature = "format")]
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(entries = entries.len())))]
+	pub async fn restore(&mut self, entries: Vec<DumpedEntry>) -> Result<(), D::Error> {
+		let key_prefix = self.key_prefix().unwrap_or_default();
+
+		let mut batches = HashMap::<Option<Duration>, Vec<(String, Raw)>>::new();
+		for (key, data, expiry) in entries {
+			batches
+				.entry(expiry)
+				.or_default()
+				.push((format!("{key_prefix}{key}"), Raw(data)));
+		}
+
+		for (expiry, entries) in batches {
+			let batch = entries
+				.iter()
+				.map(|(key, value)| (key.as_str(), value))
+				.collect::<Vec<_>>();
+
+			self.driver.put_many(&batch, expiry).await?;
+			self.record(|metrics| metrics.write(batch.len()));
+		}
+
+		Ok(())
+	}
+
+	/// Store an item in the cache for a given duration, serialized with `format` instead of the driver's own, for types
+	/// that only work with a particular format (like JSON for structs using `#[serde(flatten)]`). Read it back with
+	/// [`Cache::get_with_format`].
+	///
+	/// The serialized value is stored as-is, prefixed with a byte saying which format it's in, so it can only be read
+	/// with [`Cache::get_with_format`].
+	///
+	/// # Errors
+	///
+	/// Returns an error if the item can't be serialized with `format`, or if the driver fails to store it.
+	#[cfg(feature = "format")]
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
+	pub async fn put_with_format<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		format: Format,
+		expiry: impl Into<Expiry>,
+	) -> Result<(), FormatError<D::Error>> {
+		let mut data = vec![format.marker()];
+		data.extend(format.serialize(value)?);
+
+		self.put(key, &Raw(data), expiry)
+			.await
+			.map_err(FormatError::Cache)
+	}
+
+	/// Retrieve an item stored with [`Cache::put_with_format`], deserializing it with the format it was stored in.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve the item, if it wasn't stored with [`Cache::put_with_format`],
+	/// or if it can't be deserialized.
+	#[cfg(feature = "format")]
+	pub async fn get_with_format<T: DeserializeOwned>(
+		&self,
+		key: &str,
+	) -> Result<Option<T>, FormatError<D::Error>> {
+		let Some(Raw(data)) = self.get::<Raw>(key).await.map_err(FormatError::Cache)? else {
+			return Ok(None);
+		};
+
+		let (&marker, data) = data.split_first().ok_or(FormatError::UnknownFormat(None))?;
+		let format = Format::from_marker(marker).ok_or(FormatError::UnknownFormat(Some(marker)))?;
+
+		Ok(Some(format.deserialize(data)?))
+	}
+
+	/// Stream a blob stored with [`Cache::put_stream`] out of the cache.
+	/// The file and S3 drivers read it in chunks as it's consumed, while other drivers load the whole blob first.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve the blob. Failures while reading it are returned by the reader.
+	#[cfg(feature = "stream")]
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key, hit = tracing::field::Empty)))]
+	pub async fn get_stream(
+		&self,
+		key: &str,
+	) -> Result<Option<impl tokio::io::AsyncRead + Send + Unpin>, D::Error> {
+		let reader = self.driver.get_stream(&self.key(key)).await?;
+		trace::record_hit(reader.is_some());
+		self.record(|metrics| metrics.lookup(reader.is_some()));
+
+		Ok(reader)
+	}
+
+	/// Store a blob read from `reader` in the cache, without serializing it, so it can be streamed back out with
+	/// [`Cache::get_stream`]. The file and S3 drivers write it in chunks as it's read (S3 with a multipart upload for
+	/// large blobs), while other drivers read the whole blob first.
+	///
+	/// # Errors
+	///
+	/// Returns an error if reading the blob fails, or if the driver fails to store it.
+	#[cfg(feature = "stream")]
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
+	pub async fn put_stream<R: tokio::io::AsyncRead + Send + Unpin>(
+		&mut self,
+		key: &str,
+		reader: R,
+		expiry: impl Into<Expiry>,
+	) -> Result<(), StreamError<D::Error>> {
+		let key = self.key(key);
+
+		match expiry.into().resolve(self.default_expiry) {
+			Some(Duration::ZERO) => {
+				self.driver.forget(&key).await.map_err(StreamError::Cache)?;
+				self.record(|metrics| metrics.delete(1));
+			},
+			expiry => {
+				self.driver.put_stream(&key, reader, expiry).await?;
+				self.record(|metrics| metrics.write(1));
+			},
+		}
+
+		Ok(())
 	}
 
 	/// Retrieve an item from the cache, or store it for some time if it doesn't exist yet.
@@ -48,16 +715,17 @@ impl<D: Driver> Cache<D> {
 	/// # Errors
 	///
 	/// Returns an error if the driver fails to retrieve or store the item.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
 	pub async fn remember<T: Serialize + DeserializeOwned + Send + Sync>(
 		&mut self,
 		key: &str,
-		duration: Duration,
+		expiry: impl Into<Expiry>,
 		value: T,
 	) -> Result<T, D::Error> {
-		let value = if let Some(value) = self.driver.get::<T>(key).await? {
+		let value = if let Some(value) = self.get::<T>(key).await? {
 			value
 		} else {
-			self.put(key, &value, duration).await?;
+			self.put(key, &value, expiry).await?;
 
 			value
 		};
@@ -65,17 +733,70 @@ impl<D: Driver> Cache<D> {
 		Ok(value)
 	}
 
+	/// Retrieve many items from the cache, computing the missing ones in a single call to the loader.
+	/// The loader receives the keys that weren't cached and returns the values it could compute, which are stored
+	/// for the given duration. The result is aligned with `keys`, with `None` for keys the loader didn't return.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve or store the items.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(keys = ?keys)))]
+	pub async fn get_many_or_compute<T, F, Fut>(
+		&mut self,
+		keys: &[&str],
+		expiry: Duration,
+		loader: F,
+	) -> Result<Vec<Option<T>>, D::Error>
+	where
+		T: Serialize + DeserializeOwned + Clone + Send + Sync,
+		F: FnOnce(Vec<String>) -> Fut,
+		Fut: Future<Output = Vec<(String, T)>>,
+	{
+		let mut values = self.get_many::<T>(keys).await?;
+
+		let mut missing = Vec::new();
+		for (key, value) in keys.iter().zip(&values) {
+			if value.is_none() && !missing.iter().any(|missing| missing == key) {
+				missing.push((*key).to_string());
+			}
+		}
+
+		if missing.is_empty() {
+			return Ok(values);
+		}
+
+		let computed = loader(missing).await;
+		let entries = computed
+			.iter()
+			.map(|(key, value)| (key.as_str(), value))
+			.collect::<Vec<_>>();
+		self.put_many(&entries, expiry).await?;
+
+		for (key, value) in computed {
+			for (slot, _) in values
+				.iter_mut()
+				.zip(keys)
+				.filter(|(slot, slot_key)| slot.is_none() && **slot_key == key)
+			{
+				*slot = Some(value.clone());
+			}
+		}
+
+		Ok(values)
+	}
+
 	/// Retrieve an item from the cache, or store it forever if it doesn't exist yet.
 	///
 	/// # Errors
 	///
 	/// Returns an error if the driver fails to retrieve or store the item.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
 	pub async fn remember_forever<T: Serialize + DeserializeOwned + Send + Sync>(
 		&mut self,
 		key: &str,
 		value: T,
 	) -> Result<T, D::Error> {
-		let value = if let Some(value) = self.driver.get::<T>(key).await? {
+		let value = if let Some(value) = self.get::<T>(key).await? {
 			value
 		} else {
 			self.forever(key, &value).await?;
@@ -86,11 +807,133 @@ impl<D: Driver> Cache<D> {
 		Ok(value)
 	}
 
+	/// Retrieve an item from the cache, or compute and store it for some time if it doesn't exist yet.
+	/// Unlike [`Cache::remember`], the value is only computed on a miss.
+	///
+	/// # Errors
+	///
+	/// Returns [`RememberError::Cache`] if the driver fails to retrieve or store the item, or
+	/// [`RememberError::Producer`] if the loader fails (in which case nothing is stored).
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
+	pub async fn remember_with<T, E, F, Fut>(
+		&mut self,
+		key: &str,
+		expiry: impl Into<Expiry>,
+		loader: F,
+	) -> Result<T, RememberError<D::Error, E>>
+	where
+		T: Serialize + DeserializeOwned + Send + Sync,
+		F: FnOnce() -> Fut,
+		Fut: Future<Output = Result<T, E>>,
+	{
+		if let Some(value) = self.get::<T>(key).await.map_err(RememberError::Cache)? {
+			return Ok(value);
+		}
+
+		let _flight = if let Some(single_flight) = &self.single_flight {
+			let flight = single_flight.begin(&self.key(key)).await;
+
+			// Another caller may have stored the value while we were waiting for it to finish.
+			if let Some(value) = self.get::<T>(key).await.map_err(RememberError::Cache)? {
+				return Ok(value);
+			}
+
+			Some(flight)
+		} else {
+			None
+		};
+
+		let value = loader().await.map_err(RememberError::Producer)?;
+		self.put(key, &value, expiry)
+			.await
+			.map_err(RememberError::Cache)?;
+
+		Ok(value)
+	}
+
+	/// Retrieve an item from the cache, or compute and store it forever if it doesn't exist yet.
+	/// Unlike [`Cache::remember_forever`], the value is only computed on a miss.
+	///
+	/// # Errors
+	///
+	/// Returns [`RememberError::Cache`] if the driver fails to retrieve or store the item, or
+	/// [`RememberError::Producer`] if the loader fails (in which case nothing is stored).
+	pub async fn remember_forever_with<T, E, F, Fut>(
+		&mut self,
+		key: &str,
+		loader: F,
+	) -> Result<T, RememberError<D::Error, E>>
+	where
+		T: Serialize + DeserializeOwned + Send + Sync,
+		F: FnOnce() -> Fut,
+		Fut: Future<Output = Result<T, E>>,
+	{
+		self.remember_with(key, Expiry::Forever, loader).await
+	}
+
+	/// Retrieve an item from the cache, or compute and store it for some time if it doesn't exist yet.
+	/// The loader receives the previous value (even if it has expired) so it can update it instead of starting over.
+	///
+	/// To make this possible, a copy of the value is kept under a separate key in the reserved `__previous:` namespace
+	/// until the item has been expired for the cache's [stale window](Cache::with_stale_window). The copy is only
+	/// replaced by the next call, so it outlives [`Cache::forget`] (which only removes the item) until it expires.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve or store the item.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
+	pub async fn remember_with_previous<T, F, Fut>(
+		&mut self,
+		key: &str,
+		expiry: Duration,
+		loader: F,
+	) -> Result<T, D::Error>
+	where
+		T: Serialize + DeserializeOwned + Send + Sync,
+		F: FnOnce(Option<T>) -> Fut,
+		Fut: Future<Output = T>,
+	{
+		if let Some(value) = self.get::<T>(key).await? {
+			return Ok(value);
+		}
+
+		let previous_key = format!("{PREVIOUS_NAMESPACE}{}", self.key(key));
+		let previous = self.driver.get::<T>(&previous_key).await?;
+
+		let value = loader(previous).await;
+		self.put(key, &value, expiry).await?;
+		self.driver
+			.put(
+				&previous_key,
+				&value,
+				Some(expiry.saturating_add(self.stale_window)),
+			)
+			.await?;
+
+		Ok(value)
+	}
+
+	/// Retrieve an item from the cache, or store it for the given number of milliseconds if it doesn't exist yet.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve or store the item.
+	pub async fn remember_millis<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		millis: u64,
+		value: T,
+	) -> Result<T, D::Error> {
+		self.remember(key, Duration::from_millis(millis), value)
+			.await
+	}
+
 	/// Remove an item from the cache and return it.
 	///
 	/// # Errors
 	///
 	/// Returns an error if the driver fails to retrieve or remove the item.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
 	pub async fn pull<T: DeserializeOwned + Send>(
 		&mut self,
 		key: &str,
@@ -105,37 +948,249 @@ impl<D: Driver> Cache<D> {
 	}
 
 	/// Store an item in the cache for a given duration.
+	/// Storing an item with an expiry that has already passed removes it from the cache instead.
 	///
 	/// # Errors
 	///
 	/// Returns an error if the driver fails to store the item.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
 	pub async fn put<T: Serialize + Sync>(
 		&mut self,
 		key: &str,
 		value: &T,
-		expiry: Duration,
+		expiry: impl Into<Expiry>,
 	) -> Result<(), D::Error> {
-		self.driver.put(key, value, Some(expiry)).await
+		let key = self.key(key);
+
+		match expiry.into().resolve(self.default_expiry) {
+			Some(Duration::ZERO) => {
+				self.driver.forget(&key).await?;
+				self.record(|metrics| metrics.delete(1));
+			},
+			expiry => {
+				self.driver.put(&key, value, expiry).await?;
+				self.record(|metrics| metrics.write(1));
+			},
+		}
+
+		Ok(())
 	}
 
-	/// Store an item in the cache if it doesn't exist yet.
+	/// Store an item in the cache for `base` plus a random offset of up to `jitter`.
+	/// Spreading out the expiries of items stored at the same time (like when warming the cache) keeps them from all
+	/// expiring, and being recomputed, at once.
 	///
 	/// # Errors
 	///
 	/// Returns an error if the driver fails to store the item.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
+	pub async fn put_jittered<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		base: Duration,
+		jitter: Duration,
+	) -> Result<(), D::Error> {
+		let expiry = base + self.jitter_source.jitter(jitter);
+
+		self.put(key, value, expiry).await
+	}
+
+	/// Store an item in the cache for a given duration, returning the one it replaced.
+	/// Drivers that support it (like Redis and `DynamoDB`) swap the values in a single atomic operation.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to store the item, or to retrieve the previous one.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
+	pub async fn replace<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: impl Into<Expiry>,
+	) -> Result<Option<T>, D::Error> {
+		match expiry.into().resolve(self.default_expiry) {
+			// Like `put`, storing an item that expires right away removes it instead.
+			Some(Duration::ZERO) => self.pull(key).await,
+			expiry => {
+				let key = self.key(key);
+				let previous = self.driver.replace(&key, value, expiry).await?;
+				self.record(|metrics| metrics.write(1));
+
+				Ok(previous)
+			},
+		}
+	}
+
+	/// Store many items in the cache at once, all for the same duration.
+	/// Drivers that support it (like Redis and `DynamoDB`) store them in a single round trip.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to store the items.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(entries = entries.len())))]
+	pub async fn put_many<T: Serialize + Sync>(
+		&mut self,
+		entries: &[(&str, T)],
+		expiry: impl Into<Expiry>,
+	) -> Result<(), D::Error> {
+		let keys = entries
+			.iter()
+			.map(|(key, _)| self.key(key))
+			.collect::<Vec<_>>();
+
+		match expiry.into().resolve(self.default_expiry) {
+			Some(Duration::ZERO) => {
+				for key in &keys {
+					self.driver.forget(key).await?;
+				}
+				self.record(|metrics| metrics.delete(keys.len()));
+
+				Ok(())
+			},
+			expiry => {
+				let entries = keys
+					.iter()
+					.zip(entries)
+					.map(|(key, (_, value))| (key.as_ref(), value))
+					.collect::<Vec<_>>();
+
+				self.driver.put_many(&entries, expiry).await?;
+				self.record(|metrics| metrics.write(entries.len()));
+
+				Ok(())
+			},
+		}
+	}
+
+	/// Retrieve an item from the cache, or store the given default if it doesn't exist yet.
+	/// Unlike checking and storing separately, this is atomic on drivers that support it (like Redis).
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve or store the item.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
+	pub async fn get_or_put<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		default: T,
+		expiry: impl Into<Expiry>,
+	) -> Result<T, D::Error> {
+		let key = self.key(key);
+
+		match expiry.into().resolve(self.default_expiry) {
+			Some(Duration::ZERO) => Ok(self.driver.get(&key).await?.unwrap_or(default)),
+			expiry => self.driver.get_or_put(&key, default, expiry).await,
+		}
+	}
+
+	/// Store an item in the cache for the given number of milliseconds.
+	/// Every driver preserves millisecond precision, so sub-second expiries behave the same across backends.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to store the item.
+	pub async fn put_millis<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		millis: u64,
+	) -> Result<(), D::Error> {
+		self.put(key, value, Duration::from_millis(millis)).await
+	}
+
+	/// Store an item in the cache until the given point in time (for example, until the token it was derived from
+	/// expires). Drivers that store absolute expirations (like Redis, `DynamoDB` and the memory driver) keep the exact
+	/// deadline, instead of a duration computed from it.
+	///
+	/// Storing an item with a deadline that has already passed removes it from the cache instead.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to store the item.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
+	pub async fn put_until<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		deadline: SystemTime,
+	) -> Result<(), D::Error> {
+		let key = self.key(key);
+
+		if deadline <= SystemTime::now() {
+			self.driver.forget(&key).await?;
+			self.record(|metrics| metrics.delete(1));
+		} else {
+			self.driver.put_until(&key, value, deadline).await?;
+			self.record(|metrics| metrics.write(1));
+		}
+
+		Ok(())
+	}
+
+	/// Store an item in the cache if it doesn't exist yet, returning whether it was stored.
+	/// On drivers that can check and store atomically (like Redis and `DynamoDB`), only one of several concurrent
+	/// callers adding the same key gets `true`.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to store the item.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
 	pub async fn add<T: Serialize + Send + Sync>(
 		&mut self,
 		key: &str,
 		value: T,
-		expiry: Duration,
+		expiry: impl Into<Expiry>,
 	) -> Result<bool, D::Error> {
-		if self.has(key).await? {
-			return Ok(false);
+		let key = self.key(key);
+
+		match expiry.into().resolve(self.default_expiry) {
+			// An item that expires right away is never stored.
+			Some(Duration::ZERO) => Ok(false),
+			expiry => {
+				let added = self.driver.add(&key, &value, expiry).await?;
+				if added {
+					self.record(|metrics| metrics.write(1));
+				}
+
+				Ok(added)
+			},
 		}
+	}
 
-		self.put(key, &value, expiry).await?;
+	/// Store an item in the cache only if it's currently equal to `expected`, returning whether it was stored.
+	/// Useful for read-modify-write updates without a lock: read the item, compute the new one, and retry if this
+	/// returns `false` because someone else changed it in the meantime. This is atomic on drivers that support it (like
+	/// Redis and `DynamoDB`), which compare the serialized values instead of using `PartialEq`.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve or store the item.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
+	pub async fn compare_and_swap<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+		new: &T,
+		expiry: impl Into<Expiry>,
+	) -> Result<bool, D::Error> {
+		match expiry.into().resolve(self.default_expiry) {
+			// Like `put`, swapping in an item that expires right away removes it instead.
+			Some(Duration::ZERO) => self.forget_if(key, expected).await,
+			expiry => {
+				let key = self.key(key);
 
-		Ok(true)
+				let swapped = self
+					.driver
+					.compare_and_swap(&key, expected, new, expiry)
+					.await?;
+				if swapped {
+					self.record(|metrics| metrics.write(1));
+				}
+
+				Ok(swapped)
+			},
+		}
 	}
 
 	/// Store an item in the cache indefinitely.
@@ -148,24 +1203,310 @@ impl<D: Driver> Cache<D> {
 		key: &str,
 		value: T,
 	) -> Result<(), D::Error> {
-		self.driver.put(key, &value, None).await
+		self.put(key, &value, Expiry::Forever).await
+	}
+
+	/// Load many items concurrently and store them in the cache, with at most `concurrency` loaders running at once.
+	/// Once every loader has finished, items sharing an expiry are stored with [`Cache::put_many`]. Loaders that fail are
+	/// skipped and reported in the returned summary, alongside the keys that were stored.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to store one of the loaded items.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(entries = entries.len(), concurrency = concurrency)))]
+	pub async fn warm<T, E, F, Fut>(
+		&mut self,
+		entries: Vec<(String, Duration, F)>,
+		concurrency: usize,
+	) -> Result<WarmSummary<E>, D::Error>
+	where
+		T: Serialize + Sync,
+		F: FnOnce() -> Fut,
+		Fut: Future<Output = Result<T, E>>,
+	{
+		let results = stream::iter(entries)
+			.map(|(key, expiry, loader)| async move { (key, expiry, loader().await) })
+			.buffer_unordered(concurrency.max(1))
+			.collect::<Vec<_>>()
+			.await;
+
+		let mut summary = WarmSummary::default();
+		let mut loaded = HashMap::<Duration, Vec<(String, T)>>::new();
+		for (key, expiry, result) in results {
+			match result {
+				Ok(value) => loaded.entry(expiry).or_default().push((key, value)),
+				Err(error) => summary.failed.push((key, error)),
+			}
+		}
+
+		// Items sharing an expiry are stored together, in a single round trip on drivers that support it.
+		for (expiry, entries) in loaded {
+			let batch = entries
+				.iter()
+				.map(|(key, value)| (key.as_str(), value))
+				.collect::<Vec<_>>();
+
+			self.put_many(&batch, expiry).await?;
+			summary
+				.loaded
+				.extend(entries.into_iter().map(|(key, _)| key));
+		}
+
+		Ok(summary)
 	}
 
-	/// Remove an item from the cache.
+	/// Remove an item from the cache, returning whether it existed.
 	///
 	/// # Errors
 	///
 	/// Returns an error if the driver fails to remove the item.
-	pub async fn forget(&mut self, key: &str) -> Result<(), D::Error> {
-		self.driver.forget(key).await
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
+	pub async fn forget(&mut self, key: &str) -> Result<bool, D::Error> {
+		let key = self.key(key);
+		let existed = self.driver.forget(&key).await?;
+		self.record(|metrics| metrics.delete(1));
+
+		Ok(existed)
+	}
+
+	/// Remove many items from the cache at once.
+	/// Drivers that support it (like Redis and `DynamoDB`) remove them in a single round trip.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to remove the items.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(keys = ?keys)))]
+	pub async fn forget_many(&mut self, keys: &[&str]) -> Result<(), D::Error> {
+		let keys = keys.iter().map(|key| self.key(key)).collect::<Vec<_>>();
+		let keys = keys.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+
+		self.driver.forget_many(&keys).await?;
+		self.record(|metrics| metrics.delete(keys.len()));
+
+		Ok(())
+	}
+
+	/// Remove every item whose key starts with `prefix` (like every `user:123:` key for a user).
+	///
+	/// The prefix is normalized and encoded like any other key, so this won't find anything with key encoders that
+	/// don't keep prefixes intact (like ones that hash keys). Most drivers have to look at every entry to find the
+	/// matching ones, making this O(n) in the size of the cache, and it isn't supported by Memcached at all.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to remove the items, or can't remove items by prefix.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(prefix = prefix)))]
+	pub async fn forget_prefix(&mut self, prefix: &str) -> Result<(), D::Error> {
+		let prefix = self.key(prefix);
+
+		self.driver.forget_prefix(&prefix).await
+	}
+
+	/// Change when an item expires without rewriting it, returning whether it exists.
+	/// Useful for sliding expirations, like keeping a session alive while it's being used.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to update the item.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
+	pub async fn touch(&mut self, key: &str, expiry: impl Into<Expiry>) -> Result<bool, D::Error> {
+		let key = self.key(key);
+
+		match expiry.into().resolve(self.default_expiry) {
+			Some(Duration::ZERO) => self.driver.forget(&key).await,
+			expiry => self.driver.touch(&key, expiry).await,
+		}
+	}
+
+	/// Remove an item from the cache only if it's currently equal to `expected`, returning whether it was removed.
+	/// Useful for releasing a lock only if you still hold it. This is atomic on drivers that support it (like Redis),
+	/// which compare the serialized values instead of using `PartialEq`.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve or remove the item.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key)))]
+	pub async fn forget_if<T: PartialEq + DeserializeOwned + Serialize + Sync>(
+		&mut self,
+		key: &str,
+		expected: &T,
+	) -> Result<bool, D::Error> {
+		let key = self.key(key);
+
+		let removed = self.driver.forget_if(&key, expected).await?;
+		if removed {
+			self.record(|metrics| metrics.delete(1));
+		}
+
+		Ok(removed)
+	}
+
+	/// Atomically increment a numeric item in the cache, returning its new value.
+	/// Items that don't exist yet start at zero, and are stored forever.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to update the item, if it isn't numeric, or if the result would overflow.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(key = key, by = by)))]
+	pub async fn increment(&mut self, key: &str, by: i64) -> Result<i64, D::Error> {
+		self.driver.increment(&self.key(key), by, None).await
+	}
+
+	/// Atomically decrement a numeric item in the cache, returning its new value.
+	/// Items that don't exist yet start at zero, and are stored forever.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to update the item, if it isn't numeric, or if the result would overflow.
+	pub async fn decrement(&mut self, key: &str, by: i64) -> Result<i64, D::Error> {
+		let by = by.checked_neg().ok_or(Overflow)?;
+
+		self.driver.increment(&self.key(key), by, None).await
+	}
+
+	/// Retrieve a handle to the counter with the given name.
+	pub fn counter(&mut self, name: &str) -> Counter<'_, D> {
+		let key = self.key(&Counter::<D>::key(name)).into_owned();
+
+		Counter::new(&mut self.driver, key)
+	}
+
+	/// Create a view of the cache whose items belong to the given tags, so they can be flushed together.
+	/// Items stored through it can only be read back through the same set of tags.
+	pub fn tags(&mut self, tags: &[&str]) -> TaggedCache<'_, D> {
+		TaggedCache::new(self, tags)
+	}
+
+	/// Store an item under the given tags, without going through [`Cache::tags`]. Flushing any of the tags (with
+	/// [`Cache::flush_tags`]) removes it.
+	///
+	/// Items are stored per set of tags, so storing a key again with different tags stores a separate item. It can only
+	/// be read back through the new set of tags, and the one stored under the old set stays around until it expires
+	/// (or one of its tags is flushed).
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to store the item.
+	pub async fn put_tagged<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: impl Into<Expiry>,
+		tags: &[&str],
+	) -> Result<(), D::Error> {
+		self.tags(tags).put(key, value, expiry).await
+	}
+
+	/// Remove every item stored under any of the given tags, like flushing them through [`Cache::tags`].
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to remove the items.
+	pub async fn flush_tags(&mut self, tags: &[&str]) -> Result<(), D::Error> {
+		self.tags(tags).flush().await
+	}
+
+	/// Remove every expired item from the cache, returning how many were removed.
+	/// Drivers that expire items on their own (like Redis) have nothing to remove, and always return zero.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to remove the items.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub async fn purge_expired(&mut self) -> Result<usize, D::Error> {
+		self.driver.purge_expired().await
 	}
 
 	/// Remove all items from the cache.
+	/// On a view created with [`Cache::namespace`], only the items in the namespace are removed, which (like
+	/// [`Cache::forget_prefix`]) isn't supported by drivers that can't list their keys.
 	///
 	/// # Errors
 	///
 	/// Returns an error if the driver fails to flush the cache.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 	pub async fn flush(&mut self) -> Result<(), D::Error> {
-		self.driver.flush().await
+		if self.namespace.is_empty() {
+			return self.driver.flush().await;
+		}
+
+		let prefix = self.key("");
+		self.driver.forget_prefix(&prefix).await
+	}
+
+	fn key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+		let key = self
+			.key_normalization
+			.iter()
+			.fold(Cow::Borrowed(key), |key, normalization| {
+				normalization.apply(key)
+			});
+
+		let key = match key {
+			Cow::Borrowed(key) => self.key_encoder.encode(key),
+			Cow::Owned(key) => Cow::Owned(self.key_encoder.encode(&key).into_owned()),
+		};
+
+		if self.version_prefix.is_none() && self.namespace.is_empty() {
+			return key;
+		}
+
+		Cow::Owned(format!(
+			"{}{}{key}",
+			self.version_prefix.as_deref().unwrap_or_default(),
+			self.namespace
+		))
+	}
+
+	/// The version prefix and namespace prepended to every (encoded) key, if there's any.
+	fn key_prefix(&self) -> Option<String> {
+		if self.version_prefix.is_none() && self.namespace.is_empty() {
+			return None;
+		}
+
+		Some(format!(
+			"{}{}",
+			self.version_prefix.as_deref().unwrap_or_default(),
+			self.namespace
+		))
+	}
+
+	/// Update the metrics, if they're enabled.
+	fn record(&self, update: impl FnOnce(&Metrics)) {
+		if let Some(metrics) = &self.metrics {
+			update(metrics);
+		}
+	}
+}
+
+impl<D: Driver + Clone> Cache<D> {
+	/// Create a handle to a lock with the given name, which expires after `ttl` unless it's released earlier.
+	/// The handle owns a clone of the cache, so it can be held across tasks.
+	pub fn lock(&self, name: &str, ttl: Duration) -> Lock<D>
+	where
+		D: 'static,
+	{
+		Lock::new(self.clone(), name, ttl)
+	}
+
+	/// Create a view of the cache that prepends `prefix:` to every key, so a subsystem can get its own namespace
+	/// without a second connection. Namespaces nest, so `cache.namespace("a").namespace("b")` stores keys under
+	/// `a:b:`, and items stored in a namespace can only be read through it.
+	///
+	/// Unlike [`Cache::with_prefix`], the view owns a clone of the cache instead of borrowing it.
+	#[must_use]
+	pub fn namespace(&self, prefix: &str) -> Self {
+		let mut cache = self.clone();
+		cache.namespace = format!("{}{prefix}:", self.namespace);
+
+		cache
+	}
+}
+
+impl<D: FlushWhere> Cache<D> {
+	/// Remove every item whose key matches the predicate, returning how many were removed.
+	/// Keys are matched as stored by the driver, after normalization and encoding.
+	pub fn flush_where(&mut self, predicate: impl Fn(&str) -> bool) -> usize {
+		self.driver.flush_where(predicate)
 	}
 }
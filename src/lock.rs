@@ -0,0 +1,131 @@
+use crate::{drivers::Driver, Cache};
+use futures_util::FutureExt;
+use std::{collections::hash_map::RandomState, future::Future, hash::BuildHasher, time::Duration};
+
+/// A lock shared by everything using the same cache, obtained through [`Cache::lock`].
+///
+/// The lock is stored as a cache item holding a random token, so only the handle that acquired it can release it. It
+/// expires after its TTL even if it's never released, so a crashed holder can't keep it forever. Acquiring it is only
+/// atomic on drivers that store missing values atomically (like Redis and the in-memory drivers).
+///
+/// Dropping a handle that holds the lock releases it in a background task when it's dropped on a Tokio runtime (with
+/// the `tokio` feature, which every driver doing I/O enables). Otherwise, it's only released if the driver can do so
+/// without waiting on I/O (like the in-memory drivers), and is held until its TTL runs out if not.
+#[derive(Debug)]
+pub struct Lock<D: Driver> {
+	key: String,
+	ttl: Duration,
+	token: String,
+	acquired: bool,
+	cache: Cache<D>,
+	/// Releases the lock from a background task, where the driver's bounds allow spawning one.
+	#[cfg(feature = "tokio")]
+	spawn_release: fn(&Cache<D>, String, String),
+}
+
+impl<D: Driver> Lock<D> {
+	pub(crate) fn new(cache: Cache<D>, name: &str, ttl: Duration) -> Self
+	where
+		D: Clone + 'static,
+	{
+		// Seeded randomly, so the token tells this handle apart from every other one.
+		let state = RandomState::new();
+
+		Self {
+			ttl,
+			cache,
+			acquired: false,
+			#[cfg(feature = "tokio")]
+			spawn_release: spawn_release::<D>,
+			key: format!("lock:{name}"),
+			token: format!("{:016x}{:016x}", state.hash_one(0_u8), state.hash_one(1_u8)),
+		}
+	}
+
+	/// Whether this handle currently holds the lock (as far as it knows, since the lock expires after its TTL).
+	pub const fn is_acquired(&self) -> bool {
+		self.acquired
+	}
+
+	/// Try to acquire the lock, returning whether it was acquired.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to read or store the lock.
+	pub async fn try_acquire(&mut self) -> Result<bool, D::Error> {
+		let owner = self
+			.cache
+			.get_or_put(&self.key, self.token.clone(), self.ttl)
+			.await?;
+
+		self.acquired = owner == self.token;
+
+		Ok(self.acquired)
+	}
+
+	/// Acquire the lock, calling `wait` between attempts until it's free.
+	/// The crate doesn't depend on an async runtime, so `wait` is usually something like
+	/// `|| tokio::time::sleep(Duration::from_millis(100))`. Wrap the call in a timeout to give up eventually.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to read or store the lock.
+	pub async fn acquire<F, Fut>(&mut self, mut wait: F) -> Result<(), D::Error>
+	where
+		F: FnMut() -> Fut,
+		Fut: Future<Output = ()>,
+	{
+		while !self.try_acquire().await? {
+			wait().await;
+		}
+
+		Ok(())
+	}
+
+	/// Release the lock if this handle holds it, returning whether it was released.
+	/// Returns `false` if the lock had already expired (and possibly been acquired by someone else).
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to remove the lock.
+	pub async fn release(&mut self) -> Result<bool, D::Error> {
+		if !self.acquired {
+			return Ok(false);
+		}
+
+		let released = self.cache.forget_if(&self.key, &self.token).await?;
+		self.acquired = false;
+
+		Ok(released)
+	}
+}
+
+impl<D: Driver> Drop for Lock<D> {
+	fn drop(&mut self) {
+		if !self.acquired {
+			return;
+		}
+
+		// Releasing can't be awaited here, so it's handed to the runtime. Polling it once first could cancel a request
+		// halfway through, which can leave the driver's connection out of sync.
+		#[cfg(feature = "tokio")]
+		if tokio::runtime::Handle::try_current().is_ok() {
+			(self.spawn_release)(&self.cache, self.key.clone(), self.token.clone());
+			return;
+		}
+
+		// Without a runtime, it only happens if the driver doesn't need to wait on I/O.
+		self.release().now_or_never();
+	}
+}
+
+/// Release the lock with the given key and token from a task on the current runtime.
+#[cfg(feature = "tokio")]
+fn spawn_release<D: Driver + Clone + 'static>(cache: &Cache<D>, key: String, token: String) {
+	let mut cache = cache.clone();
+
+	tokio::spawn(async move {
+		// There's no one to report a failure to, and the lock still expires after its TTL.
+		let _ = cache.forget_if(&key, &token).await;
+	});
+}
@@ -0,0 +1,11 @@
+/// The error returned by [`Cache::remember_with`](crate::Cache::remember_with), saying whether the cache or the
+/// loader failed.
+#[derive(Debug, thiserror::Error)]
+pub enum RememberError<C, P> {
+	/// The driver failed to retrieve or store the item.
+	#[error(transparent)]
+	Cache(C),
+	/// The loader failed, so nothing was stored.
+	#[error(transparent)]
+	Producer(P),
+}
@@ -0,0 +1,58 @@
+use futures_util::lock::{Mutex as AsyncMutex, OwnedMutexGuard};
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex, PoisonError},
+};
+
+/// Coalesces concurrent computations of the same key, so only one of them runs at a time.
+/// Shared between clones of a cache, so computations in different tasks are coalesced too.
+#[derive(Debug, Clone, Default)]
+pub struct SingleFlight {
+	flights: Arc<Mutex<HashMap<String, Arc<AsyncMutex<()>>>>>,
+}
+
+impl SingleFlight {
+	/// Wait for any in-flight computation of the key to finish, returning a guard that makes later ones wait on this
+	/// one until it's dropped.
+	pub async fn begin(&self, key: &str) -> Flight {
+		let flight = self
+			.flights
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.entry(key.to_string())
+			.or_default()
+			.clone();
+
+		Flight {
+			key: key.to_string(),
+			guard: Some(flight.lock_owned().await),
+			flights: self.clone(),
+		}
+	}
+}
+
+/// A computation in flight, started with [`SingleFlight::begin`].
+pub struct Flight {
+	key: String,
+	flights: SingleFlight,
+	guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl Drop for Flight {
+	fn drop(&mut self) {
+		drop(self.guard.take());
+
+		// Stop tracking the key once nobody is waiting on it, so the map doesn't grow forever.
+		let mut flights = self
+			.flights
+			.flights
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+		if flights
+			.get(&self.key)
+			.is_some_and(|flight| Arc::strong_count(flight) == 1)
+		{
+			flights.remove(&self.key);
+		}
+	}
+}
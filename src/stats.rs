@@ -0,0 +1,66 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A snapshot of a cache's hit, miss, write and delete counts, returned by [`Cache::stats`](crate::Cache::stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[allow(clippy::module_name_repetitions)]
+pub struct CacheStats {
+	/// The number of lookups (including [`Cache::has`](crate::Cache::has) checks) that found their item.
+	pub hits: u64,
+	/// The number of lookups that didn't find their item.
+	pub misses: u64,
+	/// The number of items stored.
+	pub writes: u64,
+	/// The number of items removed, counting every key that was forgotten (or stored with a zero expiry) whether or not
+	/// it existed, since drivers can't always tell when removing many keys at once. Conditional removals (like
+	/// [`Cache::forget_if`](crate::Cache::forget_if)) only count when they go through.
+	pub deletes: u64,
+}
+
+impl CacheStats {
+	/// The share of lookups that found their item, or `None` if nothing has been looked up yet.
+	#[must_use]
+	#[allow(clippy::cast_precision_loss)]
+	pub fn hit_rate(&self) -> Option<f64> {
+		let lookups = self.hits + self.misses;
+
+		(lookups > 0).then(|| self.hits as f64 / lookups as f64)
+	}
+}
+
+/// The running counters behind [`CacheStats`], shared between clones of a cache.
+#[derive(Debug, Default)]
+pub struct Metrics {
+	hits: AtomicU64,
+	misses: AtomicU64,
+	writes: AtomicU64,
+	deletes: AtomicU64,
+}
+
+impl Metrics {
+	/// Count a lookup, depending on whether it found its item.
+	pub fn lookup(&self, hit: bool) {
+		let counter = if hit { &self.hits } else { &self.misses };
+
+		counter.fetch_add(1, Ordering::Relaxed);
+	}
+
+	/// Count `count` stored items.
+	pub fn write(&self, count: usize) {
+		self.writes.fetch_add(count as u64, Ordering::Relaxed);
+	}
+
+	/// Count `count` removed items.
+	pub fn delete(&self, count: usize) {
+		self.deletes.fetch_add(count as u64, Ordering::Relaxed);
+	}
+
+	/// Take a snapshot of the counters.
+	pub fn snapshot(&self) -> CacheStats {
+		CacheStats {
+			hits: self.hits.load(Ordering::Relaxed),
+			misses: self.misses.load(Ordering::Relaxed),
+			writes: self.writes.load(Ordering::Relaxed),
+			deletes: self.deletes.load(Ordering::Relaxed),
+		}
+	}
+}
@@ -0,0 +1,183 @@
+use crate::{drivers::Driver, Cache, Expiry};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{collections::hash_map::RandomState, hash::BuildHasher};
+
+/// A view of the cache whose items belong to a set of tags, so they can be flushed together.
+///
+/// Like Laravel's tagged cache, every tag has a random ID (stored as a regular cache item), and items are stored under
+/// a namespace made from the IDs of their tags, so they can only be read back through the same set of tags (in any
+/// order). [`TaggedCache::flush`] gives each tag a new ID, which makes every item stored under it unreachable at once
+/// without having to keep track of them. Those items are left to expire (or be evicted) on their own, so items stored
+/// indefinitely keep taking up space until the cache is flushed.
+#[derive(Debug)]
+pub struct TaggedCache<'a, D: Driver> {
+	tags: Vec<String>,
+	cache: &'a mut Cache<D>,
+}
+
+impl<'a, D: Driver> TaggedCache<'a, D> {
+	pub(crate) fn new(cache: &'a mut Cache<D>, tags: &[&str]) -> Self {
+		let mut tags = tags.iter().map(ToString::to_string).collect::<Vec<_>>();
+		tags.sort_unstable();
+		tags.dedup();
+
+		Self { tags, cache }
+	}
+
+	/// The cache key an item is stored under, namespaced by the IDs of the tags.
+	fn key(ids: &[String], key: &str) -> String {
+		format!("tagged:{}:{key}", ids.join(","))
+	}
+
+	/// The cache key a tag's ID is kept in.
+	fn tag_key(tag: &str) -> String {
+		format!("tag:{tag}:id")
+	}
+
+	/// The IDs of the tags, or `None` if any of them doesn't have one yet (so nothing was stored under it).
+	async fn ids(&self) -> Result<Option<Vec<String>>, D::Error> {
+		let keys = self
+			.tags
+			.iter()
+			.map(|tag| Self::tag_key(tag))
+			.collect::<Vec<_>>();
+		let keys = keys.iter().map(String::as_str).collect::<Vec<_>>();
+
+		Ok(self.cache.get_many(&keys).await?.into_iter().collect())
+	}
+
+	/// The IDs of the tags, giving the ones that don't have one yet a new one.
+	async fn ids_or_create(&mut self) -> Result<Vec<String>, D::Error> {
+		let keys = self
+			.tags
+			.iter()
+			.map(|tag| Self::tag_key(tag))
+			.collect::<Vec<_>>();
+		let existing = {
+			let keys = keys.iter().map(String::as_str).collect::<Vec<_>>();
+			self.cache.get_many::<String>(&keys).await?
+		};
+
+		let mut ids = Vec::with_capacity(keys.len());
+		for (key, id) in keys.iter().zip(existing) {
+			// Only one of several concurrent callers gets to set a new tag's ID, so they all agree on it.
+			ids.push(match id {
+				Some(id) => id,
+				None => {
+					self.cache
+						.get_or_put(key, random_id(), Expiry::Forever)
+						.await?
+				},
+			});
+		}
+
+		Ok(ids)
+	}
+
+	/// Retrieve an item from the cache.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve the item.
+	pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, D::Error> {
+		let Some(ids) = self.ids().await? else {
+			return Ok(None);
+		};
+
+		self.cache.get(&Self::key(&ids, key)).await
+	}
+
+	/// Check if an item exists in the cache.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to check the item.
+	pub async fn has(&self, key: &str) -> Result<bool, D::Error> {
+		let Some(ids) = self.ids().await? else {
+			return Ok(false);
+		};
+
+		self.cache.has(&Self::key(&ids, key)).await
+	}
+
+	/// Store an item in the cache for some time.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to store the item.
+	pub async fn put<T: Serialize + Sync>(
+		&mut self,
+		key: &str,
+		value: &T,
+		expiry: impl Into<Expiry>,
+	) -> Result<(), D::Error> {
+		let key = Self::key(&self.ids_or_create().await?, key);
+
+		self.cache.put(&key, value, expiry).await
+	}
+
+	/// Store an item in the cache indefinitely.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to store the item.
+	pub async fn forever<T: Serialize + Send + Sync>(
+		&mut self,
+		key: &str,
+		value: T,
+	) -> Result<(), D::Error> {
+		self.put(key, &value, Expiry::Forever).await
+	}
+
+	/// Retrieve an item from the cache, or store it for some time if it doesn't exist yet.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to retrieve or store the item.
+	pub async fn remember<T: Serialize + DeserializeOwned + Send + Sync>(
+		&mut self,
+		key: &str,
+		expiry: impl Into<Expiry>,
+		value: T,
+	) -> Result<T, D::Error> {
+		if let Some(value) = self.get::<T>(key).await? {
+			return Ok(value);
+		}
+
+		self.put(key, &value, expiry).await?;
+
+		Ok(value)
+	}
+
+	/// Remove an item from the cache, returning whether it existed.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to remove the item.
+	pub async fn forget(&mut self, key: &str) -> Result<bool, D::Error> {
+		let Some(ids) = self.ids().await? else {
+			return Ok(false);
+		};
+
+		self.cache.forget(&Self::key(&ids, key)).await
+	}
+
+	/// Remove every item stored under any of the tags, including ones stored alongside other tags.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the driver fails to remove the items.
+	pub async fn flush(&mut self) -> Result<(), D::Error> {
+		for tag in &self.tags {
+			self.cache.forever(&Self::tag_key(tag), random_id()).await?;
+		}
+
+		Ok(())
+	}
+}
+
+/// A new random ID for a tag.
+fn random_id() -> String {
+	// Every `RandomState` is seeded differently, so hashing anything with a new one gives a random number.
+	format!("{:016x}", RandomState::new().hash_one(0_u8))
+}
@@ -0,0 +1,22 @@
+//! Helpers for recording the outcome of an operation on its tracing span, which compile to nothing without the
+//! `tracing` feature.
+
+/// Record whether a lookup found its item on the current span.
+#[cfg(feature = "tracing")]
+pub fn record_hit(hit: bool) {
+	tracing::Span::current().record("hit", hit);
+}
+
+/// Record whether a lookup found its item on the current span.
+#[cfg(not(feature = "tracing"))]
+pub const fn record_hit(_hit: bool) {}
+
+/// Record how many items of a batch lookup were found on the current span.
+#[cfg(feature = "tracing")]
+pub fn record_hits<T>(values: &[Option<T>]) {
+	tracing::Span::current().record("hits", values.iter().flatten().count());
+}
+
+/// Record how many items of a batch lookup were found on the current span.
+#[cfg(not(feature = "tracing"))]
+pub const fn record_hits<T>(_values: &[Option<T>]) {}
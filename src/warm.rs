@@ -0,0 +1,17 @@
+/// The outcome of warming the cache with [`Cache::warm`](crate::Cache::warm).
+#[derive(Debug)]
+pub struct WarmSummary<E> {
+	/// The keys that were loaded and stored in the cache.
+	pub loaded: Vec<String>,
+	/// The keys whose loader failed, alongside the error it returned.
+	pub failed: Vec<(String, E)>,
+}
+
+impl<E> Default for WarmSummary<E> {
+	fn default() -> Self {
+		Self {
+			loaded: Vec::new(),
+			failed: Vec::new(),
+		}
+	}
+}